@@ -0,0 +1,131 @@
+//! Encode, decode and color conversion throughput.
+//!
+//! Encoding is measured for every encoder compiled into libavif at several
+//! speeds and pixel formats. The encoded size of each configuration is
+//! printed before it is measured, so changes in compression show up next to
+//! changes in speed.
+//!
+//! Run with `cargo bench`, adding `--features codec-aom,codec-svt` to include
+//! more encoders.
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use rustavif::{
+    BitDepth, Decoder, Encoder, Image, OwnedRgbImage, PixelFormat, RgbFormat, available_codecs,
+};
+use std::hint::black_box;
+
+const WIDTH: u32 = 512;
+const HEIGHT: u32 = 512;
+const QUALITY: u8 = 60;
+const SPEEDS: [u8; 2] = [6, 10];
+const FORMATS: [PixelFormat; 3] = [
+    PixelFormat::Yuv420,
+    PixelFormat::Yuv422,
+    PixelFormat::Yuv444,
+];
+
+/// Creates a photo-like test image: smooth gradients with mild noise.
+fn test_image() -> OwnedRgbImage {
+    let mut rgb = OwnedRgbImage::new(WIDTH, HEIGHT, BitDepth::Eight, RgbFormat::Rgba).unwrap();
+    let mut seed = 0x2545_f491_u32;
+    let mut noise = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        (seed % 16) as u8
+    };
+    for (i, px) in rgb.pixels_mut().chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i as u32 % WIDTH, i as u32 / WIDTH);
+        px[0] = (x * 224 / WIDTH) as u8 + noise();
+        px[1] = (y * 224 / HEIGHT) as u8 + noise();
+        px[2] = ((x + y) * 112 / WIDTH) as u8 + noise();
+        px[3] = 255;
+    }
+    rgb
+}
+
+fn format_name(format: PixelFormat) -> &'static str {
+    match format {
+        PixelFormat::Yuv444 => "444",
+        PixelFormat::Yuv422 => "422",
+        PixelFormat::Yuv420 => "420",
+        _ => "other",
+    }
+}
+
+/// Converts the test image to YUV with the given format.
+fn yuv_image(rgb: &OwnedRgbImage, format: PixelFormat) -> Image {
+    rgb.to_yuv_image(format).unwrap()
+}
+
+fn encode(c: &mut Criterion) {
+    let rgb = test_image();
+    let mut group = c.benchmark_group("encode");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(u64::from(WIDTH * HEIGHT)));
+    for codec in available_codecs() {
+        let Some(choice) = codec.encoder_choice() else {
+            continue;
+        };
+        for format in FORMATS {
+            let image = yuv_image(&rgb, format);
+            for speed in SPEEDS {
+                let mut encoder = Encoder::builder()
+                    .codec(choice)
+                    .speed(speed)
+                    .quality(QUALITY)
+                    .build()
+                    .unwrap();
+                let id = format!("{}/{}/speed{}", codec.name, format_name(format), speed);
+                let size = encoder.write(&image).unwrap().as_slice().len();
+                println!("encode/{}: {} bytes", id, size);
+                group.bench_function(id, |b| b.iter(|| encoder.write(black_box(&image)).unwrap()));
+            }
+        }
+    }
+    group.finish();
+}
+
+fn decode(c: &mut Criterion) {
+    if !available_codecs().iter().any(|codec| codec.can_encode) {
+        return;
+    }
+    let rgb = test_image();
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Elements(u64::from(WIDTH * HEIGHT)));
+    for format in FORMATS {
+        let mut encoder = Encoder::builder()
+            .speed(10)
+            .quality(QUALITY)
+            .build()
+            .unwrap();
+        let data = encoder.write(&yuv_image(&rgb, format)).unwrap();
+        let mut decoder = Decoder::new().unwrap();
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format_name(format)),
+            data.as_slice(),
+            |b, data| b.iter(|| decoder.decode(black_box(data)).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+fn convert(c: &mut Criterion) {
+    let rgb = test_image();
+    let mut group = c.benchmark_group("convert");
+    group.throughput(Throughput::Elements(u64::from(WIDTH * HEIGHT)));
+    for format in FORMATS {
+        let name = format_name(format);
+        group.bench_function(BenchmarkId::new("rgb_to_yuv", name), |b| {
+            b.iter(|| black_box(&rgb).to_yuv_image(format).unwrap())
+        });
+        let image = yuv_image(&rgb, format);
+        group.bench_function(BenchmarkId::new("yuv_to_rgb", name), |b| {
+            b.iter(|| black_box(&image).to_rgb_image(RgbFormat::Rgba).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, encode, decode, convert);
+criterion_main!(benches);
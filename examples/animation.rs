@@ -1,3 +1,4 @@
+use rustavif::encoder::RepetitionCount;
 use rustavif::{BitDepth, Encoder, PixelFormat, Result, RgbFormat, RgbImage};
 use std::fs::File;
 use std::io::Write;
@@ -15,7 +16,7 @@ fn main() -> Result<()> {
     encoder.set_quality(70);
     encoder.set_speed(6);
     encoder.set_timescale(30); // 30 FPS
-    encoder.set_repetition_count(0); // Infinite loop
+    encoder.set_repetition_count(RepetitionCount::Infinite)?;
 
     println!("Creating animated AVIF with {} frames...", num_frames);
 
@@ -56,12 +57,8 @@ fn main() -> Result<()> {
         // Create RgbImage from buffer
         let rgb_image = RgbImage::from_pixels(width, height, depth, format, &mut rgb_buffer)?;
 
-        // Convert RGB to YUV
-        let yuv_image = rgb_image.to_yuv_image(PixelFormat::Yuv420)?;
-
-        // Add frame to animation (duration: 1 second / 30 FPS = 1/30 second)
-        let duration_in_timescales = 1; // 1/30 second at 30 FPS
-        encoder.add_image(&yuv_image, duration_in_timescales, Default::default())?;
+        // Convert to YUV and add frame to animation (duration: 1/30 second at 30 FPS)
+        encoder.add_frame_rgb(&rgb_image, PixelFormat::Yuv420, 1)?;
     }
 
     // Finish encoding animation
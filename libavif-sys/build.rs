@@ -25,7 +25,62 @@ SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 use std::env;
 use std::path::PathBuf;
 
+/// Experimental libavif capabilities selected by cargo features: the CMake
+/// option enabling each, which is also the macro guarding its declarations
+/// in avif.h, and whether its feature is enabled.
+const EXPERIMENTAL_OPTIONS: [(&str, bool); 2] = [
+    (
+        "AVIF_ENABLE_EXPERIMENTAL_SAMPLE_TRANSFORM",
+        cfg!(feature = "sample-transform"),
+    ),
+    ("AVIF_ENABLE_EXPERIMENTAL_MINI", cfg!(feature = "mini")),
+];
+
 fn main() {
+    #[cfg(feature = "system-libavif")]
+    let include_dirs = probe_system_libavif();
+    #[cfg(not(feature = "system-libavif"))]
+    let include_dirs = build_vendored_libavif();
+
+    generate_bindings(&include_dirs);
+}
+
+/// Finds an installed libavif with pkg-config and links against it.
+///
+/// Bindings are generated from the installed headers, so any libavif 1.x
+/// fits.
+///
+/// # Returns
+/// The include directories of the installed headers.
+#[cfg(feature = "system-libavif")]
+fn probe_system_libavif() -> Vec<PathBuf> {
+    let mut config = pkg_config::Config::new();
+    config.atleast_version("1.0.0");
+
+    // pkg-config prints the link flags itself. Static linking can be chosen
+    // with the LIBAVIF_STATIC environment variable.
+    match config.probe("libavif") {
+        Ok(library) => library.include_paths,
+        Err(err) => panic!("libavif 1.0 or newer was not found by pkg-config: {}", err),
+    }
+}
+
+/// Builds the vendored libavif and its codecs with CMake and links against
+/// the result.
+///
+/// # Returns
+/// The include directories of the vendored headers.
+#[cfg(not(feature = "system-libavif"))]
+fn build_vendored_libavif() -> Vec<PathBuf> {
+    // Build scripts are compiled for the host, so the target has to be read
+    // from the environment rather than with `cfg!`.
+    let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    let target_env = env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default();
+    let target_family = env::var("CARGO_CFG_TARGET_FAMILY").unwrap_or_default();
+    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    let is_wasm = target_arch == "wasm32" || target_arch == "wasm64";
+    let is_unix = target_family.split(',').any(|family| family == "unix");
+
     let mut cfg = cmake::Config::new("libavif");
 
     cfg.profile(match env::var("PROFILE").as_deref() {
@@ -36,10 +91,32 @@ fn main() {
         .define("AVIF_BUILD_TESTS", "OFF")
         .define("AVIF_ENABLE_COVERAGE", "OFF")
         .define("CMAKE_POSITION_INDEPENDENT_CODE", "ON")
-        .define("BUILD_SHARED_LIBS", "OFF");
+        .define(
+            "BUILD_SHARED_LIBS",
+            if cfg!(feature = "dynamic") {
+                "ON"
+            } else {
+                "OFF"
+            },
+        );
 
-    if cfg!(target_env = "msvc") {
-        cfg.define("CMAKE_MSVC_RUNTIME_LIBRARY", "MultiThreaded");
+    if target_env == "msvc" {
+        cfg.define("CMAKE_MSVC_RUNTIME_LIBRARY", msvc_runtime_library());
+    }
+
+    if is_wasm {
+        // WebAssembly builds are single-threaded: keep pthread flags out of
+        // the C build so the result runs without shared memory support.
+        cfg.define("CMAKE_THREAD_LIBS_INIT", "")
+            .define("CMAKE_HAVE_THREADS_LIBRARY", "OFF")
+            .define("CMAKE_USE_PTHREADS_INIT", "OFF")
+            .define("THREADS_PREFER_PTHREAD_FLAG", "OFF");
+    }
+
+    match target_os.as_str() {
+        "android" => configure_android(&mut cfg, &target_arch),
+        "ios" => configure_ios(&mut cfg, &target_arch),
+        _ => {}
     }
 
     cfg.define(
@@ -53,30 +130,24 @@ fn main() {
 
     cfg.define(
         "AVIF_CODEC_AOM",
-        if cfg!(feature = "codec-aom") {
-            "LOCAL"
-        } else {
-            "OFF"
-        },
+        codec_mode(
+            cfg!(feature = "codec-aom"),
+            cfg!(feature = "codec-aom-system"),
+        ),
     );
     cfg.define(
         "AVIF_CODEC_DAV1D",
-        if cfg!(feature = "codec-dav1d") {
-            "LOCAL"
-        } else {
-            "OFF"
-        },
+        codec_mode(
+            cfg!(feature = "codec-dav1d"),
+            cfg!(feature = "codec-dav1d-system"),
+        ),
     );
     cfg.define(
         "AVIF_CODEC_LIBGAV1",
-        if cfg!(feature = "codec-gav1") {
-            "LOCAL"
-        } else {
-            "OFF"
-        },
+        codec_mode(cfg!(feature = "codec-gav1"), false),
     );
 
-    #[cfg(feature = "codec-rav1e")]
+    #[cfg(all(feature = "codec-rav1e", not(feature = "dynamic")))]
     {
         use std::fs;
 
@@ -93,6 +164,12 @@ fn main() {
             .define("RAV1E_INCLUDE_DIR", &rav1e_inc)
             .define("RAV1E_LIBRARY", "-rav1e");
     }
+    #[cfg(all(feature = "codec-rav1e", feature = "dynamic"))]
+    {
+        // The shared library links the installed librav1e instead of the
+        // rav1e crate, whose symbols it could not resolve.
+        cfg.define("AVIF_CODEC_RAV1E", "SYSTEM");
+    }
     #[cfg(not(feature = "codec-rav1e"))]
     {
         cfg.define("AVIF_CODEC_RAV1E", "OFF");
@@ -100,28 +177,273 @@ fn main() {
 
     cfg.define(
         "AVIF_CODEC_SVT",
-        if cfg!(feature = "codec-svt") {
+        codec_mode(
+            cfg!(feature = "codec-svt"),
+            cfg!(feature = "codec-svt-system"),
+        ),
+    );
+
+    cfg.define(
+        "AVIF_LIBSHARPYUV",
+        if cfg!(feature = "sharpyuv") {
             "LOCAL"
         } else {
             "OFF"
         },
     );
 
+    cfg.define(
+        "AVIF_ENABLE_GAINMAP",
+        if cfg!(feature = "gain-map") {
+            "ON"
+        } else {
+            "OFF"
+        },
+    );
+    for (option, enabled) in EXPERIMENTAL_OPTIONS {
+        cfg.define(option, if enabled { "ON" } else { "OFF" });
+    }
+
+    // Defines given by the user come last so they override the ones above.
+    for (name, value) in extra_cmake_defines() {
+        cfg.define(name, value);
+    }
+
     let dst = cfg.build();
 
     println!("cargo:rustc-link-search=native={}/lib", dst.display());
 
+    // The shared library records its own dependencies on the codecs and
+    // system libraries.
+    if cfg!(feature = "dynamic") {
+        println!("cargo:rustc-link-lib=dylib=avif");
+        return vec![PathBuf::from("libavif/include")];
+    }
+
     println!("cargo:rustc-link-lib=static=avif");
 
-    #[cfg(target_family = "unix")]
-    {
+    // libavif's static library does not include codecs found on the
+    // system, so link them after it.
+    #[cfg(feature = "codec-aom-system")]
+    probe_system_codec("aom");
+    #[cfg(feature = "codec-dav1d-system")]
+    probe_system_codec("dav1d");
+    #[cfg(feature = "codec-svt-system")]
+    probe_system_codec("SvtAv1Enc");
+
+    if is_unix && !is_wasm {
         println!("cargo:rustc-link-lib=m");
-        println!("cargo:rustc-link-lib=pthread");
+        // Android's libc includes pthreads and has no separate library.
+        if target_os != "android" {
+            println!("cargo:rustc-link-lib=pthread");
+        }
+    }
+
+    vec![PathBuf::from("libavif/include")]
+}
+
+/// Returns the MSVC C runtime libavif is compiled against.
+///
+/// It matches the one Rust links, which is the static CRT only with the
+/// `crt-static` target feature, so that libavif does not conflict with other
+/// C code in the binary. `LIBAVIF_SYS_MSVC_RUNTIME` overrides it with `MT`,
+/// `MD` or any value CMake accepts for `CMAKE_MSVC_RUNTIME_LIBRARY`.
+#[cfg(not(feature = "system-libavif"))]
+fn msvc_runtime_library() -> String {
+    println!("cargo:rerun-if-env-changed=LIBAVIF_SYS_MSVC_RUNTIME");
+    match env::var("LIBAVIF_SYS_MSVC_RUNTIME").as_deref() {
+        Ok("MT") => "MultiThreaded".to_owned(),
+        Ok("MD") => "MultiThreadedDLL".to_owned(),
+        Ok(runtime) => runtime.to_owned(),
+        Err(_) => {
+            let features = env::var("CARGO_CFG_TARGET_FEATURE").unwrap_or_default();
+            if features.split(',').any(|feature| feature == "crt-static") {
+                "MultiThreaded".to_owned()
+            } else {
+                "MultiThreadedDLL".to_owned()
+            }
+        }
+    }
+}
+
+/// Returns the additional CMake defines given in `LIBAVIF_SYS_CMAKE_DEFINES`.
+///
+/// The variable holds `NAME=VALUE` pairs separated by whitespace, such as
+/// `AVIF_ENABLE_WERROR=OFF CMAKE_C_COMPILER_LAUNCHER=sccache`.
+#[cfg(not(feature = "system-libavif"))]
+fn extra_cmake_defines() -> Vec<(String, String)> {
+    println!("cargo:rerun-if-env-changed=LIBAVIF_SYS_CMAKE_DEFINES");
+    let defines = env::var("LIBAVIF_SYS_CMAKE_DEFINES").unwrap_or_default();
+    defines
+        .split_whitespace()
+        .map(|define| match define.split_once('=') {
+            Some((name, value)) if !name.is_empty() => (name.to_owned(), value.to_owned()),
+            _ => panic!(
+                "invalid entry \"{}\" in LIBAVIF_SYS_CMAKE_DEFINES, expected NAME=VALUE",
+                define
+            ),
+        })
+        .collect()
+}
+
+/// Cross-compiles for Android with the NDK's CMake toolchain file.
+///
+/// The NDK is taken from `ANDROID_NDK_HOME`, `ANDROID_NDK_ROOT` or
+/// `ANDROID_NDK`, or else the newest NDK installed in the SDK at
+/// `ANDROID_HOME` or `ANDROID_SDK_ROOT`. The minimum API level defaults to
+/// 21 and can be changed with `ANDROID_PLATFORM`, such as `android-24`.
+#[cfg(not(feature = "system-libavif"))]
+fn configure_android(cfg: &mut cmake::Config, target_arch: &str) {
+    for var in [
+        "ANDROID_NDK_HOME",
+        "ANDROID_NDK_ROOT",
+        "ANDROID_NDK",
+        "ANDROID_HOME",
+        "ANDROID_SDK_ROOT",
+        "ANDROID_PLATFORM",
+        "CMAKE_TOOLCHAIN_FILE",
+    ] {
+        println!("cargo:rerun-if-env-changed={}", var);
+    }
+
+    // A toolchain file given by the user takes precedence.
+    if env::var_os("CMAKE_TOOLCHAIN_FILE").is_none() {
+        let ndk = find_android_ndk().expect(
+            "Android NDK not found; set ANDROID_NDK_HOME to the NDK installation directory",
+        );
+        cfg.define(
+            "CMAKE_TOOLCHAIN_FILE",
+            ndk.join("build")
+                .join("cmake")
+                .join("android.toolchain.cmake"),
+        );
     }
+    let abi = match target_arch {
+        "aarch64" => "arm64-v8a",
+        "arm" => "armeabi-v7a",
+        "x86_64" => "x86_64",
+        "x86" => "x86",
+        arch => panic!("unsupported Android architecture {}", arch),
+    };
+    let platform = env::var("ANDROID_PLATFORM").unwrap_or_else(|_| "21".to_owned());
+    let platform = match platform.strip_prefix("android-") {
+        Some(_) => platform,
+        None => format!("android-{}", platform),
+    };
+    cfg.define("ANDROID_ABI", abi)
+        .define("ANDROID_PLATFORM", platform);
+}
+
+/// Returns the directory of the Android NDK to build with.
+#[cfg(not(feature = "system-libavif"))]
+fn find_android_ndk() -> Option<PathBuf> {
+    for var in ["ANDROID_NDK_HOME", "ANDROID_NDK_ROOT", "ANDROID_NDK"] {
+        if let Some(dir) = env::var_os(var) {
+            return Some(PathBuf::from(dir));
+        }
+    }
+    let sdk = env::var_os("ANDROID_HOME").or_else(|| env::var_os("ANDROID_SDK_ROOT"))?;
+    let sdk = PathBuf::from(sdk);
+    // Side-by-side NDKs are installed in directories named by version.
+    let newest = std::fs::read_dir(sdk.join("ndk"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.join("build").is_dir())
+        .max_by_key(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| {
+                    name.split('.')
+                        .map(|part| part.parse::<u32>().unwrap_or(0))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        });
+    newest.or_else(|| Some(sdk.join("ndk-bundle")).filter(|path| path.is_dir()))
+}
+
+/// Cross-compiles for iOS devices or the simulator.
+///
+/// The minimum iOS version defaults to 12.0 and can be changed with
+/// `IPHONEOS_DEPLOYMENT_TARGET`. Bitcode, which Xcode no longer accepts
+/// since version 14, is only embedded when `LIBAVIF_SYS_IOS_BITCODE` is set.
+#[cfg(not(feature = "system-libavif"))]
+fn configure_ios(cfg: &mut cmake::Config, target_arch: &str) {
+    println!("cargo:rerun-if-env-changed=IPHONEOS_DEPLOYMENT_TARGET");
+    println!("cargo:rerun-if-env-changed=LIBAVIF_SYS_IOS_BITCODE");
+
+    let target_abi = env::var("CARGO_CFG_TARGET_ABI").unwrap_or_default();
+    let simulator = target_abi == "sim" || target_arch == "x86_64";
+    let arch = match target_arch {
+        "aarch64" => "arm64",
+        arch => arch,
+    };
+    let deployment_target =
+        env::var("IPHONEOS_DEPLOYMENT_TARGET").unwrap_or_else(|_| "12.0".to_owned());
+    cfg.define("CMAKE_SYSTEM_NAME", "iOS")
+        .define("CMAKE_OSX_ARCHITECTURES", arch)
+        .define(
+            "CMAKE_OSX_SYSROOT",
+            if simulator {
+                "iphonesimulator"
+            } else {
+                "iphoneos"
+            },
+        )
+        .define("CMAKE_OSX_DEPLOYMENT_TARGET", deployment_target);
+    if env::var_os("LIBAVIF_SYS_IOS_BITCODE").is_some() {
+        cfg.cflag("-fembed-bitcode");
+    }
+}
+
+/// Returns how libavif's CMake build should obtain a codec.
+///
+/// An installed copy takes precedence over building it from source. A
+/// shared libavif always links installed codecs, so that none is embedded
+/// in it.
+#[cfg(not(feature = "system-libavif"))]
+fn codec_mode(local: bool, system: bool) -> &'static str {
+    match (local, system) {
+        (_, true) => "SYSTEM",
+        (true, false) if cfg!(feature = "dynamic") => "SYSTEM",
+        (true, false) => "LOCAL",
+        (false, false) => "OFF",
+    }
+}
+
+/// Links a codec library installed on the system, found with pkg-config.
+#[cfg(all(
+    not(feature = "system-libavif"),
+    any(
+        feature = "codec-aom-system",
+        feature = "codec-dav1d-system",
+        feature = "codec-svt-system"
+    )
+))]
+fn probe_system_codec(name: &str) {
+    if let Err(err) = pkg_config::probe_library(name) {
+        panic!("{} was not found by pkg-config: {}", name, err);
+    }
+}
 
-    // Generate bindings
+/// Generates Rust bindings for `avif/avif.h` found in `include_dirs`, with
+/// the macros of the enabled experimental features defined.
+fn generate_bindings(include_dirs: &[PathBuf]) {
     let bindings = bindgen::Builder::default()
-        .header("libavif/include/avif/avif.h")
+        .header_contents("wrapper.h", "#include <avif/avif.h>\n")
+        .clang_args(
+            include_dirs
+                .iter()
+                .map(|dir| format!("-I{}", dir.display())),
+        )
+        .clang_args(
+            EXPERIMENTAL_OPTIONS
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(option, _)| format!("-D{}", option)),
+        )
         .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
         .generate()
         .expect("Unable to generate bindings");
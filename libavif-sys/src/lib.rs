@@ -2,7 +2,7 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
-#[cfg(feature = "codec-rav1e")]
+#[cfg(all(feature = "codec-rav1e", not(feature = "dynamic")))]
 pub use rav1e::capi::*;
 
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
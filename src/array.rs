@@ -0,0 +1,102 @@
+//! `ndarray` views of image planes and RGB pixels.
+//!
+//! The views borrow the underlying buffers, so decoded data can be analyzed
+//! with `ndarray` without copying. Planes are indexed `[y, x]` and RGB pixels
+//! `[y, x, channel]`; row padding is skipped through the view's strides.
+
+use crate::rgb::RgbImage;
+use crate::{Channel, Image};
+use libavif_sys::*;
+use ndarray::{ArrayView2, ArrayView3, ShapeBuilder};
+
+impl Image {
+    /// Returns a plane of an 8-bit image as a 2D array.
+    ///
+    /// # Arguments
+    /// * `channel` - The plane to view
+    ///
+    /// # Returns
+    /// The view, or `None` if the plane is absent or the image uses 16-bit
+    /// samples.
+    pub fn plane_array(&self, channel: Channel) -> Option<ArrayView2<'_, u8>> {
+        if self.uses_u16() {
+            return None;
+        }
+        let plane = self.plane(channel)?;
+        let shape = (
+            self.plane_height(channel) as usize,
+            self.plane_width(channel) as usize,
+        )
+            .strides((self.plane_row_bytes(channel) as usize, 1));
+        ArrayView2::from_shape(shape, plane).ok()
+    }
+
+    /// Returns a plane of an image deeper than 8 bits as a 2D array.
+    ///
+    /// # Arguments
+    /// * `channel` - The plane to view
+    ///
+    /// # Returns
+    /// The view, or `None` if the plane is absent or the image uses 8-bit
+    /// samples.
+    pub fn plane_array_u16(&self, channel: Channel) -> Option<ArrayView2<'_, u16>> {
+        if !self.uses_u16() {
+            return None;
+        }
+        let plane = self.plane(channel)?;
+        // SAFETY: every bit pattern is a valid u16.
+        let (prefix, samples, _) = unsafe { plane.align_to::<u16>() };
+        if !prefix.is_empty() {
+            return None;
+        }
+        let shape = (
+            self.plane_height(channel) as usize,
+            self.plane_width(channel) as usize,
+        )
+            .strides((self.plane_row_bytes(channel) as usize / 2, 1));
+        ArrayView2::from_shape(shape, samples).ok()
+    }
+}
+
+impl RgbImage<'_> {
+    /// Returns the pixels of an 8-bit RGB image as a 3D array.
+    ///
+    /// # Returns
+    /// The view, or `None` if the image uses 16-bit, floating-point or
+    /// packed RGB 565 samples.
+    pub fn pixels_array(&self) -> Option<ArrayView3<'_, u8>> {
+        if self.inner.depth != 8
+            || self.inner.isFloat != 0
+            || self.inner.format == avifRGBFormat_AVIF_RGB_FORMAT_RGB_565
+        {
+            return None;
+        }
+        let shape = (
+            self.height() as usize,
+            self.width() as usize,
+            self.channel_count() as usize,
+        )
+            .strides((self.row_bytes() as usize, self.channel_count() as usize, 1));
+        ArrayView3::from_shape(shape, self.pixels()).ok()
+    }
+
+    /// Returns the pixels of an RGB image deeper than 8 bits as a 3D array.
+    ///
+    /// # Returns
+    /// The view, or `None` if the image uses 8-bit or floating-point samples
+    /// or the buffer is not aligned for `u16`.
+    pub fn pixels_array_u16(&self) -> Option<ArrayView3<'_, u16>> {
+        let pixels = self.pixels_u16()?;
+        let shape = (
+            self.height() as usize,
+            self.width() as usize,
+            self.channel_count() as usize,
+        )
+            .strides((
+                self.row_bytes() as usize / 2,
+                self.channel_count() as usize,
+                1,
+            ));
+        ArrayView3::from_shape(shape, pixels).ok()
+    }
+}
@@ -0,0 +1,118 @@
+//! Parallel encoding of independent images.
+//!
+//! This module encodes many images concurrently with a shared configuration,
+//! which is the typical shape of thumbnail generation or backfill jobs. The
+//! total number of threads is bounded: the budget is split between images
+//! encoded concurrently and the threads each encoder may use internally.
+//!
+//! By default each batch runs on its own thread pool. Applications that
+//! already use rayon can pass their pool with
+//! [`BatchEncoder::set_thread_pool`] so that batches, and the crate's other
+//! parallel loops running inside `ThreadPool::install`, share its threads.
+
+use crate::{AvifError, EncoderBuilder, Image, Result, RwData};
+use rayon::ThreadPool;
+use rayon::prelude::*;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Encodes batches of images in parallel with a shared configuration.
+#[derive(Debug, Clone)]
+pub struct BatchEncoder {
+    config: EncoderBuilder,
+    max_threads: usize,
+    pool: Option<Arc<ThreadPool>>,
+}
+
+impl BatchEncoder {
+    /// Creates a batch encoder using the given configuration for every image.
+    ///
+    /// The thread budget defaults to the crate's thread budget if one is set
+    /// with `set_thread_budget`, or else the number of available CPUs.
+    ///
+    /// # Arguments
+    /// * `config` - Encoder configuration; its thread count is overridden
+    pub fn new(config: EncoderBuilder) -> Self {
+        let max_threads = crate::threads::budget_or_available() as usize;
+        Self {
+            config,
+            max_threads,
+            pool: None,
+        }
+    }
+
+    /// Sets the total number of threads used by a batch.
+    ///
+    /// # Arguments
+    /// * `threads` - Thread budget (at least 1)
+    pub fn set_max_threads(&mut self, threads: usize) {
+        self.max_threads = threads.max(1);
+    }
+
+    /// Sets the thread pool that batches run on.
+    ///
+    /// The thread budget still applies: no more images are encoded at once
+    /// than the budget allows, however many threads the pool has, and the
+    /// budget is split between them. `None` creates a dedicated pool for
+    /// every batch.
+    ///
+    /// # Arguments
+    /// * `pool` - The application's thread pool
+    pub fn set_thread_pool(&mut self, pool: Option<Arc<ThreadPool>>) {
+        self.pool = pool;
+    }
+
+    /// Encodes every image and returns the results in input order.
+    ///
+    /// A failure to encode one image does not affect the others.
+    ///
+    /// # Arguments
+    /// * `images` - The images to encode
+    ///
+    /// # Returns
+    /// One result per image, or an error if the configuration is invalid or
+    /// the thread pool cannot be created.
+    pub fn encode(&self, images: &[Image]) -> Result<Vec<Result<RwData>>> {
+        if images.is_empty() {
+            return Ok(Vec::new());
+        }
+        let pool_threads = match &self.pool {
+            Some(pool) => pool.current_num_threads(),
+            None => self.max_threads,
+        };
+        let concurrency = pool_threads.min(self.max_threads).min(images.len());
+        let threads_per_encoder = (self.max_threads / concurrency) as u32;
+        let config = self.config.clone().max_threads(threads_per_encoder);
+        // Validate once up front rather than failing every image.
+        drop(config.build()?);
+
+        // Each of `concurrency` workers takes the next image until none are
+        // left, so a larger pool cannot run more encoders at once.
+        let next = AtomicUsize::new(0);
+        let encode_all = || {
+            let mut results: Vec<(usize, Result<RwData>)> = (0..concurrency)
+                .into_par_iter()
+                .flat_map_iter(|_| {
+                    std::iter::from_fn(|| {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let image = images.get(index)?;
+                        let result = config.build().and_then(|mut encoder| encoder.write(image));
+                        Some((index, result))
+                    })
+                })
+                .collect();
+            results.sort_unstable_by_key(|(index, _)| *index);
+            results.into_iter().map(|(_, result)| result).collect()
+        };
+        match &self.pool {
+            Some(pool) => Ok(pool.install(encode_all)),
+            None => {
+                let pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(concurrency)
+                    .build()
+                    .map_err(|_| AvifError::UnknownError)?;
+                Ok(pool.install(encode_all))
+            }
+        }
+    }
+}
@@ -0,0 +1,303 @@
+//! Command-line AVIF decoder.
+//!
+//! Decodes the first image of an AVIF file to PNG, PPM or Y4M.
+
+use clap::{Parser, ValueEnum};
+use rustavif::{
+    Channel, Decoder, DecoderCodecChoice, Image, PixelFormat, RgbFormat, StrictFlags, YuvRange,
+};
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Decode AVIF images to PNG, PPM or Y4M
+#[derive(Parser)]
+#[command(name = "ravif-dec", version)]
+struct Args {
+    /// Input AVIF file
+    input: PathBuf,
+
+    /// Output file
+    output: PathBuf,
+
+    /// Output format; detected from the output extension if omitted
+    #[arg(short, long, value_enum)]
+    format: Option<Format>,
+
+    /// Bit depth of PNG and PPM output; defaults to 8 for 8-bit images and
+    /// 16 otherwise
+    #[arg(short, long, value_enum)]
+    depth: Option<Depth>,
+
+    /// AV1 codec to use
+    #[arg(short, long, value_enum, default_value_t = Codec::Auto)]
+    codec: Codec,
+
+    /// Number of worker threads
+    #[arg(short, long)]
+    jobs: Option<u32>,
+
+    /// Accept files that violate the AVIF specification where possible
+    #[arg(long)]
+    no_strict: bool,
+
+    /// Maximum number of pixels in an image
+    #[arg(long)]
+    size_limit: Option<u32>,
+
+    /// Maximum width or height of an image; 0 for no limit
+    #[arg(long)]
+    dimension_limit: Option<u32>,
+
+    /// Maximum number of images in a sequence; 0 for no limit
+    #[arg(long)]
+    count_limit: Option<u32>,
+
+    /// Rotate and mirror the pixels according to the image's orientation
+    #[arg(long)]
+    orient: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Png,
+    Ppm,
+    Y4m,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Depth {
+    #[value(name = "8")]
+    Eight,
+    #[value(name = "16")]
+    Sixteen,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Codec {
+    Auto,
+    Aom,
+    Dav1d,
+    Libgav1,
+}
+
+fn codec_choice(codec: Codec) -> Result<DecoderCodecChoice, String> {
+    match codec {
+        Codec::Auto => Ok(DecoderCodecChoice::Auto),
+        #[cfg(feature = "codec-aom")]
+        Codec::Aom => Ok(DecoderCodecChoice::Aom),
+        #[cfg(feature = "codec-dav1d")]
+        Codec::Dav1d => Ok(DecoderCodecChoice::Dav1d),
+        #[cfg(feature = "codec-gav1")]
+        Codec::Libgav1 => Ok(DecoderCodecChoice::Libgav1),
+        #[allow(unreachable_patterns)]
+        _ => Err("codec not enabled in this build".to_string()),
+    }
+}
+
+/// Converts an image to tightly packed RGB samples of the given depth.
+///
+/// 16-bit samples are big-endian, as both PNG and PPM expect.
+fn rgb_samples(image: &Image, format: RgbFormat, wide: bool) -> Result<Vec<u8>, Box<dyn Error>> {
+    let rgb = image.to_rgb_image(format)?;
+    let Some(samples) = rgb.pixels_u16() else {
+        return Ok(if wide {
+            rgb.pixels()
+                .iter()
+                .flat_map(|&v| (v as u16 * 257).to_be_bytes())
+                .collect()
+        } else {
+            rgb.pixels().to_vec()
+        });
+    };
+    let max = ((1u32 << u32::from(image.depth())) - 1) as f32;
+    Ok(if wide {
+        samples
+            .iter()
+            .flat_map(|&v| ((v as f32 * 65535.0 / max).round() as u16).to_be_bytes())
+            .collect()
+    } else {
+        samples
+            .iter()
+            .map(|&v| (v as f32 * 255.0 / max).round() as u8)
+            .collect()
+    })
+}
+
+fn write_png(image: &Image, path: &Path, wide: bool) -> Result<(), Box<dyn Error>> {
+    let gray = image.yuv_format() == PixelFormat::Yuv400;
+    let (format, color_type) = match (gray, image.has_alpha()) {
+        (true, false) => (RgbFormat::Gray, png::ColorType::Grayscale),
+        (true, true) => (RgbFormat::GrayA, png::ColorType::GrayscaleAlpha),
+        (false, false) => (RgbFormat::Rgb, png::ColorType::Rgb),
+        (false, true) => (RgbFormat::Rgba, png::ColorType::Rgba),
+    };
+    let mut info = png::Info::with_size(image.width(), image.height());
+    info.color_type = color_type;
+    info.bit_depth = if wide {
+        png::BitDepth::Sixteen
+    } else {
+        png::BitDepth::Eight
+    };
+    if !image.icc().is_empty() {
+        info.icc_profile = Some(Cow::Borrowed(image.icc()));
+    }
+    let file = BufWriter::new(File::create(path)?);
+    let mut writer = png::Encoder::with_info(file, info)?.write_header()?;
+    writer.write_image_data(&rgb_samples(image, format, wide)?)?;
+    writer.finish()?;
+    Ok(())
+}
+
+fn write_ppm(image: &Image, path: &Path, wide: bool) -> Result<(), Box<dyn Error>> {
+    let (format, magic) = if image.yuv_format() == PixelFormat::Yuv400 {
+        (RgbFormat::Gray, "P5")
+    } else {
+        (RgbFormat::Rgb, "P6")
+    };
+    let max = if wide { 65535 } else { 255 };
+    let mut file = BufWriter::new(File::create(path)?);
+    write!(
+        file,
+        "{}\n{} {}\n{}\n",
+        magic,
+        image.width(),
+        image.height(),
+        max
+    )?;
+    file.write_all(&rgb_samples(image, format, wide)?)?;
+    file.flush()?;
+    Ok(())
+}
+
+fn write_y4m(image: &Image, path: &Path) -> Result<(), Box<dyn Error>> {
+    let depth = u32::from(image.depth());
+    let (colorspace, subsampling) = match image.yuv_format() {
+        PixelFormat::Yuv444 => ("444", "444"),
+        PixelFormat::Yuv422 => ("422", "422"),
+        PixelFormat::Yuv420 if depth == 8 => ("420jpeg", "420JPEG"),
+        PixelFormat::Yuv420 => ("420", "420"),
+        PixelFormat::Yuv400 => ("mono", "400"),
+        _ => return Err("unsupported YUV format for Y4M".into()),
+    };
+    let (colorspace, subsampling) = if depth == 8 {
+        (colorspace.to_string(), subsampling.to_string())
+    } else {
+        let colorspace = match colorspace {
+            "mono" => format!("mono{}", depth),
+            other => format!("{}p{}", other, depth),
+        };
+        (colorspace, format!("{}P{}", subsampling, depth))
+    };
+    let range = match image.yuv_range() {
+        YuvRange::Full => " XCOLORRANGE=FULL",
+        _ => "",
+    };
+    if image.has_alpha() {
+        eprintln!("warning: Y4M output drops the alpha plane");
+    }
+
+    let mut file = BufWriter::new(File::create(path)?);
+    write!(
+        file,
+        "YUV4MPEG2 W{} H{} F25:1 Ip A0:0 C{} XYSCSS={}{}\nFRAME\n",
+        image.width(),
+        image.height(),
+        colorspace,
+        subsampling,
+        range
+    )?;
+    let sample_size = if image.uses_u16() { 2 } else { 1 };
+    for channel in [Channel::Y, Channel::U, Channel::V] {
+        let Some(plane) = image.plane(channel) else {
+            continue;
+        };
+        let row_bytes = image.plane_row_bytes(channel) as usize;
+        let width = image.plane_width(channel) as usize * sample_size;
+        for row in plane
+            .chunks(row_bytes)
+            .take(image.plane_height(channel) as usize)
+        {
+            let row = &row[..width];
+            if image.uses_u16() {
+                // Y4M stores samples deeper than 8 bits little-endian.
+                let bytes: Vec<u8> = row
+                    .chunks_exact(2)
+                    .flat_map(|s| u16::from_ne_bytes([s[0], s[1]]).to_le_bytes())
+                    .collect();
+                file.write_all(&bytes)?;
+            } else {
+                file.write_all(row)?;
+            }
+        }
+    }
+    file.flush()?;
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let format = match args.format {
+        Some(format) => format,
+        None => match args.output.extension().and_then(|e| e.to_str()) {
+            Some("png") => Format::Png,
+            Some("ppm") | Some("pgm") => Format::Ppm,
+            Some("y4m") => Format::Y4m,
+            _ => return Err("cannot detect the output format; use --format".into()),
+        },
+    };
+
+    let mut decoder = Decoder::new()?;
+    decoder.set_codec_choice(codec_choice(args.codec)?);
+    if let Some(threads) = args.jobs {
+        decoder.set_max_threads(threads);
+    }
+    if args.no_strict {
+        decoder.set_strict_flags(StrictFlags::DISABLED);
+    }
+    if let Some(limit) = args.size_limit {
+        decoder.set_image_size_limit(limit);
+    }
+    if let Some(limit) = args.dimension_limit {
+        decoder.set_image_dimension_limit(limit);
+    }
+    if let Some(limit) = args.count_limit {
+        decoder.set_image_count_limit(limit);
+    }
+    decoder.set_apply_orientation(args.orient);
+
+    let data = fs::read(&args.input)?;
+    let started = Instant::now();
+    let image = decoder.decode(&data)?;
+    let decode_time = started.elapsed();
+    println!(
+        "Decoded {}x{} {}-bit {:?}{} in {:.2?}",
+        image.width(),
+        image.height(),
+        u32::from(image.depth()),
+        image.yuv_format(),
+        if image.has_alpha() { " with alpha" } else { "" },
+        decode_time,
+    );
+
+    let wide = match args.depth {
+        Some(Depth::Eight) => false,
+        Some(Depth::Sixteen) => true,
+        None => image.uses_u16(),
+    };
+    let started = Instant::now();
+    match format {
+        Format::Png => write_png(&image, &args.output, wide)?,
+        Format::Ppm => write_ppm(&image, &args.output, wide)?,
+        Format::Y4m => write_y4m(&image, &args.output)?,
+    }
+    println!(
+        "Wrote {} in {:.2?}",
+        args.output.display(),
+        started.elapsed()
+    );
+    Ok(())
+}
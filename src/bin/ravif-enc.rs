@@ -0,0 +1,307 @@
+//! Command-line AVIF encoder.
+//!
+//! Converts PNG and JPEG files to AVIF. Several inputs are encoded as an
+//! image sequence.
+
+use clap::{Parser, ValueEnum};
+use rustavif::convert::{
+    jpeg_to_image, jpeg_to_lossless_image, png_to_image, png_to_lossless_image,
+};
+use rustavif::encoder::{AddImageFlags, EncoderCodecChoice, RepetitionCount};
+use rustavif::{
+    BitDepth, ChromaDownsampling, ChromaUpsampling, EncoderBuilder, Image, PixelFormat,
+};
+use std::error::Error;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Encode PNG or JPEG images to AVIF
+#[derive(Parser)]
+#[command(name = "ravif-enc", version)]
+struct Args {
+    /// Input files; more than one produces an image sequence
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Output file
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// Color quality, 0 (worst) to 100 (lossless)
+    #[arg(short, long, value_parser = clap::value_parser!(u8).range(0..=100))]
+    quality: Option<u8>,
+
+    /// Alpha quality, 0 (worst) to 100 (lossless)
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+    quality_alpha: Option<u8>,
+
+    /// Encode losslessly, storing color pixels as identity-coded YUV 4:4:4
+    #[arg(short, long, conflicts_with_all = ["quality", "quality_alpha"])]
+    lossless: bool,
+
+    /// Encoder speed, 0 (slowest) to 10 (fastest)
+    #[arg(short, long, value_parser = clap::value_parser!(u8).range(0..=10))]
+    speed: Option<u8>,
+
+    /// AV1 codec to use
+    #[arg(short, long, value_enum, default_value_t = Codec::Auto)]
+    codec: Codec,
+
+    /// Number of worker threads
+    #[arg(short, long)]
+    jobs: Option<u32>,
+
+    /// YUV format of the output; defaults to the format chosen for the input
+    #[arg(short, long, value_enum)]
+    yuv: Option<Yuv>,
+
+    /// Bit depth of the output; defaults to the depth of the input
+    #[arg(short, long, value_enum)]
+    depth: Option<Depth>,
+
+    /// Log2 of the number of tile rows
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=6))]
+    tile_rows_log2: Option<u8>,
+
+    /// Log2 of the number of tile columns
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=6))]
+    tile_cols_log2: Option<u8>,
+
+    /// Choose tiling from the image size
+    #[arg(long, conflicts_with_all = ["tile_rows_log2", "tile_cols_log2"])]
+    autotiling: bool,
+
+    /// Frame rate of image sequences
+    #[arg(long, default_value_t = 30)]
+    fps: u64,
+
+    /// Maximum number of frames between keyframes
+    #[arg(long)]
+    keyframe_interval: Option<u32>,
+
+    /// Number of times an image sequence repeats; loops forever if omitted
+    #[arg(long)]
+    repeat: Option<u32>,
+
+    /// Codec-specific option as KEY=VALUE; may be repeated
+    #[arg(short = 'a', long = "advanced", value_parser = parse_key_value)]
+    codec_options: Vec<(String, String)>,
+
+    /// ICC profile to embed instead of the input's
+    #[arg(long)]
+    icc: Option<PathBuf>,
+
+    /// Exif payload to embed instead of the input's
+    #[arg(long)]
+    exif: Option<PathBuf>,
+
+    /// XMP packet to embed instead of the input's
+    #[arg(long)]
+    xmp: Option<PathBuf>,
+
+    /// Do not carry over the input's ICC profile
+    #[arg(long, conflicts_with = "icc")]
+    ignore_icc: bool,
+
+    /// Do not carry over the input's Exif metadata
+    #[arg(long, conflicts_with = "exif")]
+    ignore_exif: bool,
+
+    /// Do not carry over the input's XMP metadata
+    #[arg(long, conflicts_with = "xmp")]
+    ignore_xmp: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Codec {
+    Auto,
+    Aom,
+    Rav1e,
+    Svt,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Yuv {
+    #[value(name = "444")]
+    Yuv444,
+    #[value(name = "422")]
+    Yuv422,
+    #[value(name = "420")]
+    Yuv420,
+    #[value(name = "400")]
+    Yuv400,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Depth {
+    #[value(name = "8")]
+    Eight,
+    #[value(name = "10")]
+    Ten,
+    #[value(name = "12")]
+    Twelve,
+}
+
+fn parse_key_value(arg: &str) -> Result<(String, String), String> {
+    let (key, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected KEY=VALUE, got \"{}\"", arg))?;
+    Ok((key.to_string(), value.to_string()))
+}
+
+fn codec_choice(codec: Codec) -> Result<EncoderCodecChoice, String> {
+    match codec {
+        Codec::Auto => Ok(EncoderCodecChoice::Auto),
+        #[cfg(feature = "codec-aom")]
+        Codec::Aom => Ok(EncoderCodecChoice::Aom),
+        #[cfg(feature = "codec-rav1e")]
+        Codec::Rav1e => Ok(EncoderCodecChoice::Rav1e),
+        #[cfg(feature = "codec-svt")]
+        Codec::Svt => Ok(EncoderCodecChoice::Svt),
+        #[allow(unreachable_patterns)]
+        _ => Err("codec not enabled in this build".to_string()),
+    }
+}
+
+/// Builds the encoder settings from the command-line options.
+fn settings(args: &Args) -> Result<EncoderBuilder, String> {
+    let mut builder = EncoderBuilder::new()
+        .codec(codec_choice(args.codec)?)
+        .timescale(args.fps)
+        .repetition_count(match args.repeat {
+            Some(count) => RepetitionCount::Finite(count),
+            None => RepetitionCount::Infinite,
+        });
+    if args.lossless {
+        builder = builder.lossless();
+    }
+    if let Some(quality) = args.quality {
+        builder = builder.quality(quality);
+    }
+    if let Some(quality) = args.quality_alpha {
+        builder = builder.quality_alpha(quality);
+    }
+    if let Some(speed) = args.speed {
+        builder = builder.speed(speed);
+    }
+    if let Some(threads) = args.jobs {
+        builder = builder.max_threads(threads);
+    }
+    if args.tile_rows_log2.is_some() || args.tile_cols_log2.is_some() {
+        builder = builder.tiling(
+            args.tile_rows_log2.unwrap_or(0),
+            args.tile_cols_log2.unwrap_or(0),
+        );
+    }
+    if args.autotiling {
+        builder = builder.auto_tiling(true);
+    }
+    if let Some(interval) = args.keyframe_interval {
+        builder = builder.keyframe_interval(interval);
+    }
+    for (key, value) in &args.codec_options {
+        builder = builder.codec_option(key, value);
+    }
+    Ok(builder)
+}
+
+/// Reads a PNG or JPEG file, detected from its signature.
+///
+/// With `lossless`, the pixels are stored as identity-coded `Yuv444` so the
+/// RGB to YUV conversion loses nothing, as avifenc does.
+fn read_image(path: &Path, lossless: bool) -> Result<Image, Box<dyn Error>> {
+    let data = fs::read(path)?;
+    let image = if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        if lossless {
+            png_to_lossless_image(Cursor::new(&data))?
+        } else {
+            png_to_image(Cursor::new(&data))?
+        }
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        if lossless {
+            jpeg_to_lossless_image(data.as_slice())?
+        } else {
+            jpeg_to_image(data.as_slice())?
+        }
+    } else {
+        return Err(format!("{}: not a PNG or JPEG file", path.display()).into());
+    };
+    Ok(image)
+}
+
+/// Applies the format and metadata options to an input image.
+fn prepare(image: &mut Image, args: &Args) -> Result<(), Box<dyn Error>> {
+    if let Some(yuv) = args.yuv {
+        if args.lossless && !matches!(yuv, Yuv::Yuv444) {
+            return Err("--lossless requires --yuv 444".into());
+        }
+        let format = match yuv {
+            Yuv::Yuv444 => PixelFormat::Yuv444,
+            Yuv::Yuv422 => PixelFormat::Yuv422,
+            Yuv::Yuv420 => PixelFormat::Yuv420,
+            Yuv::Yuv400 => PixelFormat::Yuv400,
+        };
+        image.convert_format(
+            format,
+            ChromaDownsampling::Automatic,
+            ChromaUpsampling::Automatic,
+        )?;
+    }
+    if let Some(depth) = args.depth {
+        image.convert_depth(match depth {
+            Depth::Eight => BitDepth::Eight,
+            Depth::Ten => BitDepth::Ten,
+            Depth::Twelve => BitDepth::Twelve,
+        })?;
+    }
+
+    let replace = |path: &Option<PathBuf>, ignore: bool| -> std::io::Result<Option<Vec<u8>>> {
+        match path {
+            Some(path) => fs::read(path).map(Some),
+            None if ignore => Ok(Some(Vec::new())),
+            None => Ok(None),
+        }
+    };
+    if let Some(icc) = replace(&args.icc, args.ignore_icc)? {
+        image.set_icc(&icc)?;
+    }
+    if let Some(exif) = replace(&args.exif, args.ignore_exif)? {
+        image.set_exif(&exif)?;
+    }
+    if let Some(xmp) = replace(&args.xmp, args.ignore_xmp)? {
+        image.set_xmp(&xmp)?;
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    let mut encoder = settings(&args)?.build()?;
+
+    let started = Instant::now();
+    let output = if let [input] = args.inputs.as_slice() {
+        let mut image = read_image(input, args.lossless)?;
+        prepare(&mut image, &args)?;
+        encoder.write(&image)?
+    } else {
+        for input in &args.inputs {
+            let mut image = read_image(input, args.lossless)?;
+            prepare(&mut image, &args)?;
+            encoder.add_image(&image, 1, AddImageFlags::NONE)?;
+        }
+        encoder.finish()?
+    };
+    fs::write(&args.output, output.as_slice())?;
+
+    println!(
+        "Wrote {} ({} bytes, {} image{}) in {:.2?}",
+        args.output.display(),
+        output.as_slice().len(),
+        args.inputs.len(),
+        if args.inputs.len() == 1 { "" } else { "s" },
+        started.elapsed(),
+    );
+    Ok(())
+}
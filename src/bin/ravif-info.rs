@@ -0,0 +1,42 @@
+//! Command-line AVIF inspector.
+//!
+//! Prints the brands, items, properties and tracks of AVIF files without
+//! decoding them.
+
+use clap::Parser;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+/// Print the box structure of AVIF files
+#[derive(Parser)]
+#[command(name = "ravif-info", version)]
+struct Args {
+    /// Input AVIF files
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args = Args::parse();
+    for (i, input) in args.inputs.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        let data = fs::read(input)?;
+        println!("{} ({} bytes)", input.display(), data.len());
+        match rustavif::inspect(&data) {
+            Ok(report) => print!("{}", report),
+            Err(err) => {
+                print!("Error: {}", err);
+                let mut source = err.source();
+                while let Some(cause) = source {
+                    print!(": {}", cause);
+                    source = cause.source();
+                }
+                println!();
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,41 @@
+//! Typed codec-specific encoder options.
+//!
+//! libavif forwards codec-specific options to the underlying AV1 encoder as
+//! string key/value pairs. The structs in the submodules expose the commonly
+//! tuned options of each codec with proper types, validate their ranges, and
+//! serialize them into those pairs. Apply them with
+//! [`Encoder::set_codec_options`](crate::Encoder::set_codec_options).
+
+use crate::Result;
+use crate::encoder::EncoderCodecChoice;
+
+#[cfg(feature = "codec-aom")]
+pub mod aom;
+#[cfg(feature = "codec-rav1e")]
+pub mod rav1e;
+#[cfg(feature = "codec-svt")]
+pub mod svt;
+
+/// Options specific to one AV1 encoder.
+pub trait CodecOptions {
+    /// Returns the codec these options apply to.
+    fn codec(&self) -> EncoderCodecChoice;
+
+    /// Checks that every option is within the range the codec accepts.
+    fn validate(&self) -> Result<()>;
+
+    /// Returns the options as libavif codec-specific key/value pairs.
+    fn to_pairs(&self) -> Vec<(&'static str, String)>;
+}
+
+/// Returns `InvalidArgument` unless `value` lies within `range`.
+#[cfg(any(feature = "codec-aom", feature = "codec-rav1e", feature = "codec-svt"))]
+pub(crate) fn check_range<T: PartialOrd>(
+    value: Option<T>,
+    range: std::ops::RangeInclusive<T>,
+) -> Result<()> {
+    match value {
+        Some(value) if !range.contains(&value) => Err(crate::AvifError::InvalidArgument),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,70 @@
+//! Options for the aom (libaom) encoder.
+
+use super::{CodecOptions, check_range};
+use crate::Result;
+use crate::encoder::EncoderCodecChoice;
+
+/// Metric libaom optimizes rate-distortion decisions for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tune {
+    /// Peak signal-to-noise ratio (libaom's default)
+    Psnr,
+    /// Structural similarity, which tends to look better at low bitrates
+    Ssim,
+}
+
+/// Typed options for the aom encoder.
+///
+/// Unset fields keep libavif's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AomOptions {
+    /// Tuning metric (`tune`)
+    pub tune: Option<Tune>,
+    /// Constant quality level from 0 to 63 (`cq-level`); lower is better
+    pub cq_level: Option<u8>,
+    /// Loop filter sharpness from 0 to 7 (`sharpness`)
+    pub sharpness: Option<u8>,
+    /// Adaptive chroma quantization (`enable-chroma-deltaq`), which helps
+    /// saturated colors
+    pub enable_chroma_deltaq: Option<bool>,
+    /// Strength of denoising before encoding, with matching film grain
+    /// synthesized at decode time (`denoise-noise-level`); 0 disables it
+    pub denoise_noise_level: Option<u32>,
+}
+
+impl CodecOptions for AomOptions {
+    fn codec(&self) -> EncoderCodecChoice {
+        EncoderCodecChoice::Aom
+    }
+
+    fn validate(&self) -> Result<()> {
+        check_range(self.cq_level, 0..=63)?;
+        check_range(self.sharpness, 0..=7)?;
+        check_range(self.denoise_noise_level, 0..=50)
+    }
+
+    fn to_pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = Vec::new();
+        if let Some(tune) = self.tune {
+            let tune = match tune {
+                Tune::Psnr => "psnr",
+                Tune::Ssim => "ssim",
+            };
+            pairs.push(("tune", tune.to_owned()));
+        }
+        if let Some(level) = self.cq_level {
+            pairs.push(("end-usage", "q".to_owned()));
+            pairs.push(("cq-level", level.to_string()));
+        }
+        if let Some(sharpness) = self.sharpness {
+            pairs.push(("sharpness", sharpness.to_string()));
+        }
+        if let Some(enabled) = self.enable_chroma_deltaq {
+            pairs.push(("enable-chroma-deltaq", (enabled as u8).to_string()));
+        }
+        if let Some(level) = self.denoise_noise_level {
+            pairs.push(("denoise-noise-level", level.to_string()));
+        }
+        pairs
+    }
+}
@@ -0,0 +1,46 @@
+//! Options for the rav1e encoder.
+
+use super::{CodecOptions, check_range};
+use crate::Result;
+use crate::encoder::EncoderCodecChoice;
+
+/// Typed options for the rav1e encoder.
+///
+/// Unset fields keep libavif's defaults. rav1e's C API has no key for its
+/// photon noise synthesis, so film grain is not available with rav1e.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rav1eOptions {
+    /// Speed preset from 0 (slowest) to 10 (fastest) (`speed`), which
+    /// overrides the encoder speed
+    pub speed: Option<u8>,
+    /// Minimum number of tiles (`tiles`), which overrides libavif's tiling
+    pub tiles: Option<u32>,
+    /// Number of threads rav1e uses (`threads`); 0 lets rav1e decide
+    pub threads: Option<u32>,
+}
+
+impl CodecOptions for Rav1eOptions {
+    fn codec(&self) -> EncoderCodecChoice {
+        EncoderCodecChoice::Rav1e
+    }
+
+    fn validate(&self) -> Result<()> {
+        check_range(self.speed, 0..=10)?;
+        check_range(self.tiles, 1..=4096)?;
+        check_range(self.threads, 0..=1024)
+    }
+
+    fn to_pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = Vec::new();
+        if let Some(speed) = self.speed {
+            pairs.push(("speed", speed.to_string()));
+        }
+        if let Some(tiles) = self.tiles {
+            pairs.push(("tiles", tiles.to_string()));
+        }
+        if let Some(threads) = self.threads {
+            pairs.push(("threads", threads.to_string()));
+        }
+        pairs
+    }
+}
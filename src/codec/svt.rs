@@ -0,0 +1,61 @@
+//! Options for the SVT-AV1 encoder.
+
+use super::{CodecOptions, check_range};
+use crate::Result;
+use crate::encoder::EncoderCodecChoice;
+
+/// Metric SVT-AV1 optimizes for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tune {
+    /// Subjective visual quality
+    VisualQuality = 0,
+    /// Peak signal-to-noise ratio
+    Psnr = 1,
+    /// Structural similarity
+    Ssim = 2,
+}
+
+/// Typed options for the SVT-AV1 encoder.
+///
+/// Unset fields keep libavif's defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SvtOptions {
+    /// Encoder preset from -1 (slowest) to 13 (fastest) (`preset`), which
+    /// overrides the preset libavif derives from the encoder speed
+    pub preset: Option<i8>,
+    /// Tuning metric (`tune`)
+    pub tune: Option<Tune>,
+    /// Film grain denoising and synthesis strength from 0 to 50
+    /// (`film-grain`); 0 disables it
+    pub film_grain: Option<u8>,
+    /// Trade compression efficiency for faster decoding (`fast-decode`)
+    pub fast_decode: Option<bool>,
+}
+
+impl CodecOptions for SvtOptions {
+    fn codec(&self) -> EncoderCodecChoice {
+        EncoderCodecChoice::Svt
+    }
+
+    fn validate(&self) -> Result<()> {
+        check_range(self.preset, -1..=13)?;
+        check_range(self.film_grain, 0..=50)
+    }
+
+    fn to_pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = Vec::new();
+        if let Some(preset) = self.preset {
+            pairs.push(("preset", preset.to_string()));
+        }
+        if let Some(tune) = self.tune {
+            pairs.push(("tune", (tune as u8).to_string()));
+        }
+        if let Some(strength) = self.film_grain {
+            pairs.push(("film-grain", strength.to_string()));
+        }
+        if let Some(enabled) = self.fast_decode {
+            pairs.push(("fast-decode", (enabled as u8).to_string()));
+        }
+        pairs
+    }
+}
@@ -0,0 +1,192 @@
+//! Color management of decoded images.
+//!
+//! Most consumers assume that pixels are sRGB and ignore an image's ICC
+//! profile or CICP values, which shows wide-gamut and HDR images with wrong
+//! colors. The methods here use Little CMS to convert decoded images to
+//! display-ready sRGB.
+
+use crate::rgb::OwnedRgbImage;
+use crate::{AvifError, BitDepth, Decoder, Image, Result, RgbFormat};
+use lcms2::{
+    CIExyY, CIExyYTRIPLE, ColorSpaceSignature, Flags, Intent, PixelFormat, Profile, ToneCurve,
+    Transform,
+};
+use libavif_sys::*;
+
+/// Luminance of reference white in nits, which HDR transfers map to sRGB
+/// white (ITU-R BT.2408).
+const REFERENCE_WHITE_NITS: f64 = 203.0;
+/// Number of entries in tabulated transfer curves.
+const CURVE_ENTRIES: usize = 4096;
+/// Parameters of the sRGB curve as an ICC type 4 parametric curve.
+const SRGB_CURVE: [f64; 5] = [2.4, 1.0 / 1.055, 0.055 / 1.055, 1.0 / 12.92, 0.04045];
+/// Parameters of the BT.709 curve as an ICC type 4 parametric curve.
+const BT709_CURVE: [f64; 5] = [1.0 / 0.45, 1.0 / 1.099, 0.099 / 1.099, 1.0 / 4.5, 0.081];
+
+/// SMPTE ST 2084 (PQ) EOTF, relative to reference white.
+fn pq_eotf(encoded: f64) -> f64 {
+    const M1: f64 = 2610.0 / 16384.0;
+    const M2: f64 = 2523.0 / 4096.0 * 128.0;
+    const C1: f64 = 3424.0 / 4096.0;
+    const C2: f64 = 2413.0 / 4096.0 * 32.0;
+    const C3: f64 = 2392.0 / 4096.0 * 32.0;
+    let e = encoded.powf(1.0 / M2);
+    let nits = ((e - C1).max(0.0) / (C2 - C3 * e)).powf(1.0 / M1) * 10000.0;
+    nits / REFERENCE_WHITE_NITS
+}
+
+/// HLG inverse OETF and OOTF for a 1000-nit display, relative to reference
+/// white. The OOTF is applied per channel.
+fn hlg_eotf(encoded: f64) -> f64 {
+    const A: f64 = 0.17883277;
+    const B: f64 = 0.28466892;
+    const C: f64 = 0.55991073;
+    let scene = if encoded <= 0.5 {
+        encoded * encoded / 3.0
+    } else {
+        (((encoded - C) / A).exp() + B) / 12.0
+    };
+    scene.powf(1.2) * 1000.0 / REFERENCE_WHITE_NITS
+}
+
+/// Returns the curve converting encoded values of a CICP transfer
+/// characteristic to linear light. Unknown transfers are treated as sRGB.
+fn transfer_curve(transfer: u32) -> Result<ToneCurve> {
+    let parametric =
+        |params: &[f64]| ToneCurve::new_parametric(4, params).map_err(|_| AvifError::UnknownError);
+    let tabulated = |eotf: fn(f64) -> f64| {
+        let values: Vec<u16> = (0..CURVE_ENTRIES)
+            .map(|i| {
+                let linear = eotf(i as f64 / (CURVE_ENTRIES - 1) as f64);
+                (linear.clamp(0.0, 1.0) * u16::MAX as f64).round() as u16
+            })
+            .collect();
+        ToneCurve::new_tabulated(&values)
+    };
+    match transfer {
+        AVIF_TRANSFER_CHARACTERISTICS_BT709
+        | AVIF_TRANSFER_CHARACTERISTICS_BT601
+        | AVIF_TRANSFER_CHARACTERISTICS_BT2020_10BIT
+        | AVIF_TRANSFER_CHARACTERISTICS_BT2020_12BIT => parametric(&BT709_CURVE),
+        AVIF_TRANSFER_CHARACTERISTICS_BT470M => Ok(ToneCurve::new(2.2)),
+        AVIF_TRANSFER_CHARACTERISTICS_BT470BG => Ok(ToneCurve::new(2.8)),
+        AVIF_TRANSFER_CHARACTERISTICS_LINEAR => Ok(ToneCurve::new(1.0)),
+        AVIF_TRANSFER_CHARACTERISTICS_PQ => Ok(tabulated(pq_eotf)),
+        AVIF_TRANSFER_CHARACTERISTICS_HLG => Ok(tabulated(hlg_eotf)),
+        _ => parametric(&SRGB_CURVE),
+    }
+}
+
+/// Builds an RGB profile from the CICP primaries and transfer of an image.
+fn cicp_profile(image: &Image) -> Result<Profile> {
+    let mut values = [0.0f32; 8];
+    unsafe { avifColorPrimariesGetValues(image.color_primaries(), values.as_mut_ptr()) };
+    let point = |i: usize| CIExyY {
+        x: values[i] as f64,
+        y: values[i + 1] as f64,
+        Y: 1.0,
+    };
+    let primaries = CIExyYTRIPLE {
+        Red: point(0),
+        Green: point(2),
+        Blue: point(4),
+    };
+    let curve = transfer_curve(image.transfer_characteristics().into())?;
+    Profile::new_rgb(&point(6), &primaries, &[&curve, &curve, &curve])
+        .map_err(|_| AvifError::UnknownError)
+}
+
+impl Image {
+    /// Returns whether the CICP values describe sRGB, treating unspecified
+    /// values as sRGB.
+    fn is_srgb(&self) -> bool {
+        matches!(
+            u32::from(self.color_primaries()),
+            AVIF_COLOR_PRIMARIES_BT709 | AVIF_COLOR_PRIMARIES_UNSPECIFIED
+        ) && matches!(
+            u32::from(self.transfer_characteristics()),
+            AVIF_TRANSFER_CHARACTERISTICS_SRGB | AVIF_TRANSFER_CHARACTERISTICS_UNSPECIFIED
+        )
+    }
+
+    /// Converts this image to 8-bit sRGB with straight alpha.
+    ///
+    /// The embedded ICC profile takes precedence; otherwise the CICP color
+    /// primaries and transfer characteristics describe the source. PQ and
+    /// HLG content is mapped so that reference white (203 nits) becomes sRGB
+    /// white, and brighter highlights are clipped.
+    ///
+    /// # Returns
+    /// An RGBA image owning its pixels, or an error if the ICC profile is
+    /// invalid or conversion fails.
+    pub fn to_srgb(&self) -> Result<OwnedRgbImage> {
+        let source = if !self.icc().is_empty() {
+            Profile::new_icc(self.icc()).map_err(|_| AvifError::IncompatibleImage)?
+        } else if self.is_srgb() {
+            return self.to_straight_rgb(RgbFormat::Rgba, 8);
+        } else {
+            cicp_profile(self)?
+        };
+        let srgb = Profile::new_srgb();
+        let mut output = OwnedRgbImage::new(
+            self.width(),
+            self.height(),
+            BitDepth::Eight,
+            RgbFormat::Rgba,
+        )?;
+        let (pixels, _) = output.pixels_mut().as_chunks_mut::<4>();
+
+        let transform_error = |_| AvifError::IncompatibleImage;
+        if source.color_space() == ColorSpaceSignature::GrayData {
+            let input = self.to_straight_rgb(RgbFormat::GrayA, 16)?;
+            let (samples, _) = input
+                .pixels_u16()
+                .ok_or(AvifError::UnknownError)?
+                .as_chunks();
+            let transform: Transform<[u16; 2], [u8; 4]> = Transform::new_flags(
+                &source,
+                PixelFormat::GRAYA_16,
+                &srgb,
+                PixelFormat::RGBA_8,
+                Intent::Perceptual,
+                Flags::COPY_ALPHA,
+            )
+            .map_err(transform_error)?;
+            transform.transform_pixels(samples, pixels);
+        } else {
+            let input = self.to_straight_rgb(RgbFormat::Rgba, 16)?;
+            let (samples, _) = input
+                .pixels_u16()
+                .ok_or(AvifError::UnknownError)?
+                .as_chunks();
+            let transform: Transform<[u16; 4], [u8; 4]> = Transform::new_flags(
+                &source,
+                PixelFormat::RGBA_16,
+                &srgb,
+                PixelFormat::RGBA_8,
+                Intent::Perceptual,
+                Flags::COPY_ALPHA,
+            )
+            .map_err(transform_error)?;
+            transform.transform_pixels(samples, pixels);
+        }
+        Ok(output)
+    }
+}
+
+impl Decoder {
+    /// Decodes the first image of an AVIF file to display-ready sRGB.
+    ///
+    /// See [`Image::to_srgb`] for how the image's color information is
+    /// applied.
+    ///
+    /// # Arguments
+    /// * `data` - The encoded AVIF data
+    ///
+    /// # Returns
+    /// An 8-bit RGBA image, or an error if decoding or color conversion
+    /// fails.
+    pub fn decode_srgb(&mut self, data: &[u8]) -> Result<OwnedRgbImage> {
+        self.decode(data)?.to_srgb()
+    }
+}
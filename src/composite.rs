@@ -0,0 +1,325 @@
+//! Alpha compositing of images.
+//!
+//! This module extends `Image` with operations that combine images directly in
+//! YUV, such as overlaying a watermark or assembling sprite sheets before
+//! encoding. Blending in YUV is equivalent to blending the corresponding
+//! gamma-encoded RGB values, since the YUV transform is linear.
+
+use crate::transform::{PlaneData, read_sample, write_sample};
+use crate::{
+    AvifError, BitDepth, Channel, Image, PixelFormat, Planes, Result, RgbFormat, RgbImage, YuvRange,
+};
+use libavif_sys::*;
+
+/// Blend modes for [`Image::composite`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Replace the destination with the source, including its alpha
+    Source,
+    /// Porter-Duff "source over destination"
+    SourceOver,
+}
+
+impl BlendMode {
+    /// Blends offset-free sample values with normalized alphas.
+    fn blend(self, src: f32, dst: f32, src_alpha: f32, dst_alpha: f32, premultiplied: bool) -> f32 {
+        match self {
+            BlendMode::Source => src,
+            BlendMode::SourceOver if premultiplied => src + dst * (1.0 - src_alpha),
+            BlendMode::SourceOver => {
+                let out_alpha = self.blend_alpha(src_alpha, dst_alpha);
+                if out_alpha <= 0.0 {
+                    0.0
+                } else {
+                    (src * src_alpha + dst * dst_alpha * (1.0 - src_alpha)) / out_alpha
+                }
+            }
+        }
+    }
+
+    /// Returns the resulting normalized alpha.
+    fn blend_alpha(self, src_alpha: f32, dst_alpha: f32) -> f32 {
+        match self {
+            BlendMode::Source => src_alpha,
+            BlendMode::SourceOver => src_alpha + dst_alpha * (1.0 - src_alpha),
+        }
+    }
+}
+
+/// The placement and settings of a compositing pass, with positions in
+/// destination luma coordinates.
+struct Pass<'a> {
+    src: &'a Image,
+    /// Position of the source's top-left corner
+    x: i64,
+    y: i64,
+    /// Overlapping area, exclusive of `x1` and `y1`
+    x0: i64,
+    y0: i64,
+    x1: i64,
+    y1: i64,
+    max: f32,
+    mode: BlendMode,
+    premultiplied: bool,
+}
+
+impl Pass<'_> {
+    fn to_sample(&self, value: f32) -> u16 {
+        value.round().clamp(0.0, self.max) as u16
+    }
+
+    fn alpha(&self, plane: Option<&PlaneData>, x: i64, y: i64) -> f32 {
+        match plane {
+            Some(plane) => plane.get(x as usize, y as usize) as f32 / self.max,
+            None => 1.0,
+        }
+    }
+
+    /// Blends the overlapping part of row `cy` of a chroma plane into `out`.
+    fn chroma_row(
+        &self,
+        dst: &Image,
+        channel: Channel,
+        cy: i64,
+        (shift_x, shift_y): (u32, u32),
+        mid: f32,
+        out: &mut Vec<u16>,
+    ) {
+        out.clear();
+        let (Some(src), Some(dst_plane)) = (
+            PlaneData::new(self.src, channel),
+            PlaneData::new(dst, channel),
+        ) else {
+            return;
+        };
+        let src_alpha = PlaneData::new(self.src, Channel::A);
+        let dst_alpha = PlaneData::new(dst, Channel::A);
+        let block_y0 = cy << shift_y;
+        let block_y1 = (block_y0 + (1 << shift_y)).min(dst.height() as i64);
+        for cx in (self.x0 >> shift_x)..=((self.x1 - 1) >> shift_x) {
+            // Average both alphas over the luma block this chroma sample
+            // covers; uncovered source positions count as transparent.
+            let block_x0 = cx << shift_x;
+            let block_x1 = (block_x0 + (1 << shift_x)).min(dst.width() as i64);
+            let (mut sa, mut da) = (0.0, 0.0);
+            for ly in block_y0..block_y1 {
+                for lx in block_x0..block_x1 {
+                    if (self.x0..self.x1).contains(&lx) && (self.y0..self.y1).contains(&ly) {
+                        sa += self.alpha(src_alpha.as_ref(), lx - self.x, ly - self.y);
+                    }
+                    da += self.alpha(dst_alpha.as_ref(), lx, ly);
+                }
+            }
+            let block_size = ((block_x1 - block_x0) * (block_y1 - block_y0)) as f32;
+            let (sa, da) = (sa / block_size, da / block_size);
+
+            let sx = (block_x0.max(self.x0) - self.x) >> shift_x;
+            let sy = (block_y0.max(self.y0) - self.y) >> shift_y;
+            let s = src.get(sx as usize, sy as usize) as f32;
+            let d = dst_plane.get(cx as usize, cy as usize) as f32;
+            let value = self
+                .mode
+                .blend(s - mid, d - mid, sa, da, self.premultiplied)
+                + mid;
+            out.push(self.to_sample(value));
+        }
+    }
+
+    /// Blends the overlapping part of luma row `ly` into `luma`, and its
+    /// alpha into `alpha` if the destination has an alpha plane.
+    fn luma_row(
+        &self,
+        dst: &Image,
+        ly: i64,
+        black: f32,
+        luma: &mut Vec<u16>,
+        alpha: &mut Vec<u16>,
+    ) {
+        luma.clear();
+        alpha.clear();
+        let (Some(src), Some(dst_plane)) = (
+            PlaneData::new(self.src, Channel::Y),
+            PlaneData::new(dst, Channel::Y),
+        ) else {
+            return;
+        };
+        let src_alpha = PlaneData::new(self.src, Channel::A);
+        let dst_alpha = PlaneData::new(dst, Channel::A);
+        for lx in self.x0..self.x1 {
+            let sa = self.alpha(src_alpha.as_ref(), lx - self.x, ly - self.y);
+            let da = self.alpha(dst_alpha.as_ref(), lx, ly);
+            let s = src.get((lx - self.x) as usize, (ly - self.y) as usize) as f32;
+            let d = dst_plane.get(lx as usize, ly as usize) as f32;
+            let value = self
+                .mode
+                .blend(s - black, d - black, sa, da, self.premultiplied)
+                + black;
+            luma.push(self.to_sample(value));
+            if dst_alpha.is_some() {
+                alpha.push(self.to_sample(self.mode.blend_alpha(sa, da) * self.max));
+            }
+        }
+    }
+}
+
+impl Image {
+    /// Composites another image onto this one.
+    ///
+    /// Both images must share bit depth, YUV format, YUV range, matrix
+    /// coefficients and alpha premultiplication. The source may be placed
+    /// partially or entirely outside of this image; only the overlapping area
+    /// is modified. Images without an alpha plane are treated as opaque.
+    ///
+    /// # Arguments
+    /// * `other` - The source image to draw
+    /// * `x` - Horizontal position of the source's top-left corner
+    /// * `y` - Vertical position of the source's top-left corner
+    /// * `mode` - How source and destination are combined
+    pub fn composite(&mut self, other: &Image, x: i32, y: i32, mode: BlendMode) -> Result<()> {
+        if self.depth() != other.depth()
+            || self.yuv_format() != other.yuv_format()
+            || self.yuv_range() != other.yuv_range()
+            || self.matrix_coefficients() != other.matrix_coefficients()
+            || self.alpha_premultiplied() != other.alpha_premultiplied()
+        {
+            return Err(AvifError::IncompatibleImage);
+        }
+        if self.plane(Channel::Y).is_none() || other.plane(Channel::Y).is_none() {
+            return Err(AvifError::NoContent);
+        }
+
+        // Overlapping area in destination luma coordinates.
+        let (x, y) = (x as i64, y as i64);
+        let x0 = x.max(0);
+        let y0 = y.max(0);
+        let x1 = (x + other.width() as i64).min(self.width() as i64);
+        let y1 = (y + other.height() as i64).min(self.height() as i64);
+        if x0 >= x1 || y0 >= y1 {
+            return Ok(());
+        }
+
+        let depth = u32::from(self.depth());
+        let max = ((1u32 << depth) - 1) as f32;
+        let mid = (1u32 << (depth - 1)) as f32;
+        let black = match self.yuv_range() {
+            YuvRange::Limited => (16u32 << (depth - 8)) as f32,
+            YuvRange::Full => 0.0,
+            YuvRange::Unknown(_) => return Err(AvifError::IncompatibleImage),
+        };
+        let pass = Pass {
+            src: other,
+            x,
+            y,
+            x0,
+            y0,
+            x1,
+            y1,
+            max,
+            mode,
+            premultiplied: self.alpha_premultiplied(),
+        };
+
+        // Rows are blended in place. Chroma goes first and alpha last, since
+        // every plane is blended with the destination's original alpha.
+        let mut row = Vec::new();
+        if let Some(shift) = self.yuv_format().chroma_shift() {
+            for channel in [Channel::U, Channel::V] {
+                for cy in (y0 >> shift.1)..=((y1 - 1) >> shift.1) {
+                    pass.chroma_row(self, channel, cy, shift, mid, &mut row);
+                    self.write_row(channel, (x0 >> shift.0) as usize, cy as usize, &row);
+                }
+            }
+        }
+        let mut alpha_row = Vec::new();
+        for ly in y0..y1 {
+            pass.luma_row(self, ly, black, &mut row, &mut alpha_row);
+            self.write_row(Channel::Y, x0 as usize, ly as usize, &row);
+            self.write_row(Channel::A, x0 as usize, ly as usize, &alpha_row);
+        }
+        Ok(())
+    }
+
+    /// Composites the image over a solid background color and drops the alpha
+    /// plane.
+    ///
+    /// This is useful when the delivery target does not support transparency.
+    /// Images without an alpha plane are left unchanged.
+    ///
+    /// # Arguments
+    /// * `background` - Background color as red, green and blue samples at
+    ///   the image's bit depth
+    pub fn flatten(&mut self, background: [u16; 3]) -> Result<()> {
+        if !self.has_alpha() {
+            return Ok(());
+        }
+
+        let color = self.rgb_to_yuv_sample(background)?;
+        let mut flattened =
+            self.derive(self.width(), self.height(), self.depth(), self.yuv_format())?;
+        flattened.free_planes(Planes::A);
+        let wide = flattened.uses_u16();
+        for (channel, value) in [Channel::Y, Channel::U, Channel::V].into_iter().zip(color) {
+            let width = flattened.plane_width(channel) as usize;
+            let height = flattened.plane_height(channel) as usize;
+            let row_bytes = flattened.plane_row_bytes(channel) as usize;
+            let Some(plane) = flattened.plane_mut(channel) else {
+                continue;
+            };
+            for y in 0..height {
+                let row = &mut plane[y * row_bytes..];
+                for x in 0..width {
+                    write_sample(row, x, wide, value);
+                }
+            }
+        }
+
+        flattened.composite(self, 0, 0, BlendMode::SourceOver)?;
+        flattened.set_alpha_premultiplied(false);
+        *self = flattened;
+        Ok(())
+    }
+
+    /// Converts a single RGB color to YUV samples using this image's CICP
+    /// values and range.
+    fn rgb_to_yuv_sample(&self, rgb: [u16; 3]) -> Result<[u16; 3]> {
+        let mut pixels: Vec<u8> = if self.depth() == BitDepth::Eight {
+            rgb.iter().map(|&c| c as u8).collect()
+        } else {
+            rgb.iter().flat_map(|c| c.to_ne_bytes()).collect()
+        };
+        let rgb_image = RgbImage::from_pixels(1, 1, self.depth(), RgbFormat::Rgb, &mut pixels)?;
+
+        let mut sample = Image::new(1, 1, self.depth(), PixelFormat::Yuv444)?;
+        sample.set_yuv_range(self.yuv_range());
+        sample.set_color_primaries(self.color_primaries());
+        sample.set_transfer_characteristics(self.transfer_characteristics());
+        sample.set_matrix_coefficients(self.matrix_coefficients());
+        let result = unsafe { avifImageRGBToYUV(sample.inner, &rgb_image.inner) };
+        if result != avifResult_AVIF_RESULT_OK {
+            return Err(AvifError::from(result));
+        }
+
+        let wide = sample.uses_u16();
+        let mut yuv = [0u16; 3];
+        for (value, channel) in yuv.iter_mut().zip([Channel::Y, Channel::U, Channel::V]) {
+            if let Some(plane) = sample.plane(channel) {
+                *value = read_sample(plane, 0, wide);
+            }
+        }
+        Ok(yuv)
+    }
+
+    /// Writes consecutive samples to row `y` of a plane, starting at column
+    /// `x` and ignoring absent planes.
+    fn write_row(&mut self, channel: Channel, x: usize, y: usize, values: &[u16]) {
+        let wide = self.uses_u16();
+        let row_bytes = self.plane_row_bytes(channel) as usize;
+        let Some(plane) = self.plane_mut(channel) else {
+            return;
+        };
+        let row = &mut plane[y * row_bytes..];
+        for (i, &value) in values.iter().enumerate() {
+            write_sample(row, x + i, wide, value);
+        }
+    }
+}
@@ -0,0 +1,347 @@
+//! Conversion of images in other formats to AVIF.
+//!
+//! These helpers cover the common migration path of re-encoding existing
+//! files: they decode the source, carry its color information and metadata
+//! over to the AVIF, and encode it with the given settings. PNG support
+//! requires the `png` feature and JPEG support the `jpeg` feature.
+
+#![allow(non_upper_case_globals)]
+
+use crate::error::Operation;
+use crate::rgb::image_from_rgb;
+use crate::{AvifError, EncoderBuilder, Image, PixelFormat, Result, RgbFormat, RwData};
+use libavif_sys::*;
+#[cfg(feature = "jpeg")]
+use std::io::Read;
+#[cfg(feature = "png")]
+use std::io::{BufRead, Seek};
+#[cfg(feature = "jpeg")]
+use zune_jpeg::zune_core::{bytestream::ZCursor, colorspace::ColorSpace};
+
+/// Tolerance when comparing PNG gamma values and chromaticities, in units of
+/// 1/100000.
+#[cfg(feature = "png")]
+const PNG_TOLERANCE: u32 = 100;
+
+/// BT.709 white point and primaries as `(x, y)` pairs in units of 1/100000,
+/// in the order white, red, green, blue.
+#[cfg(feature = "png")]
+const BT709_CHROMATICITIES: [(u32, u32); 4] = [
+    (31270, 32900),
+    (64000, 33000),
+    (30000, 60000),
+    (15000, 6000),
+];
+
+/// Wraps a PNG decoding error with the operation and its message.
+#[cfg(feature = "png")]
+fn png_error(error: png::DecodingError) -> AvifError {
+    let source = match error {
+        png::DecodingError::IoError(_) => AvifError::IoError,
+        png::DecodingError::LimitsExceeded => AvifError::OutOfMemory,
+        _ => AvifError::InvalidArgument,
+    };
+    source.with_detail(Operation::ReadInput, error.to_string())
+}
+
+/// Returns whether two values in units of 1/100000 are within tolerance.
+#[cfg(feature = "png")]
+fn png_close(a: u32, b: u32) -> bool {
+    a.abs_diff(b) <= PNG_TOLERANCE
+}
+
+/// Sets the CICP values and ICC profile of an image from the color chunks of
+/// a PNG file.
+///
+/// Chunks are applied in the precedence order of the PNG specification:
+/// `cICP`, then `iCCP`, then `sRGB`, then `gAMA` and `cHRM`. A file without
+/// any of them is treated as sRGB.
+#[cfg(feature = "png")]
+fn set_png_color(image: &mut Image, info: &png::Info<'_>) -> Result<()> {
+    if let Some(cicp) = info.coding_independent_code_points {
+        image.set_color_primaries(cicp.color_primaries.into());
+        image.set_transfer_characteristics(cicp.transfer_function.into());
+        return Ok(());
+    }
+    if let Some(icc) = &info.icc_profile {
+        image.set_icc(icc)?;
+        image.set_color_primaries(AVIF_COLOR_PRIMARIES_UNSPECIFIED as avifColorPrimaries);
+        image.set_transfer_characteristics(
+            AVIF_TRANSFER_CHARACTERISTICS_UNSPECIFIED as avifTransferCharacteristics,
+        );
+        return Ok(());
+    }
+    if info.srgb.is_some() || (info.gama_chunk.is_none() && info.chrm_chunk.is_none()) {
+        image.set_color_primaries(AVIF_COLOR_PRIMARIES_BT709 as avifColorPrimaries);
+        image.set_transfer_characteristics(
+            AVIF_TRANSFER_CHARACTERISTICS_SRGB as avifTransferCharacteristics,
+        );
+        return Ok(());
+    }
+
+    let primaries = match info.chrm_chunk {
+        None => AVIF_COLOR_PRIMARIES_BT709,
+        Some(chrm) => {
+            let points = [chrm.white, chrm.red, chrm.green, chrm.blue];
+            let bt709 = points.iter().zip(BT709_CHROMATICITIES).all(|(p, (x, y))| {
+                png_close(p.0.into_scaled(), x) && png_close(p.1.into_scaled(), y)
+            });
+            if bt709 {
+                AVIF_COLOR_PRIMARIES_BT709
+            } else {
+                AVIF_COLOR_PRIMARIES_UNSPECIFIED
+            }
+        }
+    };
+    // gAMA stores the reciprocal of the display gamma.
+    let transfer = match info.gama_chunk.map(|gamma| gamma.into_scaled()) {
+        None => AVIF_TRANSFER_CHARACTERISTICS_SRGB,
+        Some(gamma) if png_close(gamma, 45455) => AVIF_TRANSFER_CHARACTERISTICS_BT470M,
+        Some(gamma) if png_close(gamma, 35714) => AVIF_TRANSFER_CHARACTERISTICS_BT470BG,
+        Some(gamma) if png_close(gamma, 100000) => AVIF_TRANSFER_CHARACTERISTICS_LINEAR,
+        Some(_) => AVIF_TRANSFER_CHARACTERISTICS_UNSPECIFIED,
+    };
+    image.set_color_primaries(primaries as avifColorPrimaries);
+    image.set_transfer_characteristics(transfer as avifTransferCharacteristics);
+    Ok(())
+}
+
+/// Decodes a PNG file to a YUV image.
+///
+/// All PNG color types are supported: palette and low-bit-depth images are
+/// expanded to 8 bits, with `tRNS` transparency becoming alpha. 16-bit
+/// images produce 10-bit YUV. Color images use `Yuv444` and grayscale
+/// images `Yuv400`. The color chunks of the file select the CICP values or
+/// ICC profile of the image, and Exif metadata is carried over. Only the
+/// default image of an animated PNG is decoded.
+///
+/// # Arguments
+/// * `reader` - The PNG data
+///
+/// # Returns
+/// The image, or an error if the PNG cannot be decoded.
+#[cfg(feature = "png")]
+pub fn png_to_image<R: BufRead + Seek>(reader: R) -> Result<Image> {
+    decode_png(reader, false)
+}
+
+/// Decodes a PNG file to a YUV image that preserves every decoded sample.
+///
+/// The file is decoded as described for [`png_to_image`], except that color
+/// images use `Yuv444` with full range and identity matrix coefficients, as
+/// `RgbImage::to_lossless_yuv_image` does. Encode the result with
+/// `EncoderBuilder::lossless` to produce a lossless AVIF. 16-bit images are
+/// still reduced to 10 bits.
+///
+/// # Arguments
+/// * `reader` - The PNG data
+///
+/// # Returns
+/// The image, or an error if the PNG cannot be decoded.
+#[cfg(feature = "png")]
+pub fn png_to_lossless_image<R: BufRead + Seek>(reader: R) -> Result<Image> {
+    decode_png(reader, true)
+}
+
+#[cfg(feature = "png")]
+fn decode_png<R: BufRead + Seek>(reader: R, lossless: bool) -> Result<Image> {
+    let mut decoder = png::Decoder::new(reader);
+    decoder.set_transformations(png::Transformations::EXPAND);
+    decoder.set_ignore_iccp_chunk(false);
+    let mut reader = decoder.read_info().map_err(png_error)?;
+    let size = reader.output_buffer_size().ok_or(AvifError::OutOfMemory)?;
+    let mut buf = vec![0u8; size];
+    let frame = reader.next_frame(&mut buf).map_err(png_error)?;
+    buf.truncate(frame.buffer_size());
+
+    let (color_type, bit_depth) = reader.output_color_type();
+    let format = match color_type {
+        png::ColorType::Grayscale => RgbFormat::Gray,
+        png::ColorType::GrayscaleAlpha => RgbFormat::GrayA,
+        png::ColorType::Rgb => RgbFormat::Rgb,
+        png::ColorType::Rgba => RgbFormat::Rgba,
+        png::ColorType::Indexed => return Err(AvifError::NotImplemented),
+    };
+    let wide = bit_depth == png::BitDepth::Sixteen;
+    if wide {
+        // PNG stores 16-bit samples big-endian.
+        for sample in buf.chunks_exact_mut(2) {
+            let value = u16::from_be_bytes([sample[0], sample[1]]);
+            sample.copy_from_slice(&value.to_ne_bytes());
+        }
+    }
+
+    let mut image = image_from_rgb(
+        &buf,
+        frame.width,
+        frame.height,
+        format,
+        wide,
+        PixelFormat::Yuv444,
+        lossless,
+    )?;
+    let info = reader.info();
+    set_png_color(&mut image, info)?;
+    if let Some(exif) = &info.exif_metadata {
+        image.set_exif(exif)?;
+    }
+    Ok(image)
+}
+
+/// Converts a PNG file to AVIF.
+///
+/// The file is decoded as described for [`png_to_image`].
+///
+/// # Arguments
+/// * `reader` - The PNG data
+/// * `settings` - Encoder settings for the output
+///
+/// # Returns
+/// The AVIF data, or an error if the PNG cannot be decoded or encoding
+/// fails.
+#[cfg(feature = "png")]
+pub fn png_to_avif<R: BufRead + Seek>(reader: R, settings: &EncoderBuilder) -> Result<RwData> {
+    let image = png_to_image(reader)?;
+    settings.build()?.write(&image)
+}
+
+/// Wraps a JPEG decoding error with the operation and its message.
+#[cfg(feature = "jpeg")]
+fn jpeg_error(error: zune_jpeg::errors::DecodeErrors) -> AvifError {
+    AvifError::InvalidArgument.with_detail(Operation::ReadInput, error.to_string())
+}
+
+/// Chooses the chroma subsampling matching a JPEG's frame header.
+///
+/// Baseline JPEG's 4:2:0 and 4:2:2 layouts keep their subsampling so the
+/// output is not larger than necessary; any other layout uses `Yuv444`.
+#[cfg(feature = "jpeg")]
+fn jpeg_yuv_format(data: &[u8]) -> PixelFormat {
+    // Skip the SOI marker, then walk the marker segments up to the frame
+    // header.
+    let mut pos = 2;
+    while let Some(&[0xFF, marker, high, low]) = data.get(pos..pos + 4) {
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        let length = u16::from_be_bytes([high, low]) as usize;
+        let is_frame = matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+        if !is_frame {
+            pos += 2 + length;
+            continue;
+        }
+        // Precision, height, width and component count precede three bytes
+        // per component, the second holding the sampling factors.
+        let Some(header) = data.get(pos + 4..pos + 2 + length) else {
+            break;
+        };
+        let sampling: Vec<u8> = header.iter().skip(7).step_by(3).copied().collect();
+        return match sampling.as_slice() {
+            [0x22, 0x11, 0x11] => PixelFormat::Yuv420,
+            [0x21, 0x11, 0x11] => PixelFormat::Yuv422,
+            [_] => PixelFormat::Yuv400,
+            _ => PixelFormat::Yuv444,
+        };
+    }
+    PixelFormat::Yuv444
+}
+
+/// Decodes a JPEG file to a YUV image.
+///
+/// The image keeps the source's chroma subsampling when it is 4:2:0 or
+/// 4:2:2 and uses `Yuv444` otherwise; grayscale images use `Yuv400`. The
+/// ICC profile and Exif and XMP metadata are carried over, and the Exif
+/// orientation is moved to the `irot` and `imir` properties. Without an ICC
+/// profile the image is tagged as sRGB.
+///
+/// # Arguments
+/// * `reader` - The JPEG data
+///
+/// # Returns
+/// The image, or an error if the JPEG cannot be decoded.
+#[cfg(feature = "jpeg")]
+pub fn jpeg_to_image<R: Read>(reader: R) -> Result<Image> {
+    decode_jpeg(reader, false)
+}
+
+/// Decodes a JPEG file to a YUV image that preserves every decoded sample.
+///
+/// The file is decoded as described for [`jpeg_to_image`], except that
+/// color images use `Yuv444` with full range and identity matrix
+/// coefficients, so encoding the result with `EncoderBuilder::lossless`
+/// stores the decoded pixels exactly.
+///
+/// # Arguments
+/// * `reader` - The JPEG data
+///
+/// # Returns
+/// The image, or an error if the JPEG cannot be decoded.
+#[cfg(feature = "jpeg")]
+pub fn jpeg_to_lossless_image<R: Read>(reader: R) -> Result<Image> {
+    decode_jpeg(reader, true)
+}
+
+#[cfg(feature = "jpeg")]
+fn decode_jpeg<R: Read>(mut reader: R, lossless: bool) -> Result<Image> {
+    let mut data = Vec::new();
+    reader
+        .read_to_end(&mut data)
+        .map_err(|_| AvifError::IoError.context(Operation::ReadInput))?;
+
+    let mut decoder = zune_jpeg::JpegDecoder::new(ZCursor::new(data.as_slice()));
+    decoder.decode_headers().map_err(jpeg_error)?;
+    let format = if decoder.input_colorspace() == Some(ColorSpace::Luma) {
+        let options = decoder.options().jpeg_set_out_colorspace(ColorSpace::Luma);
+        decoder.set_options(options);
+        RgbFormat::Gray
+    } else {
+        RgbFormat::Rgb
+    };
+    let pixels = decoder.decode().map_err(jpeg_error)?;
+    let info = decoder.info().ok_or(AvifError::UnknownError)?;
+
+    let mut image = image_from_rgb(
+        &pixels,
+        info.width.into(),
+        info.height.into(),
+        format,
+        false,
+        jpeg_yuv_format(&data),
+        lossless,
+    )?;
+    match decoder.icc_profile() {
+        Some(icc) => image.set_icc(&icc)?,
+        None => {
+            image.set_color_primaries(AVIF_COLOR_PRIMARIES_BT709 as avifColorPrimaries);
+            image.set_transfer_characteristics(
+                AVIF_TRANSFER_CHARACTERISTICS_SRGB as avifTransferCharacteristics,
+            );
+        }
+    }
+    if let Some(exif) = decoder.exif() {
+        image.set_exif(exif)?;
+        image.extract_exif_orientation()?;
+    }
+    if let Some(xmp) = decoder.xmp() {
+        image.set_xmp(xmp)?;
+    }
+    Ok(image)
+}
+
+/// Converts a JPEG file to AVIF.
+///
+/// The file is decoded as described for [`jpeg_to_image`].
+///
+/// # Arguments
+/// * `reader` - The JPEG data
+/// * `settings` - Encoder settings for the output
+///
+/// # Returns
+/// The AVIF data, or an error if the JPEG cannot be decoded or encoding
+/// fails.
+#[cfg(feature = "jpeg")]
+pub fn jpeg_to_avif<R: Read>(reader: R, settings: &EncoderBuilder) -> Result<RwData> {
+    let image = jpeg_to_image(reader)?;
+    settings.build()?.write(&image)
+}
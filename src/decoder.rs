@@ -0,0 +1,714 @@
+//! AVIF decoder functionality.
+//!
+//! This module provides the `Decoder` struct for decoding AVIF data back into
+//! YUV images.
+
+use crate::encoder::Frame;
+use crate::error::Operation;
+use crate::{AvifError, Channel, Image, Planes, Rect, Result, Rotation};
+use libavif_sys::*;
+use std::mem::ManuallyDrop;
+use std::ops;
+#[cfg(feature = "mmap")]
+use std::{fs::File, path::Path};
+
+/// Available codec choices for AVIF decoding.
+///
+/// Variants exist for the decoders enabled by the crate's codec features.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecoderCodecChoice {
+    /// Automatically select the best available codec
+    Auto,
+    /// Use the AOM AV1 decoder (reference implementation)
+    #[cfg(feature = "codec-aom")]
+    Aom,
+    /// Use the dav1d decoder (fast, assembly-optimized)
+    #[cfg(feature = "codec-dav1d")]
+    Dav1d,
+    /// Use the libgav1 decoder
+    #[cfg(feature = "codec-gav1")]
+    Libgav1,
+    /// A codec not enabled in this build or not known to this version of the
+    /// crate
+    Unknown(avifCodecChoice),
+}
+
+impl From<DecoderCodecChoice> for avifCodecChoice {
+    fn from(choice: DecoderCodecChoice) -> Self {
+        match choice {
+            DecoderCodecChoice::Auto => avifCodecChoice_AVIF_CODEC_CHOICE_AUTO,
+            #[cfg(feature = "codec-aom")]
+            DecoderCodecChoice::Aom => avifCodecChoice_AVIF_CODEC_CHOICE_AOM,
+            #[cfg(feature = "codec-dav1d")]
+            DecoderCodecChoice::Dav1d => avifCodecChoice_AVIF_CODEC_CHOICE_DAV1D,
+            #[cfg(feature = "codec-gav1")]
+            DecoderCodecChoice::Libgav1 => avifCodecChoice_AVIF_CODEC_CHOICE_LIBGAV1,
+            DecoderCodecChoice::Unknown(choice) => choice,
+        }
+    }
+}
+
+impl From<avifCodecChoice> for DecoderCodecChoice {
+    fn from(choice: avifCodecChoice) -> Self {
+        match choice {
+            #[cfg(feature = "codec-aom")]
+            avifCodecChoice_AVIF_CODEC_CHOICE_AOM => DecoderCodecChoice::Aom,
+            #[cfg(feature = "codec-dav1d")]
+            avifCodecChoice_AVIF_CODEC_CHOICE_DAV1D => DecoderCodecChoice::Dav1d,
+            #[cfg(feature = "codec-gav1")]
+            avifCodecChoice_AVIF_CODEC_CHOICE_LIBGAV1 => DecoderCodecChoice::Libgav1,
+            avifCodecChoice_AVIF_CODEC_CHOICE_AUTO => DecoderCodecChoice::Auto,
+            other => DecoderCodecChoice::Unknown(other),
+        }
+    }
+}
+
+/// Flags selecting which AVIF specification violations make decoding fail.
+///
+/// These flags can be combined using the bitwise OR operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrictFlags(u32);
+
+impl StrictFlags {
+    /// Accept files that violate the specification where possible
+    pub const DISABLED: Self = Self(avifStrictFlag_AVIF_STRICT_DISABLED as u32);
+    /// Require a `pixi` property on every image item
+    pub const PIXI_REQUIRED: Self = Self(avifStrictFlag_AVIF_STRICT_PIXI_REQUIRED as u32);
+    /// Require `clap` properties to be valid
+    pub const CLAP_VALID: Self = Self(avifStrictFlag_AVIF_STRICT_CLAP_VALID as u32);
+    /// Require an `ispe` property on alpha items
+    pub const ALPHA_ISPE_REQUIRED: Self =
+        Self(avifStrictFlag_AVIF_STRICT_ALPHA_ISPE_REQUIRED as u32);
+    /// All checks
+    pub const ENABLED: Self = Self(avifStrictFlag_AVIF_STRICT_ENABLED as u32);
+}
+
+impl StrictFlags {
+    /// Returns the raw flag bits.
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if all flags in `other` are set.
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl Default for StrictFlags {
+    fn default() -> Self {
+        Self::ENABLED
+    }
+}
+
+impl ops::BitOr for StrictFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self::Output {
+        Self(self.0 | other.0)
+    }
+}
+
+/// Timing of a decoded frame and of the sequence it belongs to.
+pub(crate) struct FrameInfo {
+    pub(crate) timescale: u64,
+    pub(crate) duration_in_timescales: u64,
+    pub(crate) image_count: u32,
+    pub(crate) repetition_count: i32,
+}
+
+/// Input that libavif reads from after `parse`.
+enum Source {
+    Buffer(Box<[u8]>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+/// Wraps an error opening or mapping an input file with its message.
+#[cfg(feature = "mmap")]
+fn io_error(error: std::io::Error) -> AvifError {
+    AvifError::IoError.with_detail(Operation::ReadInput, error.to_string())
+}
+
+impl ops::Deref for Source {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Source::Buffer(data) => data,
+            #[cfg(feature = "mmap")]
+            Source::Mapped(map) => map,
+        }
+    }
+}
+
+/// AVIF decoder for converting AVIF data to images.
+pub struct Decoder {
+    inner: *mut avifDecoder,
+    apply_orientation: bool,
+    /// Input given to `parse` or `parse_mmap`, which libavif reads from
+    /// until the next input is set.
+    source: Option<Source>,
+}
+
+impl Decoder {
+    /// Creates a new AVIF decoder.
+    ///
+    /// The decoder uses the crate's thread budget if one is set with
+    /// `set_thread_budget`.
+    ///
+    /// # Returns
+    /// A new decoder instance or an error if creation fails.
+    pub fn new() -> Result<Self> {
+        let inner = unsafe { avifDecoderCreate() };
+        if inner.is_null() {
+            return Err(AvifError::OutOfMemory);
+        }
+        let mut decoder = unsafe { Self::from_raw(inner) };
+        if let Some(threads) = crate::thread_budget() {
+            decoder.set_max_threads(threads);
+        }
+        Ok(decoder)
+    }
+
+    /// Takes ownership of a decoder created by libavif.
+    ///
+    /// Settings already made on the libavif decoder are kept.
+    ///
+    /// # Safety
+    /// `decoder` must be a valid, non-null pointer returned by
+    /// `avifDecoderCreate` of the libavif linked into this crate, and must
+    /// not be used or destroyed elsewhere afterwards. The decoder is
+    /// destroyed when the returned value is dropped.
+    pub unsafe fn from_raw(decoder: *mut avifDecoder) -> Self {
+        Self {
+            inner: decoder,
+            apply_orientation: false,
+            source: None,
+        }
+    }
+
+    /// Releases ownership of the underlying libavif decoder.
+    ///
+    /// Input given to `parse` is released, so the decoder must be given new
+    /// input before it decodes again.
+    ///
+    /// # Returns
+    /// The decoder, which the caller must destroy with `avifDecoderDestroy`
+    /// or pass back to `Decoder::from_raw`.
+    pub fn into_raw(mut self) -> *mut avifDecoder {
+        std::mem::replace(&mut self.inner, std::ptr::null_mut())
+    }
+
+    /// Returns the underlying libavif decoder without giving up ownership.
+    ///
+    /// The pointer is valid as long as this decoder is, and must not be
+    /// destroyed.
+    pub fn as_raw(&self) -> *const avifDecoder {
+        self.inner
+    }
+
+    /// Returns the underlying libavif decoder for modification without
+    /// giving up ownership.
+    ///
+    /// The pointer is valid as long as this decoder is, and must not be
+    /// destroyed.
+    pub fn as_raw_mut(&mut self) -> *mut avifDecoder {
+        self.inner
+    }
+
+    /// Sets the maximum number of threads to use for decoding.
+    ///
+    /// # Arguments
+    /// * `threads` - Number of threads (clamped to 1024, or 1 on WebAssembly)
+    pub fn set_max_threads(&mut self, threads: u32) {
+        unsafe {
+            (*self.inner).maxThreads = threads.min(crate::MAX_THREADS) as i32;
+        }
+    }
+
+    /// Returns the codec used for decoding.
+    pub fn codec_choice(&self) -> DecoderCodecChoice {
+        unsafe { (*self.inner).codecChoice }.into()
+    }
+
+    /// Sets the codec used for decoding.
+    ///
+    /// Decoding fails with `AvifError::NoCodecAvailable` if the chosen codec
+    /// is not compiled into libavif. The default is `DecoderCodecChoice::Auto`.
+    ///
+    /// # Arguments
+    /// * `choice` - The codec to use
+    pub fn set_codec_choice(&mut self, choice: DecoderCodecChoice) {
+        unsafe {
+            (*self.inner).codecChoice = choice.into();
+        }
+    }
+
+    /// Sets which specification violations make decoding fail.
+    ///
+    /// All checks are enabled by default.
+    ///
+    /// # Arguments
+    /// * `flags` - The checks to enable
+    pub fn set_strict_flags(&mut self, flags: StrictFlags) {
+        unsafe {
+            (*self.inner).strictFlags = flags.bits() as avifStrictFlags;
+        }
+    }
+
+    /// Sets the maximum number of pixels in an image.
+    ///
+    /// Larger images fail to decode, which protects against decompression
+    /// bombs. The default, which is also the maximum, is 16384 x 16384.
+    ///
+    /// # Arguments
+    /// * `pixels` - The limit (clamped to between 1 and the default)
+    pub fn set_image_size_limit(&mut self, pixels: u32) {
+        unsafe {
+            (*self.inner).imageSizeLimit = pixels.clamp(1, AVIF_DEFAULT_IMAGE_SIZE_LIMIT);
+        }
+    }
+
+    /// Sets the maximum width or height of an image.
+    ///
+    /// libavif's default is 32768.
+    ///
+    /// # Arguments
+    /// * `dimension` - The limit in pixels, or 0 for no limit
+    pub fn set_image_dimension_limit(&mut self, dimension: u32) {
+        unsafe {
+            (*self.inner).imageDimensionLimit = dimension;
+        }
+    }
+
+    /// Sets the maximum number of images in an image sequence.
+    ///
+    /// libavif's default is 3600 x 60.
+    ///
+    /// # Arguments
+    /// * `count` - The limit, or 0 for no limit
+    pub fn set_image_count_limit(&mut self, count: u32) {
+        unsafe {
+            (*self.inner).imageCountLimit = count;
+        }
+    }
+
+    /// Sets whether decoded images are returned already oriented.
+    ///
+    /// When enabled, `decode` rotates and mirrors the pixels according to the
+    /// `irot` and `imir` properties, or the Exif orientation if neither is
+    /// present, so consumers need not implement orientation themselves. See
+    /// `Image::apply_orientation`. Disabled by default.
+    ///
+    /// # Arguments
+    /// * `apply` - Whether to apply the orientation
+    pub fn set_apply_orientation(&mut self, apply: bool) {
+        self.apply_orientation = apply;
+    }
+
+    /// Decodes the first image of an AVIF file held in memory.
+    ///
+    /// # Arguments
+    /// * `data` - The encoded AVIF data
+    ///
+    /// # Returns
+    /// The decoded image, or an error if decoding fails.
+    pub fn decode(&mut self, data: &[u8]) -> Result<Image> {
+        self.source = None;
+        let inner = unsafe { avifImageCreateEmpty() };
+        if inner.is_null() {
+            return Err(AvifError::OutOfMemory);
+        }
+        let mut image = Image { inner };
+        let result =
+            unsafe { avifDecoderReadMemory(self.inner, image.inner, data.as_ptr(), data.len()) };
+        if result != avifResult_AVIF_RESULT_OK {
+            return Err(self.diagnostic_error(Operation::Decode, result));
+        }
+        if self.apply_orientation {
+            image.apply_orientation()?;
+        }
+        Ok(image)
+    }
+
+    /// Decodes the first image of an AVIF file held in memory, scaled down
+    /// to fit within the given size.
+    ///
+    /// The image is scaled straight from the codec's output, so no
+    /// full-resolution copy is made, which keeps thumbnailing large images
+    /// cheap. The aspect ratio is kept, and images that already fit are not
+    /// enlarged. When orientation is applied, the bounds refer to the
+    /// displayed orientation. ICC, Exif and XMP metadata and the other
+    /// properties are kept, except that a scaled image drops its clean
+    /// aperture, which refers to the full-size image. Scaling requires
+    /// libyuv, which the `libyuv` feature enables.
+    ///
+    /// # Arguments
+    /// * `data` - The encoded AVIF data
+    /// * `max_width` - Largest width of the result in pixels
+    /// * `max_height` - Largest height of the result in pixels
+    ///
+    /// # Returns
+    /// The decoded image, or an error if a bound is 0 or decoding or scaling
+    /// fails.
+    pub fn decode_scaled(&mut self, data: &[u8], max_width: u32, max_height: u32) -> Result<Image> {
+        if max_width == 0 || max_height == 0 {
+            return Err(AvifError::InvalidArgument);
+        }
+        self.source = None;
+        self.parse_memory(data)?;
+        let result = unsafe { avifDecoderNextImage(self.inner) };
+        if result != avifResult_AVIF_RESULT_OK {
+            return Err(self.diagnostic_error(Operation::Decode, result));
+        }
+
+        // The decoder keeps ownership of its image.
+        let decoded = ManuallyDrop::new(Image {
+            inner: unsafe { (*self.inner).image },
+        });
+        let (width, height) = (decoded.width(), decoded.height());
+        let (max_width, max_height) = match decoded.rotation() {
+            Rotation::Ccw90 | Rotation::Ccw270 if self.apply_orientation => (max_height, max_width),
+            _ => (max_width, max_height),
+        };
+        let scale = (max_width as f64 / width as f64).min(max_height as f64 / height as f64);
+        let mut image = if scale >= 1.0 {
+            decoded.copy()?
+        } else {
+            // Copy the metadata, which a view leaves out, then borrow the
+            // decoder's planes; scaling gives the image planes of its own at
+            // the new size.
+            let inner = unsafe { avifImageCreateEmpty() };
+            if inner.is_null() {
+                return Err(AvifError::OutOfMemory);
+            }
+            let mut image = Image { inner };
+            let result = unsafe { avifImageCopy(image.inner, decoded.inner, 0) };
+            if result != avifResult_AVIF_RESULT_OK {
+                return Err(AvifError::from(result).context(Operation::Scale));
+            }
+            let rect = avifCropRect::from(Rect::new(0, 0, width, height));
+            let result = unsafe { avifImageSetViewRect(image.inner, decoded.inner, &rect) };
+            if result != avifResult_AVIF_RESULT_OK {
+                return Err(AvifError::from(result).context(Operation::Scale));
+            }
+            let scaled_width = ((width as f64 * scale).round() as u32).clamp(1, max_width);
+            let scaled_height = ((height as f64 * scale).round() as u32).clamp(1, max_height);
+            image
+                .scale(scaled_width, scaled_height)
+                .map_err(|err| err.context(Operation::Scale))?;
+            // The clean aperture is in full-size coordinates. The pixel
+            // aspect ratio survives, as both axes are scaled alike.
+            unsafe {
+                (*image.inner).transformFlags &=
+                    !(avifTransformFlag_AVIF_TRANSFORM_CLAP as avifTransformFlags);
+                (*image.inner).clap = std::mem::zeroed();
+            }
+            image
+        };
+        if self.apply_orientation {
+            image.apply_orientation()?;
+        }
+        Ok(image)
+    }
+
+    /// Parses an AVIF file to decode its images one at a time.
+    ///
+    /// The decoder keeps the data until other input is given. Images are
+    /// then decoded in display order with `next_image_into`.
+    ///
+    /// # Arguments
+    /// * `data` - The encoded AVIF data
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if the data cannot be parsed.
+    pub fn parse(&mut self, data: impl Into<Box<[u8]>>) -> Result<()> {
+        self.parse_source(Source::Buffer(data.into()))
+    }
+
+    /// Creates a decoder that parses a memory-mapped AVIF file.
+    ///
+    /// The file is parsed with the default settings, before limits such as
+    /// `set_image_size_limit` or strictness flags can be set. For untrusted
+    /// files, create the decoder with `new`, configure it and then call
+    /// `parse_mmap` instead.
+    ///
+    /// # Arguments
+    /// * `path` - The AVIF file to map
+    ///
+    /// # Returns
+    /// A decoder ready for `next_image_into`, or an error if the file cannot
+    /// be mapped or parsed.
+    ///
+    /// # Safety
+    /// The same as for `parse_mmap`: the file must not be modified while the
+    /// decoder holds the mapping.
+    #[cfg(feature = "mmap")]
+    pub unsafe fn from_mmap(path: impl AsRef<Path>) -> Result<Self> {
+        let mut decoder = Self::new()?;
+        // SAFETY: upheld by the caller.
+        unsafe { decoder.parse_mmap(path)? };
+        Ok(decoder)
+    }
+
+    /// Parses an AVIF file mapped into memory to decode its images one at a
+    /// time.
+    ///
+    /// libavif reads the mapping directly instead of a copy of the file, so
+    /// only the pages it touches are loaded and the operating system can
+    /// evict them again. This keeps peak memory low when decoding large
+    /// grids or long sequences from disk. The mapping is kept until other
+    /// input is given.
+    ///
+    /// # Arguments
+    /// * `path` - The AVIF file to map
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if the file cannot be mapped or parsed.
+    /// Errors opening or mapping the file carry the operating system's
+    /// message as detail.
+    ///
+    /// # Safety
+    /// The file must not be modified or truncated, by this or any other
+    /// process, until the decoder is given other input or dropped. libavif
+    /// reads the mapped pages as they are, so a concurrent change is
+    /// undefined behavior and a truncation can crash the process.
+    #[cfg(feature = "mmap")]
+    pub unsafe fn parse_mmap(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        self.source = None;
+        let file = File::open(path).map_err(io_error)?;
+        // SAFETY: the mapping is only read, and the caller guarantees the
+        // file is not modified while the decoder holds it.
+        let map = unsafe { memmap2::Mmap::map(&file) }.map_err(io_error)?;
+        self.parse_source(Source::Mapped(map))
+    }
+
+    /// Makes `source` the decoder's input and parses it.
+    fn parse_source(&mut self, source: Source) -> Result<()> {
+        self.source = Some(source);
+        let data = self.source.as_deref().unwrap_or_default();
+        let result = self.parse_memory(data);
+        if result.is_err() {
+            self.source = None;
+        }
+        result
+    }
+
+    /// Points the decoder at `data` and parses it.
+    ///
+    /// `data` must outlive the decoder's use of it: until other input is
+    /// given, or until the current call returns when it is borrowed.
+    fn parse_memory(&self, data: &[u8]) -> Result<()> {
+        let result = unsafe { avifDecoderSetIOMemory(self.inner, data.as_ptr(), data.len()) };
+        if result != avifResult_AVIF_RESULT_OK {
+            return Err(AvifError::from(result).context(Operation::Parse));
+        }
+        let result = unsafe { avifDecoderParse(self.inner) };
+        if result != avifResult_AVIF_RESULT_OK {
+            return Err(self.diagnostic_error(Operation::Parse, result));
+        }
+        Ok(())
+    }
+
+    /// Returns the number of images in the file given to `parse`.
+    pub fn image_count(&self) -> u32 {
+        unsafe { (*self.inner).imageCount.max(0) as u32 }
+    }
+
+    /// Decodes the next image of the file given to `parse` into an existing
+    /// image.
+    ///
+    /// When `image` already has the decoded image's size, depth, YUV format
+    /// and planes, such as the previous frame of a sequence, its planes are
+    /// reused and only the samples and properties are copied. Otherwise its
+    /// planes are reallocated.
+    ///
+    /// # Arguments
+    /// * `image` - The image receiving the decoded pixels and properties
+    ///
+    /// # Returns
+    /// `true` if an image was decoded, `false` once no images remain, or an
+    /// error if decoding fails or `parse` has not succeeded.
+    pub fn next_image_into(&mut self, image: &mut Image) -> Result<bool> {
+        if self.source.is_none() {
+            return Err(AvifError::IoNotSet.context(Operation::Decode));
+        }
+        let result = unsafe { avifDecoderNextImage(self.inner) };
+        if result == avifResult_AVIF_RESULT_NO_IMAGES_REMAINING {
+            return Ok(false);
+        }
+        if result != avifResult_AVIF_RESULT_OK {
+            return Err(self.diagnostic_error(Operation::Decode, result));
+        }
+
+        // The decoder keeps ownership of its image.
+        let decoded = ManuallyDrop::new(Image {
+            inner: unsafe { (*self.inner).image },
+        });
+        if image.has_same_layout(&decoded) {
+            // Copying properties frees the destination's planes, so move
+            // them aside meanwhile.
+            let inner = unsafe { avifImageCreateEmpty() };
+            if inner.is_null() {
+                return Err(AvifError::OutOfMemory);
+            }
+            let mut planes = Image { inner };
+            image.steal_planes(&mut planes, Planes::ALL);
+            let result = unsafe { avifImageCopy(image.inner, decoded.inner, 0) };
+            planes.steal_planes(image, Planes::ALL);
+            if result != avifResult_AVIF_RESULT_OK {
+                return Err(AvifError::from(result).context(Operation::Decode));
+            }
+            for channel in [Channel::Y, Channel::U, Channel::V, Channel::A] {
+                copy_plane(&decoded, image, channel);
+            }
+        } else {
+            let result = unsafe { avifImageCopy(image.inner, decoded.inner, Planes::ALL.bits()) };
+            if result != avifResult_AVIF_RESULT_OK {
+                return Err(AvifError::from(result).context(Operation::Decode));
+            }
+        }
+        if self.apply_orientation {
+            image.apply_orientation()?;
+        }
+        Ok(true)
+    }
+
+    /// Decodes every frame of an AVIF file held in memory as shareable
+    /// frames.
+    ///
+    /// Each frame is copied out of the decoder, so it outlives the call and
+    /// can be cloned cheaply and handed to other threads. Durations are in
+    /// the file's timescale, which `timescale` returns afterwards. A still
+    /// image yields a single frame.
+    ///
+    /// # Arguments
+    /// * `data` - The encoded AVIF data
+    /// * `f` - Called with each frame, in display order
+    ///
+    /// # Returns
+    /// Ok(()) once every frame has been passed to `f`, or the first error
+    /// from decoding or from `f`.
+    pub fn decode_frames<F>(&mut self, data: &[u8], mut f: F) -> Result<()>
+    where
+        F: FnMut(Frame) -> Result<()>,
+    {
+        let apply_orientation = self.apply_orientation;
+        self.for_each_frame(data, |image, info| {
+            let mut image = image.copy()?;
+            if apply_orientation {
+                image.apply_orientation()?;
+            }
+            f(Frame::yuv(image, info.duration_in_timescales))
+        })
+    }
+
+    /// Returns the timescale of the most recently parsed file, in units per
+    /// second.
+    pub fn timescale(&self) -> u64 {
+        unsafe { (*self.inner).timescale }
+    }
+
+    /// Converts a failed result of a decoding call into an error carrying
+    /// the decoder's diagnostic message.
+    fn diagnostic_error(&self, op: Operation, result: avifResult) -> AvifError {
+        AvifError::with_diagnostics(op, result, unsafe { &(*self.inner).diag })
+    }
+
+    /// Decodes every frame of an AVIF file held in memory.
+    ///
+    /// The image passed to `f` is owned by the decoder and is only valid for
+    /// the duration of the call; it carries the file's CICP values and
+    /// metadata.
+    ///
+    /// # Arguments
+    /// * `data` - The encoded AVIF data
+    /// * `f` - Called with each frame, in display order
+    pub(crate) fn for_each_frame<F>(&mut self, data: &[u8], mut f: F) -> Result<()>
+    where
+        F: FnMut(&Image, &FrameInfo) -> Result<()>,
+    {
+        self.source = None;
+        let result = unsafe { avifDecoderSetIOMemory(self.inner, data.as_ptr(), data.len()) };
+        if result != avifResult_AVIF_RESULT_OK {
+            return Err(AvifError::from(result).context(Operation::Parse));
+        }
+        let result = unsafe { avifDecoderParse(self.inner) };
+        if result != avifResult_AVIF_RESULT_OK {
+            return Err(self.diagnostic_error(Operation::Parse, result));
+        }
+
+        loop {
+            let result = unsafe { avifDecoderNextImage(self.inner) };
+            if result == avifResult_AVIF_RESULT_NO_IMAGES_REMAINING {
+                return Ok(());
+            }
+            if result != avifResult_AVIF_RESULT_OK {
+                return Err(self.diagnostic_error(Operation::Decode, result));
+            }
+            let decoder = unsafe { &*self.inner };
+            let info = FrameInfo {
+                timescale: decoder.imageTiming.timescale,
+                duration_in_timescales: decoder.imageTiming.durationInTimescales,
+                image_count: decoder.imageCount.max(0) as u32,
+                repetition_count: decoder.repetitionCount,
+            };
+            // The decoder keeps ownership of its image.
+            let image = ManuallyDrop::new(Image {
+                inner: decoder.image,
+            });
+            f(&image, &info)?;
+        }
+    }
+}
+
+impl Image {
+    /// Returns true if both images have the same geometry and allocated
+    /// planes, and this image owns its planes.
+    fn has_same_layout(&self, other: &Image) -> bool {
+        let this = unsafe { &*self.inner };
+        this.imageOwnsYUVPlanes != 0
+            && (this.alphaPlane.is_null() || this.imageOwnsAlphaPlane != 0)
+            && self.width() == other.width()
+            && self.height() == other.height()
+            && self.depth() == other.depth()
+            && self.yuv_format() == other.yuv_format()
+            && [Channel::Y, Channel::U, Channel::V, Channel::A]
+                .into_iter()
+                .all(|channel| self.plane(channel).is_some() == other.plane(channel).is_some())
+    }
+}
+
+/// Copies the samples of one channel between images of the same layout.
+fn copy_plane(src: &Image, dst: &mut Image, channel: Channel) {
+    let (src_row_bytes, dst_row_bytes) = (
+        src.plane_row_bytes(channel) as usize,
+        dst.plane_row_bytes(channel) as usize,
+    );
+    let sample_size = if src.uses_u16() { 2 } else { 1 };
+    let width = src.plane_width(channel) as usize * sample_size;
+    let (Some(src), Some(dst)) = (src.plane(channel), dst.plane_mut(channel)) else {
+        return;
+    };
+    for (src_row, dst_row) in src.chunks(src_row_bytes).zip(dst.chunks_mut(dst_row_bytes)) {
+        dst_row[..width].copy_from_slice(&src_row[..width]);
+    }
+}
+
+impl Drop for Decoder {
+    fn drop(&mut self) {
+        if !self.inner.is_null() {
+            unsafe {
+                avifDecoderDestroy(self.inner);
+            }
+        }
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new().expect("Failed to create decoder")
+    }
+}
@@ -0,0 +1,97 @@
+//! Dithering for conversions that reduce sample precision.
+//!
+//! libavif rounds every sample to the nearest representable value, which
+//! produces visible banding in smooth gradients when the output has fewer bits
+//! than the source. The functions here quantize high-precision samples while
+//! spreading the rounding error, trading banding for fine noise.
+
+/// Dithering methods applied when RGB output has less precision than the
+/// converted image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dither {
+    /// Round to the nearest value
+    #[default]
+    None,
+    /// 4x4 Bayer matrix; fast and stable between frames
+    Ordered,
+    /// Floyd-Steinberg error diffusion; smoother, but sequential per image
+    FloydSteinberg,
+}
+
+/// 4x4 Bayer threshold matrix.
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Quantizes tightly packed 16-bit samples to the given number of bits per
+/// channel.
+///
+/// The alpha channel, if any, is rounded without dithering so that fully
+/// opaque and fully transparent areas stay exact.
+///
+/// # Arguments
+/// * `dither` - The dithering method
+/// * `samples` - Source samples, `width * bits.len()` per row
+/// * `width` - Image width in pixels
+/// * `bits` - Target bit count for each channel
+/// * `alpha` - Index of the alpha channel within a pixel
+///
+/// # Returns
+/// The quantized samples in the same layout as `samples`.
+pub(crate) fn quantize(
+    dither: Dither,
+    samples: &[u16],
+    width: usize,
+    bits: &[u32],
+    alpha: Option<usize>,
+) -> Vec<u16> {
+    let channels = bits.len();
+    let stride = width * channels;
+    let scales: Vec<f32> = bits
+        .iter()
+        .map(|&b| ((1u32 << b) - 1) as f32 / u16::MAX as f32)
+        .collect();
+
+    let mut output = vec![0u16; samples.len()];
+    let mut errors = vec![0.0f32; stride + 2 * channels];
+    let mut next_errors = vec![0.0f32; stride + 2 * channels];
+    for (y, (row, out)) in samples
+        .chunks_exact(stride)
+        .zip(output.chunks_exact_mut(stride))
+        .enumerate()
+    {
+        for x in 0..width {
+            for c in 0..channels {
+                let i = x * channels + c;
+                let max = ((1u32 << bits[c]) - 1) as f32;
+                let exact = row[i] as f32 * scales[c];
+                let value = if alpha == Some(c) {
+                    exact.round()
+                } else {
+                    match dither {
+                        Dither::None => exact.round(),
+                        Dither::Ordered => {
+                            let threshold = (BAYER[y % 4][x % 4] as f32 + 0.5) / 16.0;
+                            (exact + threshold - 0.5).round()
+                        }
+                        Dither::FloydSteinberg => {
+                            // Error buffers are offset by one pixel so that the
+                            // left neighbor of column 0 has a slot.
+                            let e = i + channels;
+                            let wanted = exact + errors[e];
+                            let value = wanted.round().clamp(0.0, max);
+                            let error = wanted - value;
+                            errors[e + channels] += error * 7.0 / 16.0;
+                            next_errors[e - channels] += error * 3.0 / 16.0;
+                            next_errors[e] += error * 5.0 / 16.0;
+                            next_errors[e + channels] += error / 16.0;
+                            value
+                        }
+                    }
+                };
+                out[i] = value.clamp(0.0, max) as u16;
+            }
+        }
+        std::mem::swap(&mut errors, &mut next_errors);
+        next_errors.fill(0.0);
+    }
+    output
+}
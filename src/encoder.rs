@@ -4,32 +4,313 @@
 //! to the AVIF format. The encoder supports various configuration options including
 //! quality settings, codec selection, tiling, and animation support.
 
-use crate::{AvifError, Image, Result, RwData};
+use crate::codec::CodecOptions;
+use crate::error::Operation;
+use crate::par;
+use crate::transform::read_sample;
+use crate::{
+    AvifError, Channel, ChromaDownsampling, Decoder, Image, OwnedRgbImage, PixelFormat, Rect,
+    Result, RgbImage, RwData,
+};
 use libavif_sys::*;
-use std::{ffi::CString, ops};
+use std::{
+    ffi::{CStr, CString},
+    io, ops,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// Largest coded image width allowed by the AVIF Baseline profile.
+const MAX_CELL_WIDTH: u32 = 8192;
+/// Largest coded image height allowed by the AVIF Baseline profile.
+const MAX_CELL_HEIGHT: u32 = 4352;
+/// Alignment of grid cell sizes, which keeps cells at least 64 pixels wide
+/// and aligned to any chroma subsampling.
+const CELL_ALIGNMENT: u32 = 64;
+
+/// Rough single-threaded encode cost in nanoseconds per pixel at each speed,
+/// used to pick a speed that fits a deadline. The values err on the slow side
+/// so that the chosen speed usually leaves some headroom.
+const ENCODE_COST_NS_PER_PIXEL: [u64; 11] = [
+    40_000, 12_000, 5_000, 2_500, 1_200, 600, 300, 150, 80, 50, 30,
+];
+
+/// Speed libavif's codecs use when none is set.
+const DEFAULT_SPEED: u8 = 6;
+
+/// Returns the cell size of a grid splitting an image into `cols` x `rows`
+/// cells of equal size, except that the last column and row take the
+/// remainder.
+///
+/// # Returns
+/// The cell width and height, or `AvifError::InvalidImageGrid` if the grid
+/// has more than 256 columns or rows, which HEIF cannot describe, or if
+/// cells before the last column or row would be smaller than 64x64 pixels or
+/// not aligned to the chroma subsampling of `yuv_format`.
+pub(crate) fn grid_cell_size(
+    width: u32,
+    height: u32,
+    cols: u32,
+    rows: u32,
+    yuv_format: PixelFormat,
+) -> Result<(u32, u32)> {
+    if !(1..=256).contains(&cols) || !(1..=256).contains(&rows) {
+        return Err(AvifError::InvalidImageGrid);
+    }
+    let cell_width = width.div_ceil(cols);
+    let cell_height = height.div_ceil(rows);
+    let (shift_x, shift_y) = yuv_format.chroma_shift().unwrap_or((0, 0));
+    let valid = |size: u32, count: u32, cell: u32, shift: u32| {
+        count == 1 || (cell >= 64 && (count - 1) * cell < size && cell.is_multiple_of(1 << shift))
+    };
+    if !valid(width, cols, cell_width, shift_x) || !valid(height, rows, cell_height, shift_y) {
+        return Err(AvifError::InvalidImageGrid);
+    }
+    Ok((cell_width, cell_height))
+}
+
+/// Quantizer for lossless encoding.
+pub const QUANTIZER_LOSSLESS: u8 = AVIF_QUANTIZER_LOSSLESS as u8;
+/// Quantizer giving the best quality.
+pub const QUANTIZER_BEST_QUALITY: u8 = AVIF_QUANTIZER_BEST_QUALITY as u8;
+/// Quantizer giving the worst quality.
+pub const QUANTIZER_WORST_QUALITY: u8 = AVIF_QUANTIZER_WORST_QUALITY as u8;
+/// Quality for lossless encoding.
+pub const QUALITY_LOSSLESS: u8 = AVIF_QUALITY_LOSSLESS as u8;
+/// Best quality.
+pub const QUALITY_BEST: u8 = AVIF_QUALITY_BEST as u8;
+/// Worst quality.
+pub const QUALITY_WORST: u8 = AVIF_QUALITY_WORST as u8;
+
+/// Converts a quality (0-100) to the quantizer (0-63) libavif uses for it.
+///
+/// This is the mapping libavif applies when a quality is set, so the result
+/// can be passed to `Encoder::set_quantizer_range` to pin the quantizer.
+pub fn quantizer_from_quality(quality: u8) -> u8 {
+    let quality = quality.min(QUALITY_BEST) as u32;
+    let worst = QUANTIZER_WORST_QUALITY as u32;
+    (((100 - quality) * worst + 50) / 100) as u8
+}
+
+/// Converts a quantizer (0-63) to the nearest equivalent quality (0-100).
+///
+/// This is the inverse of `quantizer_from_quality`, up to rounding.
+pub fn quality_from_quantizer(quantizer: u8) -> u8 {
+    let worst = QUANTIZER_WORST_QUALITY as u32;
+    let quantizer = (quantizer as u32).min(worst);
+    (((worst - quantizer) * 100 + worst / 2) / worst) as u8
+}
+
+/// JPEG qualities and the AVIF qualities giving similar perceived quality on
+/// photographic content, interpolated linearly in between.
+const JPEG_QUALITY_MAP: [(u8, u8); 8] = [
+    (0, 0),
+    (20, 22),
+    (50, 45),
+    (75, 60),
+    (85, 67),
+    (90, 73),
+    (95, 82),
+    (100, 94),
+];
+
+/// Maps a JPEG quality to the AVIF quality of similar perceived quality.
+///
+/// AVIF qualities are not comparable with JPEG qualities of the same number:
+/// encoding at the JPEG value typically wastes bytes at high settings. The
+/// mapping is an approximation for photographic content, such as JPEG 85
+/// mapping to AVIF 67; verify with `Image::ssim` where it matters.
+///
+/// # Arguments
+/// * `quality` - JPEG quality from 0 to 100
+pub fn quality_from_jpeg(quality: u8) -> u8 {
+    let quality = quality.min(100);
+    let upper = JPEG_QUALITY_MAP
+        .iter()
+        .position(|&(jpeg, _)| jpeg >= quality)
+        .unwrap_or(JPEG_QUALITY_MAP.len() - 1);
+    if upper == 0 {
+        return JPEG_QUALITY_MAP[0].1;
+    }
+    let (jpeg0, avif0) = JPEG_QUALITY_MAP[upper - 1];
+    let (jpeg1, avif1) = JPEG_QUALITY_MAP[upper];
+    let t = (quality - jpeg0) as f32 / (jpeg1 - jpeg0) as f32;
+    (avif0 as f32 + t * (avif1 - avif0) as f32).round() as u8
+}
 
 /// Available codec choices for AVIF encoding.
 ///
 /// Different codecs may have different performance characteristics,
 /// quality profiles, and feature support.
+#[non_exhaustive]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EncoderCodecChoice {
     /// Automatically select the best available codec
-    Auto = avifCodecChoice_AVIF_CODEC_CHOICE_AUTO as isize,
+    Auto,
     /// Use the AOM AV1 encoder (reference implementation)
     #[cfg(feature = "codec-aom")]
-    Aom = avifCodecChoice_AVIF_CODEC_CHOICE_AOM as isize,
+    Aom,
     /// Use the Rav1e encoder (Rust implementation)
     #[cfg(feature = "codec-rav1e")]
-    Rav1e = avifCodecChoice_AVIF_CODEC_CHOICE_RAV1E as isize,
+    Rav1e,
     /// Use the SVT-AV1 encoder (optimized for speed)
     #[cfg(feature = "codec-svt")]
-    Svt = avifCodecChoice_AVIF_CODEC_CHOICE_SVT as isize,
+    Svt,
+    /// A codec not enabled in this build or not known to this version of the
+    /// crate
+    Unknown(avifCodecChoice),
 }
 
 impl From<EncoderCodecChoice> for avifCodecChoice {
     fn from(choice: EncoderCodecChoice) -> Self {
-        choice as _
+        match choice {
+            EncoderCodecChoice::Auto => avifCodecChoice_AVIF_CODEC_CHOICE_AUTO,
+            #[cfg(feature = "codec-aom")]
+            EncoderCodecChoice::Aom => avifCodecChoice_AVIF_CODEC_CHOICE_AOM,
+            #[cfg(feature = "codec-rav1e")]
+            EncoderCodecChoice::Rav1e => avifCodecChoice_AVIF_CODEC_CHOICE_RAV1E,
+            #[cfg(feature = "codec-svt")]
+            EncoderCodecChoice::Svt => avifCodecChoice_AVIF_CODEC_CHOICE_SVT,
+            EncoderCodecChoice::Unknown(choice) => choice,
+        }
+    }
+}
+
+impl From<avifCodecChoice> for EncoderCodecChoice {
+    fn from(choice: avifCodecChoice) -> Self {
+        match choice {
+            #[cfg(feature = "codec-aom")]
+            avifCodecChoice_AVIF_CODEC_CHOICE_AOM => EncoderCodecChoice::Aom,
+            #[cfg(feature = "codec-rav1e")]
+            avifCodecChoice_AVIF_CODEC_CHOICE_RAV1E => EncoderCodecChoice::Rav1e,
+            #[cfg(feature = "codec-svt")]
+            avifCodecChoice_AVIF_CODEC_CHOICE_SVT => EncoderCodecChoice::Svt,
+            avifCodecChoice_AVIF_CODEC_CHOICE_AUTO => EncoderCodecChoice::Auto,
+            other => EncoderCodecChoice::Unknown(other),
+        }
+    }
+}
+
+/// Container header formats the encoder can write.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderFormat {
+    /// Regular AVIF header with a full `meta` box
+    Default = avifHeaderFormat_AVIF_HEADER_DEFAULT as isize,
+    /// Minimized header (`mini` box), which saves a few hundred bytes per
+    /// file but is only understood by recent decoders. Writing it needs the
+    /// `mini` feature.
+    Mini = avifHeaderFormat_AVIF_HEADER_MINI as isize,
+}
+
+impl From<HeaderFormat> for avifHeaderFormatFlags {
+    fn from(format: HeaderFormat) -> Self {
+        format as _
+    }
+}
+
+impl From<avifHeaderFormatFlags> for HeaderFormat {
+    fn from(format: avifHeaderFormatFlags) -> Self {
+        if format as avifHeaderFormat & avifHeaderFormat_AVIF_HEADER_MINI != 0 {
+            HeaderFormat::Mini
+        } else {
+            HeaderFormat::Default
+        }
+    }
+}
+
+/// Sample transforms that store images deeper than 12 bits as two coded
+/// images combined by the decoder.
+#[cfg(feature = "sample-transform")]
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleTransform {
+    /// Encode the image as is
+    None,
+    /// Split 16-bit samples into an 8-bit most significant and an 8-bit
+    /// least significant image
+    BitDepthExtension8b8b,
+    /// Split 16-bit samples into a 12-bit most significant and a 4-bit
+    /// least significant image
+    BitDepthExtension12b4b,
+    /// Split 16-bit samples into a 12-bit most significant and an 8-bit
+    /// least significant image overlapping by 4 bits, which tolerates lossy
+    /// coding of the former
+    BitDepthExtension12b8bOverlap4b,
+    /// A recipe not known to this version of the crate
+    Unknown(avifSampleTransformRecipe),
+}
+
+#[cfg(feature = "sample-transform")]
+impl From<SampleTransform> for avifSampleTransformRecipe {
+    fn from(transform: SampleTransform) -> Self {
+        match transform {
+            SampleTransform::None => avifSampleTransformRecipe_AVIF_SAMPLE_TRANSFORM_NONE,
+            SampleTransform::BitDepthExtension8b8b => {
+                avifSampleTransformRecipe_AVIF_SAMPLE_TRANSFORM_BIT_DEPTH_EXTENSION_8B_8B
+            }
+            SampleTransform::BitDepthExtension12b4b => {
+                avifSampleTransformRecipe_AVIF_SAMPLE_TRANSFORM_BIT_DEPTH_EXTENSION_12B_4B
+            }
+            SampleTransform::BitDepthExtension12b8bOverlap4b => {
+                avifSampleTransformRecipe_AVIF_SAMPLE_TRANSFORM_BIT_DEPTH_EXTENSION_12B_8B_OVERLAP_4B
+            }
+            SampleTransform::Unknown(recipe) => recipe,
+        }
+    }
+}
+
+#[cfg(feature = "sample-transform")]
+impl From<avifSampleTransformRecipe> for SampleTransform {
+    fn from(recipe: avifSampleTransformRecipe) -> Self {
+        match recipe {
+            avifSampleTransformRecipe_AVIF_SAMPLE_TRANSFORM_NONE => SampleTransform::None,
+            avifSampleTransformRecipe_AVIF_SAMPLE_TRANSFORM_BIT_DEPTH_EXTENSION_8B_8B => {
+                SampleTransform::BitDepthExtension8b8b
+            }
+            avifSampleTransformRecipe_AVIF_SAMPLE_TRANSFORM_BIT_DEPTH_EXTENSION_12B_4B => {
+                SampleTransform::BitDepthExtension12b4b
+            }
+            avifSampleTransformRecipe_AVIF_SAMPLE_TRANSFORM_BIT_DEPTH_EXTENSION_12B_8B_OVERLAP_4B => {
+                SampleTransform::BitDepthExtension12b8bOverlap4b
+            }
+            other => SampleTransform::Unknown(other),
+        }
+    }
+}
+
+/// How many times an animation is repeated after it is first played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RepetitionCount {
+    /// Loop forever
+    #[default]
+    Infinite,
+    /// Repeat this many times, so `Finite(0)` plays the animation once
+    Finite(u32),
+}
+
+impl TryFrom<RepetitionCount> for i32 {
+    type Error = AvifError;
+
+    fn try_from(count: RepetitionCount) -> Result<Self> {
+        match count {
+            RepetitionCount::Infinite => Ok(AVIF_REPETITION_COUNT_INFINITE),
+            RepetitionCount::Finite(count) => {
+                i32::try_from(count).map_err(|_| AvifError::InvalidArgument)
+            }
+        }
+    }
+}
+
+impl From<i32> for RepetitionCount {
+    fn from(count: i32) -> Self {
+        match u32::try_from(count) {
+            Ok(count) => RepetitionCount::Finite(count),
+            Err(_) => RepetitionCount::Infinite,
+        }
     }
 }
 
@@ -54,6 +335,45 @@ impl AddImageFlags {
     pub fn bits(&self) -> u32 {
         self.0
     }
+
+    /// Returns all known flags combined.
+    pub const fn all() -> Self {
+        Self(Self::FORCE_KEYFRAME.0 | Self::SINGLE.0)
+    }
+
+    /// Converts raw bits into flags, or returns `None` if unknown bits are set.
+    pub const fn from_bits(bits: u32) -> Option<Self> {
+        if bits & !Self::all().0 == 0 {
+            Some(Self(bits))
+        } else {
+            None
+        }
+    }
+
+    /// Converts raw bits into flags, dropping unknown bits.
+    pub const fn from_bits_truncate(bits: u32) -> Self {
+        Self(bits & Self::all().0)
+    }
+
+    /// Returns true if no flags are set.
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns true if all flags in `other` are set.
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Sets the flags in `other`.
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    /// Clears the flags in `other`.
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
 }
 
 impl Default for AddImageFlags {
@@ -70,6 +390,664 @@ impl ops::BitOr for AddImageFlags {
     }
 }
 
+impl ops::BitOrAssign for AddImageFlags {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+impl ops::BitAnd for AddImageFlags {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self::Output {
+        Self(self.0 & other.0)
+    }
+}
+
+impl ops::BitAndAssign for AddImageFlags {
+    fn bitand_assign(&mut self, other: Self) {
+        self.0 &= other.0;
+    }
+}
+
+/// A rational number, such as a scaling factor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fraction {
+    /// Numerator
+    pub n: i32,
+    /// Denominator
+    pub d: i32,
+}
+
+impl Fraction {
+    /// The fraction 1/1.
+    pub const ONE: Self = Self { n: 1, d: 1 };
+
+    /// Creates a new fraction.
+    pub fn new(n: i32, d: i32) -> Self {
+        Self { n, d }
+    }
+}
+
+impl From<Fraction> for avifFraction {
+    fn from(fraction: Fraction) -> Self {
+        avifFraction {
+            n: fraction.n,
+            d: fraction.d,
+        }
+    }
+}
+
+impl From<avifFraction> for Fraction {
+    fn from(fraction: avifFraction) -> Self {
+        Self::new(fraction.n, fraction.d)
+    }
+}
+
+/// Spatial scaling applied by the encoder before encoding a frame or layer.
+///
+/// Scaling a layer down lets a layered image carry a low-resolution preview;
+/// decoders upscale it back to the image size. Codecs support a limited set
+/// of factors, such as 1/2, 3/5, 4/5, 1/4, 3/4 and 1/8 with aom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScalingMode {
+    /// Horizontal scaling factor
+    pub horizontal: Fraction,
+    /// Vertical scaling factor
+    pub vertical: Fraction,
+}
+
+impl ScalingMode {
+    /// No scaling.
+    pub const NONE: Self = Self::uniform(Fraction::ONE);
+
+    /// Creates a scaling mode with the same factor in both directions.
+    pub const fn uniform(factor: Fraction) -> Self {
+        Self {
+            horizontal: factor,
+            vertical: factor,
+        }
+    }
+}
+
+impl Default for ScalingMode {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+impl From<ScalingMode> for avifScalingMode {
+    fn from(mode: ScalingMode) -> Self {
+        avifScalingMode {
+            horizontal: mode.horizontal.into(),
+            vertical: mode.vertical.into(),
+        }
+    }
+}
+
+impl From<avifScalingMode> for ScalingMode {
+    fn from(mode: avifScalingMode) -> Self {
+        Self {
+            horizontal: mode.horizontal.into(),
+            vertical: mode.vertical.into(),
+        }
+    }
+}
+
+/// Size statistics of the last encode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EncodeStats {
+    /// Size in bytes of the encoded color data
+    pub color_obu_size: usize,
+    /// Size in bytes of the encoded alpha data
+    pub alpha_obu_size: usize,
+}
+
+impl From<avifIOStats> for EncodeStats {
+    fn from(stats: avifIOStats) -> Self {
+        Self {
+            color_obu_size: stats.colorOBUSize,
+            alpha_obu_size: stats.alphaOBUSize,
+        }
+    }
+}
+
+/// Settings for one layer of a layered (progressive) image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layer {
+    /// Spatial scaling of the layer
+    pub scaling_mode: ScalingMode,
+    /// Color quality of the layer, from 0 to 100
+    pub quality: u8,
+}
+
+/// A minimum objective quality for [`Encoder::encode_to_quality`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QualityTarget {
+    /// Minimum PSNR in dB, see `Image::psnr`
+    Psnr(f64),
+    /// Minimum SSIM from 0.0 to 1.0, see `Image::ssim`
+    Ssim(f64),
+}
+
+impl QualityTarget {
+    /// Returns true if the decoded image meets the target.
+    fn is_met(self, source: &Image, decoded: &Image) -> Result<bool> {
+        Ok(match self {
+            QualityTarget::Psnr(min) => source.psnr(decoded)? >= min,
+            QualityTarget::Ssim(min) => source.ssim(decoded)? >= min,
+        })
+    }
+}
+
+/// Progress of an encode, passed to the callback set with
+/// [`Encoder::set_progress_callback`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodeProgress {
+    /// Number of images (frames, layers or grids) added so far
+    pub images_added: u32,
+}
+
+/// Callback receiving encode progress; returns false to cancel.
+type ProgressCallback = Box<dyn FnMut(&EncodeProgress) -> bool>;
+
+/// A handle for cancelling an encode from another thread.
+///
+/// Cancellation is cooperative: the encoder checks the token before each
+/// image is added and before finishing, so an image already being encoded
+/// runs to completion.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Requests cancellation.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if cancellation has been requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// An animation frame for [`Encoder::encode_sequence`], as also produced by
+/// [`Decoder::decode_frames`](crate::Decoder::decode_frames).
+///
+/// The pixels are shared behind an `Arc`, so cloning a frame is cheap and
+/// lets pipeline stages hand the same frame to several workers without
+/// copying planes.
+#[derive(Clone)]
+pub struct Frame {
+    content: FrameContent,
+    duration_in_timescales: u64,
+}
+
+/// Pixel data of a [`Frame`].
+#[derive(Clone)]
+enum FrameContent {
+    Yuv(Arc<Image>),
+    Rgb(Arc<OwnedRgbImage>, PixelFormat),
+}
+
+impl Frame {
+    /// Creates a frame from a YUV image.
+    ///
+    /// # Arguments
+    /// * `image` - The frame's pixels, owned or already shared
+    /// * `duration_in_timescales` - Duration this frame should be displayed
+    pub fn yuv(image: impl Into<Arc<Image>>, duration_in_timescales: u64) -> Self {
+        Self {
+            content: FrameContent::Yuv(image.into()),
+            duration_in_timescales,
+        }
+    }
+
+    /// Creates a frame from an RGB image, converted to YUV when encoded.
+    ///
+    /// # Arguments
+    /// * `image` - The frame's pixels, owned or already shared
+    /// * `yuv_format` - The YUV format to encode the frame as
+    /// * `duration_in_timescales` - Duration this frame should be displayed
+    pub fn rgb(
+        image: impl Into<Arc<OwnedRgbImage>>,
+        yuv_format: PixelFormat,
+        duration_in_timescales: u64,
+    ) -> Self {
+        Self {
+            content: FrameContent::Rgb(image.into(), yuv_format),
+            duration_in_timescales,
+        }
+    }
+
+    /// Returns the frame's YUV image, or `None` for an RGB frame.
+    pub fn image(&self) -> Option<&Arc<Image>> {
+        match &self.content {
+            FrameContent::Yuv(image) => Some(image),
+            FrameContent::Rgb(..) => None,
+        }
+    }
+
+    /// Returns the frame's RGB image, or `None` for a YUV frame.
+    pub fn rgb_image(&self) -> Option<&Arc<OwnedRgbImage>> {
+        match &self.content {
+            FrameContent::Rgb(image, _) => Some(image),
+            FrameContent::Yuv(_) => None,
+        }
+    }
+
+    /// Returns the duration this frame should be displayed, in timescale
+    /// units.
+    pub fn duration_in_timescales(&self) -> u64 {
+        self.duration_in_timescales
+    }
+
+    /// Returns a copy of the frame with a different duration, sharing the
+    /// pixels.
+    ///
+    /// # Arguments
+    /// * `duration_in_timescales` - The new duration
+    pub fn with_duration(&self, duration_in_timescales: u64) -> Self {
+        Self {
+            content: self.content.clone(),
+            duration_in_timescales,
+        }
+    }
+}
+
+/// Statistics about a sequence frame, passed to a scene-change callback.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameStats {
+    /// Index of the frame in the sequence
+    pub index: u32,
+    /// Number of frames added since the last keyframe
+    pub frames_since_keyframe: u32,
+    /// Mean absolute luma difference from the previous frame, from 0 to 1,
+    /// estimated on a sparse grid of samples; `None` for the first frame or
+    /// after a size change
+    pub luma_difference: Option<f64>,
+}
+
+/// Callback deciding whether a frame starts a new scene.
+type SceneChangeCallback = Box<dyn FnMut(&FrameStats) -> bool>;
+
+/// Keyframe placement applied by `Encoder::encode_sequence`.
+///
+/// The first frame is always a keyframe. Further keyframes are forced when
+/// the maximum interval is reached or the scene-change callback reports a
+/// cut, in addition to those the codec places itself. More keyframes make
+/// long animations faster to seek at the cost of size.
+#[derive(Default)]
+pub struct KeyframePolicy {
+    max_interval: Option<u32>,
+    scene_change: Option<SceneChangeCallback>,
+}
+
+impl KeyframePolicy {
+    /// Creates a policy that forces no keyframes besides the first frame.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forces a keyframe at least every `interval` frames.
+    pub fn max_interval(mut self, interval: u32) -> Self {
+        self.max_interval = Some(interval.max(1));
+        self
+    }
+
+    /// Sets a callback forcing a keyframe when it returns true.
+    pub fn scene_change<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&FrameStats) -> bool + 'static,
+    {
+        self.scene_change = Some(Box::new(callback));
+        self
+    }
+}
+
+/// Spacing of the luma samples compared between frames.
+const LUMA_SAMPLE_STEP: usize = 8;
+
+/// Keyframe placement state of a sequence being encoded.
+struct KeyframeTracker {
+    policy: KeyframePolicy,
+    index: u32,
+    since_keyframe: u32,
+    previous_luma: Option<(u32, u32, Vec<u16>)>,
+}
+
+impl KeyframeTracker {
+    /// Returns the flags for the next frame and records it.
+    fn next(&mut self, image: &Image) -> AddImageFlags {
+        let luma = sample_luma(image);
+        let luma_difference = match (&self.previous_luma, &luma) {
+            (Some((width, height, previous)), Some(current))
+                if (*width, *height) == (image.width(), image.height()) && !current.is_empty() =>
+            {
+                let max = ((1u32 << u32::from(image.depth())) - 1) as f64;
+                let total: u64 = previous
+                    .iter()
+                    .zip(current)
+                    .map(|(&a, &b)| a.abs_diff(b) as u64)
+                    .sum();
+                Some(total as f64 / current.len() as f64 / max)
+            }
+            _ => None,
+        };
+        let stats = FrameStats {
+            index: self.index,
+            frames_since_keyframe: self.since_keyframe,
+            luma_difference,
+        };
+
+        let keyframe = self.index == 0
+            || self
+                .policy
+                .max_interval
+                .is_some_and(|interval| self.since_keyframe >= interval)
+            || self
+                .policy
+                .scene_change
+                .as_mut()
+                .is_some_and(|callback| callback(&stats));
+        self.index += 1;
+        self.since_keyframe = if keyframe { 1 } else { self.since_keyframe + 1 };
+        self.previous_luma = luma.map(|luma| (image.width(), image.height(), luma));
+        if keyframe {
+            AddImageFlags::FORCE_KEYFRAME
+        } else {
+            AddImageFlags::NONE
+        }
+    }
+}
+
+/// Returns luma samples on a sparse grid, or `None` if the image has no
+/// luma plane.
+fn sample_luma(image: &Image) -> Option<Vec<u16>> {
+    let plane = image.plane(Channel::Y)?;
+    let wide = image.uses_u16();
+    let row_bytes = image.plane_row_bytes(Channel::Y) as usize;
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let mut samples = Vec::new();
+    for y in (0..height).step_by(LUMA_SAMPLE_STEP) {
+        let row = &plane[y * row_bytes..];
+        for x in (0..width).step_by(LUMA_SAMPLE_STEP) {
+            samples.push(read_sample(row, x, wide));
+        }
+    }
+    Some(samples)
+}
+
+/// Time budget of an encode.
+struct Deadline {
+    budget: Duration,
+    started: Option<Instant>,
+}
+
+/// Opinionated encoder configurations for common use cases.
+///
+/// A preset covers encoder settings, applied with `EncoderBuilder::preset` or
+/// `Encoder::with_preset`, as well as recommendations for preparing the image:
+/// convert RGB input with the preset's `chroma_downsampling` to its
+/// `pixel_format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Good visual quality at a small size for images served on the web
+    Web,
+    /// Near-transparent quality with full chroma for long-term storage
+    Archive,
+    /// Small, fast-to-encode and fast-to-decode previews
+    Thumbnail,
+    /// Fastest encoding, for live capture and screen sharing
+    Realtime,
+}
+
+impl Preset {
+    /// Returns the YUV format images should be converted to.
+    pub fn pixel_format(self) -> PixelFormat {
+        match self {
+            Preset::Archive => PixelFormat::Yuv444,
+            Preset::Web | Preset::Thumbnail | Preset::Realtime => PixelFormat::Yuv420,
+        }
+    }
+
+    /// Returns the chroma downsampling to use when converting RGB input.
+    pub fn chroma_downsampling(self) -> ChromaDownsampling {
+        match self {
+            Preset::Web | Preset::Archive => ChromaDownsampling::BestQuality,
+            Preset::Thumbnail => ChromaDownsampling::Automatic,
+            Preset::Realtime => ChromaDownsampling::Fastest,
+        }
+    }
+
+    /// Returns the color and alpha quality.
+    fn quality(self) -> (u8, u8) {
+        match self {
+            Preset::Web => (60, 80),
+            Preset::Archive => (90, 100),
+            Preset::Thumbnail => (50, 70),
+            Preset::Realtime => (55, 70),
+        }
+    }
+
+    /// Returns the encoder speed.
+    fn speed(self) -> u8 {
+        match self {
+            Preset::Web => 6,
+            Preset::Archive => 2,
+            Preset::Thumbnail => 8,
+            Preset::Realtime => 10,
+        }
+    }
+}
+
+/// Builder for configuring an [`Encoder`] in a single expression.
+///
+/// Options are validated once in `build`, rather than being clamped like the
+/// `Encoder` setters, so out-of-range values are reported instead of silently
+/// adjusted. Builders are cheap to clone, which makes them convenient for
+/// passing a configuration around and creating encoders from it on demand.
+#[derive(Debug, Clone, Default)]
+pub struct EncoderBuilder {
+    codec: Option<EncoderCodecChoice>,
+    max_threads: Option<u32>,
+    speed: Option<u8>,
+    keyframe_interval: Option<u32>,
+    timescale: Option<u64>,
+    repetition_count: Option<RepetitionCount>,
+    quality: Option<u8>,
+    quality_alpha: Option<u8>,
+    quantizer_range: Option<(u8, u8)>,
+    quantizer_alpha_range: Option<(u8, u8)>,
+    tiling: Option<(u8, u8)>,
+    auto_tiling: Option<bool>,
+    codec_options: Vec<(String, String)>,
+}
+
+impl EncoderBuilder {
+    /// Creates a builder with libavif's default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the codec to use for encoding.
+    pub fn codec(mut self, choice: EncoderCodecChoice) -> Self {
+        self.codec = Some(choice);
+        self
+    }
+
+    /// Sets the maximum number of threads (1-1024).
+    pub fn max_threads(mut self, threads: u32) -> Self {
+        self.max_threads = Some(threads);
+        self
+    }
+
+    /// Sets the speed from 0 (slowest, best quality) to 10 (fastest).
+    pub fn speed(mut self, speed: u8) -> Self {
+        self.speed = Some(speed);
+        self
+    }
+
+    /// Sets the number of frames between keyframes (0 = automatic).
+    pub fn keyframe_interval(mut self, interval: u32) -> Self {
+        self.keyframe_interval = Some(interval);
+        self
+    }
+
+    /// Sets the number of time units per second for animations.
+    pub fn timescale(mut self, timescale: u64) -> Self {
+        self.timescale = Some(timescale);
+        self
+    }
+
+    /// Sets how many times an animation is repeated.
+    pub fn repetition_count(mut self, count: RepetitionCount) -> Self {
+        self.repetition_count = Some(count);
+        self
+    }
+
+    /// Sets the color quality from 0 (lowest) to 100 (lossless).
+    pub fn quality(mut self, quality: u8) -> Self {
+        self.quality = Some(quality);
+        self
+    }
+
+    /// Sets the alpha quality from 0 (lowest) to 100 (lossless).
+    pub fn quality_alpha(mut self, quality: u8) -> Self {
+        self.quality_alpha = Some(quality);
+        self
+    }
+
+    /// Applies the encoder settings of a preset.
+    ///
+    /// Options set afterwards override the preset's values.
+    pub fn preset(mut self, preset: Preset) -> Self {
+        let (quality, quality_alpha) = preset.quality();
+        self.quality = Some(quality);
+        self.quality_alpha = Some(quality_alpha);
+        self.speed = Some(preset.speed());
+        // Thumbnails are too small to benefit from tiling.
+        self.auto_tiling = Some(preset != Preset::Thumbnail);
+        self
+    }
+
+    /// Configures lossless encoding of color and alpha.
+    ///
+    /// The image must also be stored losslessly; see `Encoder::lossless`.
+    pub fn lossless(mut self) -> Self {
+        self.quality = Some(QUALITY_LOSSLESS);
+        self.quality_alpha = Some(QUALITY_LOSSLESS);
+        self.quantizer_range = Some((QUANTIZER_LOSSLESS, QUANTIZER_LOSSLESS));
+        self.quantizer_alpha_range = Some((QUANTIZER_LOSSLESS, QUANTIZER_LOSSLESS));
+        self
+    }
+
+    /// Sets the color quantizer range; both values must be at most 63.
+    pub fn quantizer_range(mut self, min: u8, max: u8) -> Self {
+        self.quantizer_range = Some((min, max));
+        self
+    }
+
+    /// Sets the alpha quantizer range; both values must be at most 63.
+    pub fn quantizer_alpha_range(mut self, min: u8, max: u8) -> Self {
+        self.quantizer_alpha_range = Some((min, max));
+        self
+    }
+
+    /// Sets log2 of the tile rows and columns (0-6 each).
+    pub fn tiling(mut self, tile_rows_log2: u8, tile_cols_log2: u8) -> Self {
+        self.tiling = Some((tile_rows_log2, tile_cols_log2));
+        self
+    }
+
+    /// Enables or disables automatic tiling.
+    pub fn auto_tiling(mut self, enabled: bool) -> Self {
+        self.auto_tiling = Some(enabled);
+        self
+    }
+
+    /// Adds a codec-specific option, applied in the order given.
+    pub fn codec_option(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.codec_options.push((key.into(), value.into()));
+        self
+    }
+
+    /// Validates the configuration and creates the encoder.
+    ///
+    /// # Returns
+    /// The configured encoder, or `AvifError::InvalidArgument` if an option
+    /// is out of range.
+    pub fn build(&self) -> Result<Encoder> {
+        self.validate()?;
+
+        let mut encoder = Encoder::new()?;
+        if let Some(choice) = self.codec {
+            encoder.set_codec_choice(choice);
+        }
+        if let Some(threads) = self.max_threads {
+            encoder.set_max_threads(threads);
+        }
+        if let Some(speed) = self.speed {
+            encoder.set_speed(speed);
+        }
+        if let Some(interval) = self.keyframe_interval {
+            encoder.set_keyframe_interval(interval);
+        }
+        if let Some(timescale) = self.timescale {
+            encoder.set_timescale(timescale);
+        }
+        if let Some(count) = self.repetition_count {
+            encoder.set_repetition_count(count)?;
+        }
+        if let Some(quality) = self.quality {
+            encoder.set_quality(quality);
+        }
+        if let Some(quality) = self.quality_alpha {
+            encoder.set_quality_alpha(quality);
+        }
+        if let Some((min, max)) = self.quantizer_range {
+            encoder.set_quantizer_range(min, max);
+        }
+        if let Some((min, max)) = self.quantizer_alpha_range {
+            encoder.set_quantizer_alpha_range(min, max);
+        }
+        if let Some((rows, cols)) = self.tiling {
+            encoder.set_tiling(rows, cols);
+        }
+        if let Some(enabled) = self.auto_tiling {
+            encoder.set_auto_tiling(enabled);
+        }
+        for (key, value) in &self.codec_options {
+            encoder.set_codec_specific_option(key, value)?;
+        }
+        Ok(encoder)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let in_range =
+            |range: Option<(u8, u8)>, max: u8| range.is_none_or(|(lo, hi)| lo <= hi && hi <= max);
+        let valid = self.max_threads.is_none_or(|t| (1..=1024).contains(&t))
+            && self.speed.is_none_or(|s| s <= 10)
+            && self.timescale != Some(0)
+            && self.quality.is_none_or(|q| q <= 100)
+            && self.quality_alpha.is_none_or(|q| q <= 100)
+            && in_range(self.quantizer_range, QUANTIZER_WORST_QUALITY)
+            && in_range(self.quantizer_alpha_range, QUANTIZER_WORST_QUALITY)
+            && self
+                .tiling
+                .is_none_or(|(rows, cols)| rows <= 6 && cols <= 6)
+            && i32::try_from(self.keyframe_interval.unwrap_or(0)).is_ok()
+            && self
+                .repetition_count
+                .is_none_or(|count| i32::try_from(count).is_ok());
+        if valid {
+            Ok(())
+        } else {
+            Err(AvifError::InvalidArgument)
+        }
+    }
+}
+
 /// AVIF encoder for converting images to AVIF format.
 ///
 /// The encoder provides various configuration options for controlling
@@ -77,20 +1055,127 @@ impl ops::BitOr for AddImageFlags {
 /// and animation support.
 pub struct Encoder {
     inner: *mut avifEncoder,
+    resolved_tiling: Option<(u8, u8)>,
+    codec_options: Vec<(String, String)>,
+    images_added: u32,
+    progress: Option<ProgressCallback>,
+    cancel: CancelToken,
+    scratch: Option<Image>,
+    frame_duration: u64,
+    keyframes: Option<KeyframeTracker>,
+    deadline: Option<Deadline>,
 }
 
 impl Encoder {
     /// Creates a new AVIF encoder.
     ///
+    /// The encoder uses the crate's thread budget if one is set with
+    /// `set_thread_budget`.
+    ///
     /// # Returns
     /// A new encoder instance or an error if creation fails.
     pub fn new() -> Result<Self> {
         let inner = unsafe { avifEncoderCreate() };
         if inner.is_null() {
-            Err(AvifError::OutOfMemory)
-        } else {
-            Ok(Self { inner })
+            return Err(AvifError::OutOfMemory);
+        }
+        let mut encoder = unsafe { Self::from_raw(inner) };
+        if let Some(threads) = crate::thread_budget() {
+            encoder.set_max_threads(threads);
         }
+        Ok(encoder)
+    }
+
+    /// Creates an encoder with libavif's default settings without panicking.
+    ///
+    /// This is the fallible counterpart of `Encoder::default()`.
+    ///
+    /// # Returns
+    /// A new encoder instance or an error if creation fails.
+    pub fn try_default() -> Result<Self> {
+        Self::new()
+    }
+
+    /// Takes ownership of an encoder created by libavif.
+    ///
+    /// Settings already made on the libavif encoder are kept. State this
+    /// crate tracks on its own, such as the number of images added, starts
+    /// fresh, so the encoder should not have been used to encode yet.
+    ///
+    /// # Safety
+    /// `encoder` must be a valid, non-null pointer returned by
+    /// `avifEncoderCreate` of the libavif linked into this crate, and must
+    /// not be used or destroyed elsewhere afterwards. The encoder is
+    /// destroyed when the returned value is dropped.
+    pub unsafe fn from_raw(encoder: *mut avifEncoder) -> Self {
+        Self {
+            inner: encoder,
+            resolved_tiling: None,
+            codec_options: Vec::new(),
+            images_added: 0,
+            progress: None,
+            cancel: CancelToken::default(),
+            scratch: None,
+            frame_duration: 1,
+            keyframes: None,
+            deadline: None,
+        }
+    }
+
+    /// Releases ownership of the underlying libavif encoder.
+    ///
+    /// # Returns
+    /// The encoder, which the caller must destroy with `avifEncoderDestroy`
+    /// or pass back to `Encoder::from_raw`.
+    pub fn into_raw(mut self) -> *mut avifEncoder {
+        std::mem::replace(&mut self.inner, std::ptr::null_mut())
+    }
+
+    /// Returns the underlying libavif encoder without giving up ownership.
+    ///
+    /// The pointer is valid as long as this encoder is, and must not be
+    /// destroyed. Settings changed through it are not seen by this crate's
+    /// own bookkeeping.
+    pub fn as_raw(&self) -> *const avifEncoder {
+        self.inner
+    }
+
+    /// Returns the underlying libavif encoder for modification without
+    /// giving up ownership.
+    ///
+    /// The pointer is valid as long as this encoder is, and must not be
+    /// destroyed.
+    pub fn as_raw_mut(&mut self) -> *mut avifEncoder {
+        self.inner
+    }
+
+    /// Creates an encoder configured for lossless encoding.
+    ///
+    /// Color and alpha are encoded at quality 100. Encoding is only truly
+    /// lossless if the image is also stored as full-range YUV 4:4:4 with
+    /// identity matrix coefficients, which `RgbImage::to_lossless_yuv_image`
+    /// produces from RGB data.
+    ///
+    /// # Returns
+    /// A new encoder instance or an error if creation fails.
+    pub fn lossless() -> Result<Self> {
+        Self::builder().lossless().build()
+    }
+
+    /// Creates an encoder configured with a preset.
+    ///
+    /// # Arguments
+    /// * `preset` - The preset to apply
+    ///
+    /// # Returns
+    /// A new encoder instance or an error if creation fails.
+    pub fn with_preset(preset: Preset) -> Result<Self> {
+        Self::builder().preset(preset).build()
+    }
+
+    /// Returns a builder for configuring a new encoder.
+    pub fn builder() -> EncoderBuilder {
+        EncoderBuilder::new()
     }
 
     /// Sets the codec choice for encoding.
@@ -106,10 +1191,10 @@ impl Encoder {
     /// Sets the maximum number of threads to use for encoding.
     ///
     /// # Arguments
-    /// * `threads` - Number of threads (clamped to 1024)
+    /// * `threads` - Number of threads (clamped to 1024, or 1 on WebAssembly)
     pub fn set_max_threads(&mut self, threads: u32) {
         unsafe {
-            (*self.inner).maxThreads = threads.min(1024) as i32;
+            (*self.inner).maxThreads = threads.min(crate::MAX_THREADS) as i32;
         }
     }
 
@@ -143,83 +1228,456 @@ impl Encoder {
         }
     }
 
-    /// Sets the repetition count for animations.
+    /// Sets the frame rate for animations as a fraction of frames per second.
+    ///
+    /// This sets the timescale to `numerator` and the duration of frames added
+    /// with `add_frame` to `denominator` units, so 30000/1001 (29.97 FPS) is
+    /// represented exactly.
+    ///
+    /// # Arguments
+    /// * `numerator` - Frames per `denominator` seconds
+    /// * `denominator` - Seconds per `numerator` frames
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if either value is zero.
+    pub fn set_frame_rate(&mut self, numerator: u32, denominator: u32) -> Result<()> {
+        if numerator == 0 || denominator == 0 {
+            return Err(AvifError::InvalidArgument);
+        }
+        self.set_timescale(numerator as u64);
+        self.frame_duration = denominator as u64;
+        Ok(())
+    }
+
+    /// Converts a duration to units of the current timescale, rounding to the
+    /// nearest unit but never below one. Durations too long to count in
+    /// `u64` units give `u64::MAX`.
+    pub fn duration_to_timescales(&self, duration: Duration) -> u64 {
+        let timescale = self.timescale() as u128;
+        // Saturating keeps huge durations at the top of the range, where the
+        // clamp below maps them to u64::MAX.
+        let units = duration
+            .as_nanos()
+            .saturating_mul(timescale)
+            .saturating_add(500_000_000)
+            / 1_000_000_000;
+        units.clamp(1, u64::MAX as u128) as u64
+    }
+
+    /// Sets how many times an animation is repeated.
+    ///
+    /// # Arguments
+    /// * `count` - Infinite looping, or the number of repetitions after the
+    ///   first playback
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if a finite count exceeds `i32::MAX`.
+    pub fn set_repetition_count(&mut self, count: RepetitionCount) -> Result<()> {
+        let count = i32::try_from(count)?;
+        unsafe {
+            (*self.inner).repetitionCount = count;
+        }
+        Ok(())
+    }
+
+    /// Sets the quality for color channels.
+    ///
+    /// # Arguments
+    /// * `quality` - Quality from 0 (lowest) to 100 (highest/lossless)
+    pub fn set_quality(&mut self, quality: u8) {
+        unsafe {
+            (*self.inner).quality = quality.min(100) as i32;
+        }
+    }
+
+    /// Sets the color quality equivalent to a JPEG quality.
+    ///
+    /// See `quality_from_jpeg` for the mapping.
+    ///
+    /// # Arguments
+    /// * `jpeg_quality` - JPEG quality from 0 to 100
+    pub fn set_quality_like_jpeg(&mut self, jpeg_quality: u8) {
+        self.set_quality(quality_from_jpeg(jpeg_quality));
+    }
+
+    /// Sets the quality for the alpha channel.
+    ///
+    /// # Arguments
+    /// * `quality` - Quality from 0 (lowest) to 100 (highest/lossless)
+    pub fn set_quality_alpha(&mut self, quality: u8) {
+        unsafe {
+            (*self.inner).qualityAlpha = quality.min(100) as i32;
+        }
+    }
+
+    /// Sets the quantizer range for color channels.
+    ///
+    /// # Arguments
+    /// * `min` - Minimum quantizer value (0-63, lower is better)
+    /// * `max` - Maximum quantizer value (0-63, lower is better)
+    pub fn set_quantizer_range(&mut self, min: u8, max: u8) {
+        unsafe {
+            (*self.inner).minQuantizer = min.min(QUANTIZER_WORST_QUALITY) as i32;
+            (*self.inner).maxQuantizer = max.min(QUANTIZER_WORST_QUALITY) as i32;
+        }
+    }
+
+    /// Sets the quantizer range for the alpha channel.
+    ///
+    /// # Arguments
+    /// * `min` - Minimum quantizer value (0-63, lower is better)
+    /// * `max` - Maximum quantizer value (0-63, lower is better)
+    pub fn set_quantizer_alpha_range(&mut self, min: u8, max: u8) {
+        unsafe {
+            (*self.inner).minQuantizerAlpha = min.min(QUANTIZER_WORST_QUALITY) as i32;
+            (*self.inner).maxQuantizerAlpha = max.min(QUANTIZER_WORST_QUALITY) as i32;
+        }
+    }
+
+    /// Sets the tiling configuration.
+    ///
+    /// Tiling can improve encoding performance and enable parallel decoding.
+    ///
+    /// # Arguments
+    /// * `tile_rows_log2` - Log2 of the number of tile rows (0-6)
+    /// * `tile_cols_log2` - Log2 of the number of tile columns (0-6)
+    pub fn set_tiling(&mut self, tile_rows_log2: u8, tile_cols_log2: u8) {
+        unsafe {
+            (*self.inner).tileRowsLog2 = tile_rows_log2.min(6) as i32;
+            (*self.inner).tileColsLog2 = tile_cols_log2.min(6) as i32;
+        }
+    }
+
+    /// Enables or disables automatic tiling.
+    ///
+    /// When enabled, the encoder will automatically determine optimal tiling.
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether to enable automatic tiling
+    pub fn set_auto_tiling(&mut self, enabled: bool) {
+        unsafe {
+            (*self.inner).autoTiling = if enabled { 1 } else { 0 };
+        }
+    }
+
+    /// Returns the codec choice for encoding.
+    pub fn codec_choice(&self) -> EncoderCodecChoice {
+        unsafe { (*self.inner).codecChoice.into() }
+    }
+
+    /// Returns the maximum number of threads used for encoding.
+    pub fn max_threads(&self) -> u32 {
+        unsafe { (*self.inner).maxThreads.max(1) as u32 }
+    }
+
+    /// Returns the encoding speed, or `None` if the codec's default is used.
+    pub fn speed(&self) -> Option<u8> {
+        u8::try_from(unsafe { (*self.inner).speed }).ok()
+    }
+
+    /// Returns the keyframe interval for animations.
+    pub fn keyframe_interval(&self) -> u32 {
+        unsafe { (*self.inner).keyframeInterval.max(0) as u32 }
+    }
+
+    /// Returns the timescale for animations.
+    pub fn timescale(&self) -> u64 {
+        unsafe { (*self.inner).timescale }
+    }
+
+    /// Returns how many times an animation is repeated.
+    pub fn repetition_count(&self) -> RepetitionCount {
+        unsafe { (*self.inner).repetitionCount.into() }
+    }
+
+    /// Returns the quality for color channels, or `None` if the quantizer
+    /// range is used instead.
+    pub fn quality(&self) -> Option<u8> {
+        u8::try_from(unsafe { (*self.inner).quality }).ok()
+    }
+
+    /// Returns the quality for the alpha channel, or `None` if the quantizer
+    /// range is used instead.
+    pub fn quality_alpha(&self) -> Option<u8> {
+        u8::try_from(unsafe { (*self.inner).qualityAlpha }).ok()
+    }
+
+    /// Returns the minimum and maximum quantizer for color channels.
+    pub fn quantizer_range(&self) -> (u8, u8) {
+        unsafe {
+            (
+                (*self.inner).minQuantizer as u8,
+                (*self.inner).maxQuantizer as u8,
+            )
+        }
+    }
+
+    /// Returns the minimum and maximum quantizer for the alpha channel.
+    pub fn quantizer_alpha_range(&self) -> (u8, u8) {
+        unsafe {
+            (
+                (*self.inner).minQuantizerAlpha as u8,
+                (*self.inner).maxQuantizerAlpha as u8,
+            )
+        }
+    }
+
+    /// Returns log2 of the number of tile rows and columns.
+    ///
+    /// These are the explicit settings; see `resolved_tiling` for the values
+    /// used with automatic tiling.
+    pub fn tiling(&self) -> (u8, u8) {
+        unsafe {
+            (
+                (*self.inner).tileRowsLog2 as u8,
+                (*self.inner).tileColsLog2 as u8,
+            )
+        }
+    }
+
+    /// Returns whether automatic tiling is enabled.
+    pub fn auto_tiling(&self) -> bool {
+        unsafe { (*self.inner).autoTiling != 0 }
+    }
+
+    /// Returns the spatial scaling mode.
+    pub fn scaling_mode(&self) -> ScalingMode {
+        unsafe { (*self.inner).scalingMode.into() }
+    }
+
+    /// Sets the spatial scaling applied to subsequently added images.
+    ///
+    /// This may be changed between `add_image` calls, for example to encode
+    /// a downscaled first layer of a layered image.
+    ///
+    /// # Arguments
+    /// * `mode` - The scaling factors
+    pub fn set_scaling_mode(&mut self, mode: ScalingMode) {
+        unsafe {
+            (*self.inner).scalingMode = mode.into();
+        }
+    }
+
+    /// Returns the container header format.
+    pub fn header_format(&self) -> HeaderFormat {
+        unsafe { (*self.inner).headerFormat.into() }
+    }
+
+    /// Sets the container header format.
+    ///
+    /// `HeaderFormat::Mini` applies to single images without gain maps, and
+    /// needs the `mini` feature; without it, encoding fails.
     ///
     /// # Arguments
-    /// * `count` - Number of repetitions (0 = infinite loop)
-    pub fn set_repetition_count(&mut self, count: u32) {
+    /// * `format` - The header format to write
+    pub fn set_header_format(&mut self, format: HeaderFormat) {
         unsafe {
-            (*self.inner).repetitionCount = (count as i32) - 1;
+            (*self.inner).headerFormat = format.into();
         }
     }
 
-    /// Sets the quality for color channels.
+    /// Returns the sample transform applied to images deeper than 12 bits.
+    #[cfg(feature = "sample-transform")]
+    pub fn sample_transform(&self) -> SampleTransform {
+        unsafe { (*self.inner).sampleTransformRecipe.into() }
+    }
+
+    /// Sets the sample transform applied to images deeper than 12 bits.
+    ///
+    /// AV1 codes at most 12 bits per sample, so 16-bit images, created with
+    /// `BitDepth::Sixteen`, can only be encoded by splitting them into two
+    /// coded images that decoders with sample transform support recombine.
     ///
     /// # Arguments
-    /// * `quality` - Quality from 0 (lowest) to 100 (highest/lossless)
-    pub fn set_quality(&mut self, quality: u8) {
+    /// * `transform` - The transform to apply
+    #[cfg(feature = "sample-transform")]
+    pub fn set_sample_transform(&mut self, transform: SampleTransform) {
         unsafe {
-            (*self.inner).quality = quality.min(100) as i32;
+            (*self.inner).sampleTransformRecipe = transform.into();
         }
     }
 
-    /// Sets the quality for the alpha channel.
+    /// Returns the number of layers encoded in addition to the first one.
+    pub fn extra_layer_count(&self) -> u32 {
+        unsafe { (*self.inner).extraLayerCount }
+    }
+
+    /// Sets the number of layers encoded in addition to the first one.
+    ///
+    /// With extra layers, the encoder produces a layered image: call
+    /// `add_image` once per layer, typically with the same image and a
+    /// scaling mode or quality that increases with each call, then `finish`.
+    /// Decoders that support progressive rendering can show the first layer
+    /// after receiving only a fraction of the file. `write_layered` performs
+    /// this sequence for a single image.
     ///
     /// # Arguments
-    /// * `quality` - Quality from 0 (lowest) to 100 (highest/lossless)
-    pub fn set_quality_alpha(&mut self, quality: u8) {
+    /// * `count` - Number of extra layers (at most 3)
+    pub fn set_extra_layer_count(&mut self, count: u32) {
         unsafe {
-            (*self.inner).qualityAlpha = quality.min(100) as i32;
+            (*self.inner).extraLayerCount = count.min(AVIF_MAX_AV1_LAYER_COUNT - 1);
         }
     }
 
-    /// Sets the quantizer range for color channels.
+    /// Encodes an image as a layered (progressive) AVIF.
+    ///
+    /// Each entry of `layers` produces one layer, from the first (preview)
+    /// layer to the last (full quality) layer. The encoder's scaling mode and
+    /// quality are left at the values of the last layer.
     ///
     /// # Arguments
-    /// * `min` - Minimum quantizer value (0-100)
-    /// * `max` - Maximum quantizer value (0-100)
-    pub fn set_quantizer_range(&mut self, min: u8, max: u8) {
-        unsafe {
-            (*self.inner).minQuantizer = min.min(100) as i32;
-            (*self.inner).maxQuantizer = max.min(100) as i32;
+    /// * `image` - The image to encode
+    /// * `layers` - Settings of each layer (1 to 4 entries)
+    ///
+    /// # Returns
+    /// The encoded AVIF data, or an error if encoding fails.
+    pub fn write_layered(&mut self, image: &Image, layers: &[Layer]) -> Result<RwData> {
+        if layers.is_empty() || layers.len() > AVIF_MAX_AV1_LAYER_COUNT as usize {
+            return Err(AvifError::InvalidArgument);
+        }
+        self.set_extra_layer_count(layers.len() as u32 - 1);
+        for layer in layers {
+            self.set_scaling_mode(layer.scaling_mode);
+            self.set_quality(layer.quality);
+            self.add_image(image, 1, AddImageFlags::NONE)?;
         }
+        self.finish()
     }
 
-    /// Sets the quantizer range for the alpha channel.
+    /// Returns log2 of the number of tile rows and columns used for the
+    /// images added so far.
     ///
-    /// # Arguments
-    /// * `min` - Minimum quantizer value (0-100)
-    /// * `max` - Maximum quantizer value (0-100)
-    pub fn set_quantizer_alpha_range(&mut self, min: u8, max: u8) {
-        unsafe {
-            (*self.inner).minQuantizerAlpha = min.min(100) as i32;
-            (*self.inner).maxQuantizerAlpha = max.min(100) as i32;
+    /// With automatic tiling, this is the configuration the encoder derived
+    /// from the image size and thread count, which can be passed to
+    /// `set_tiling` to reproduce a result. Returns `None` before an image has
+    /// been added.
+    pub fn resolved_tiling(&self) -> Option<(u8, u8)> {
+        self.resolved_tiling
+    }
+
+    /// Records the tiling libavif uses for images of the given size.
+    fn resolve_tiling(&mut self, width: u32, height: u32) {
+        if !self.auto_tiling() {
+            self.resolved_tiling = Some(self.tiling());
+            return;
         }
+
+        // Mirrors avifSetTileConfiguration(): at most one tile per 512x512
+        // area, per thread, and 32 in total, split favoring the longer side.
+        const MIN_TILE_AREA: u64 = 512 * 512;
+        const MAX_TILES: u64 = 32;
+        let threads = self.max_threads() as u64;
+        let mut tiles_log2 = 0u8;
+        if threads > 1 {
+            let area = width as u64 * height as u64;
+            let tiles = area.div_ceil(MIN_TILE_AREA).min(MAX_TILES).min(threads);
+            tiles_log2 = tiles.max(1).ilog2() as u8;
+        }
+        let (rows, cols) = if width >= height {
+            (tiles_log2 / 2, tiles_log2 - tiles_log2 / 2)
+        } else {
+            (tiles_log2 - tiles_log2 / 2, tiles_log2 / 2)
+        };
+        self.resolved_tiling = Some((rows, cols));
     }
 
-    /// Sets the tiling configuration.
+    /// Sets a callback invoked after each image (frame, layer or grid) is
+    /// added.
     ///
-    /// Tiling can improve encoding performance and enable parallel decoding.
+    /// Returning `false` from the callback cancels the encode: the next
+    /// `add_image`, `add_image_grid` or `finish` call fails with
+    /// `AvifError::Cancelled`.
     ///
     /// # Arguments
-    /// * `tile_rows_log2` - Log2 of the number of tile rows (0-6)
-    /// * `tile_cols_log2` - Log2 of the number of tile columns (0-6)
-    pub fn set_tiling(&mut self, tile_rows_log2: u8, tile_cols_log2: u8) {
-        unsafe {
-            (*self.inner).tileRowsLog2 = tile_rows_log2.min(6) as i32;
-            (*self.inner).tileColsLog2 = tile_cols_log2.min(6) as i32;
+    /// * `callback` - Called with the progress so far
+    pub fn set_progress_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(&EncodeProgress) -> bool + 'static,
+    {
+        self.progress = Some(Box::new(callback));
+    }
+
+    /// Returns a token that cancels this encoder's work when triggered,
+    /// for example from a GUI thread.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel.clone()
+    }
+
+    /// Converts a failed result of an encoding call into an error carrying
+    /// the encoder's diagnostic message.
+    fn diagnostic_error(&self, op: Operation, result: avifResult) -> AvifError {
+        AvifError::with_diagnostics(op, result, unsafe { &(*self.inner).diag })
+    }
+
+    /// Fails with `AvifError::Cancelled` if cancellation was requested, or
+    /// with `AvifError::DeadlineExceeded` if the time budget is used up.
+    fn check_cancelled(&self) -> Result<()> {
+        if self.cancel.is_cancelled() {
+            return Err(AvifError::Cancelled);
+        }
+        match &self.deadline {
+            Some(Deadline {
+                budget,
+                started: Some(started),
+            }) if started.elapsed() >= *budget => Err(AvifError::DeadlineExceeded),
+            _ => Ok(()),
         }
     }
 
-    /// Enables or disables automatic tiling.
+    /// Sets a time budget for the encode.
     ///
-    /// When enabled, the encoder will automatically determine optimal tiling.
+    /// The clock starts when the first image is added. At that point the
+    /// speed is raised, if needed, to the slowest speed expected to encode
+    /// the image within the budget, based on its pixel count and the thread
+    /// count; libavif cannot change the speed once encoding has started, so
+    /// for sequences the first frame alone is used for the estimate. If the
+    /// budget is exceeded anyway, the next `add_image`, `add_image_grid`,
+    /// `write` or `finish` call fails with `AvifError::DeadlineExceeded`. An
+    /// image already being encoded runs to completion.
     ///
     /// # Arguments
-    /// * `enabled` - Whether to enable automatic tiling
-    pub fn set_auto_tiling(&mut self, enabled: bool) {
-        unsafe {
-            (*self.inner).autoTiling = if enabled { 1 } else { 0 };
+    /// * `budget` - Time allowed for the whole encode
+    pub fn set_deadline(&mut self, budget: Duration) {
+        self.deadline = Some(Deadline {
+            budget,
+            started: None,
+        });
+    }
+
+    /// Starts the deadline clock and picks a speed for the first image.
+    fn start_deadline(&mut self, pixels: u64) {
+        let Some(deadline) = self.deadline.as_mut() else {
+            return;
+        };
+        if deadline.started.is_some() {
+            return;
+        }
+        deadline.started = Some(Instant::now());
+
+        let threads = unsafe { (*self.inner).maxThreads }.max(1) as u64;
+        let budget = deadline.budget.as_nanos();
+        let current = self.speed().unwrap_or(DEFAULT_SPEED).min(10);
+        let speed = (current..=10)
+            .find(|&speed| {
+                let cost = pixels * ENCODE_COST_NS_PER_PIXEL[speed as usize] / threads;
+                cost as u128 <= budget
+            })
+            .unwrap_or(10);
+        if speed != current {
+            self.set_speed(speed);
+        }
+    }
+
+    /// Counts an added image and reports progress.
+    fn image_added(&mut self) {
+        self.images_added += 1;
+        let progress = EncodeProgress {
+            images_added: self.images_added,
+        };
+        if let Some(callback) = self.progress.as_mut()
+            && !callback(&progress)
+        {
+            self.cancel.cancel();
         }
     }
 
@@ -238,6 +1696,9 @@ impl Encoder {
         duration_in_timescales: u64,
         add_image_flags: AddImageFlags,
     ) -> Result<()> {
+        self.check_cancelled()?;
+        self.start_deadline(image.width() as u64 * image.height() as u64);
+        self.resolve_tiling(image.width(), image.height());
         let result = unsafe {
             avifEncoderAddImage(
                 self.inner,
@@ -247,12 +1708,141 @@ impl Encoder {
             )
         };
         if result != avifResult_AVIF_RESULT_OK {
-            Err(AvifError::from(result))
+            Err(self.diagnostic_error(Operation::AddImage, result))
         } else {
+            self.image_added();
             Ok(())
         }
     }
 
+    /// Adds an animation frame displayed for one frame period.
+    ///
+    /// The period is set with `set_frame_rate`; without it, each frame lasts
+    /// one timescale unit.
+    ///
+    /// # Arguments
+    /// * `image` - The frame to add
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if the operation fails.
+    pub fn add_frame(&mut self, image: &Image) -> Result<()> {
+        self.add_image(image, self.frame_duration, AddImageFlags::NONE)
+    }
+
+    /// Adds an animation frame displayed for the given duration.
+    ///
+    /// The duration is converted to the current timescale, so set the
+    /// timescale or frame rate before adding frames.
+    ///
+    /// # Arguments
+    /// * `image` - The frame to add
+    /// * `duration` - How long the frame is displayed
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if the operation fails.
+    pub fn add_image_with_duration(&mut self, image: &Image, duration: Duration) -> Result<()> {
+        let units = self.duration_to_timescales(duration);
+        self.add_image(image, units, AddImageFlags::NONE)
+    }
+
+    /// Converts an RGB frame to YUV and adds it to the encoder.
+    ///
+    /// The YUV image is kept between calls and reused while the frame size,
+    /// depth and format stay the same, so encoding an animation does not
+    /// allocate planes for every frame.
+    ///
+    /// # Arguments
+    /// * `rgb` - The frame to add
+    /// * `yuv_format` - The YUV format to encode the frame as
+    /// * `duration_in_timescales` - Duration this frame should be displayed
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if conversion or encoding fails.
+    pub fn add_frame_rgb(
+        &mut self,
+        rgb: &RgbImage,
+        yuv_format: PixelFormat,
+        duration_in_timescales: u64,
+    ) -> Result<()> {
+        let scratch = self.convert_to_scratch(rgb, yuv_format)?;
+        let result = self.add_image(&scratch, duration_in_timescales, AddImageFlags::NONE);
+        self.scratch = Some(scratch);
+        result
+    }
+
+    /// Converts an RGB frame to YUV, reusing the scratch image if it matches.
+    /// The caller puts the image back into `self.scratch` once done with it.
+    fn convert_to_scratch(&mut self, rgb: &RgbImage, yuv_format: PixelFormat) -> Result<Image> {
+        match self.scratch.take() {
+            Some(mut image)
+                if image.width() == rgb.width()
+                    && image.height() == rgb.height()
+                    && image.depth() == rgb.depth()
+                    && image.yuv_format() == yuv_format =>
+            {
+                rgb.convert_into(&mut image)?;
+                Ok(image)
+            }
+            _ => rgb.to_yuv_image(yuv_format),
+        }
+    }
+
+    /// Sets the keyframe placement applied by `encode_sequence`.
+    ///
+    /// # Arguments
+    /// * `policy` - The policy, or `None` to leave keyframes to the codec
+    pub fn set_keyframe_policy(&mut self, policy: Option<KeyframePolicy>) {
+        self.keyframes = policy.map(|policy| KeyframeTracker {
+            policy,
+            index: 0,
+            since_keyframe: 0,
+            previous_luma: None,
+        });
+    }
+
+    /// Returns the flags the keyframe policy chooses for the next frame.
+    fn keyframe_flags(&mut self, image: &Image) -> AddImageFlags {
+        match self.keyframes.as_mut() {
+            Some(tracker) => tracker.next(image),
+            None => AddImageFlags::NONE,
+        }
+    }
+
+    /// Encodes an animation from a sequence of frames.
+    ///
+    /// Frames are pulled from the iterator one at a time and dropped once
+    /// added, so only the frame being encoded is held in memory. This allows
+    /// encoding long sequences produced on the fly, such as decoded video.
+    /// Keyframes are forced according to the policy set with
+    /// `set_keyframe_policy`.
+    ///
+    /// # Arguments
+    /// * `frames` - The frames, in display order
+    ///
+    /// # Returns
+    /// The encoded AVIF data, or an error if conversion or encoding fails.
+    pub fn encode_sequence<I>(&mut self, frames: I) -> Result<RwData>
+    where
+        I: IntoIterator<Item = Frame>,
+    {
+        for frame in frames {
+            match frame.content {
+                FrameContent::Yuv(image) => {
+                    let flags = self.keyframe_flags(&image);
+                    self.add_image(&image, frame.duration_in_timescales, flags)?
+                }
+                FrameContent::Rgb(image, yuv_format) => {
+                    let scratch = self.convert_to_scratch(&image, yuv_format)?;
+                    let flags = self.keyframe_flags(&scratch);
+                    let result = self.add_image(&scratch, frame.duration_in_timescales, flags);
+                    self.scratch = Some(scratch);
+                    result?
+                }
+            }
+        }
+        self.finish()
+    }
+
     /// Adds a grid of images as a single tiled image.
     ///
     /// This creates a single AVIF image composed of multiple smaller images
@@ -273,6 +1863,16 @@ impl Encoder {
         images: &[&Image],
         add_image_flags: AddImageFlags,
     ) -> Result<()> {
+        self.check_cancelled()?;
+        self.start_deadline(
+            images
+                .iter()
+                .map(|cell| cell.width() as u64 * cell.height() as u64)
+                .sum(),
+        );
+        if let Some(cell) = images.first() {
+            self.resolve_tiling(cell.width(), cell.height());
+        }
         let image_ptrs: Vec<*const avifImage> =
             images.iter().map(|img| img.inner as *const _).collect();
         let result = unsafe {
@@ -285,8 +1885,9 @@ impl Encoder {
             )
         };
         if result != avifResult_AVIF_RESULT_OK {
-            Err(AvifError::from(result))
+            Err(self.diagnostic_error(Operation::AddImageGrid, result))
         } else {
+            self.image_added();
             Ok(())
         }
     }
@@ -299,10 +1900,11 @@ impl Encoder {
     /// # Returns
     /// The encoded AVIF data, or an error if encoding fails.
     pub fn finish(&mut self) -> Result<RwData> {
+        self.check_cancelled()?;
         let mut output = RwData::new();
         let result = unsafe { avifEncoderFinish(self.inner, &mut output.inner) };
         if result != avifResult_AVIF_RESULT_OK {
-            Err(AvifError::from(result))
+            Err(self.diagnostic_error(Operation::Finish, result))
         } else {
             Ok(output)
         }
@@ -320,12 +1922,180 @@ impl Encoder {
     /// The encoded AVIF data, or an error if encoding fails.
     pub fn write(&mut self, image: &Image) -> Result<RwData> {
         let mut output = RwData::new();
+        self.write_into(image, &mut output)?;
+        Ok(output)
+    }
+
+    /// Encodes a single image into an existing buffer.
+    ///
+    /// The buffer's previous contents are replaced, and its allocation is
+    /// reused when large enough, so encoding many images into the same
+    /// buffer does not allocate output memory for each of them.
+    ///
+    /// # Arguments
+    /// * `image` - The image to encode
+    /// * `output` - The buffer receiving the encoded AVIF data
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if encoding fails, in which case
+    /// `output` is left empty.
+    pub fn write_into(&mut self, image: &Image, output: &mut RwData) -> Result<()> {
+        output.clear();
+        self.check_cancelled()?;
+        self.start_deadline(image.width() as u64 * image.height() as u64);
+        self.resolve_tiling(image.width(), image.height());
+        let allocation = output.begin_write();
         let result = unsafe { avifEncoderWrite(self.inner, image.inner, &mut output.inner) };
+        output.end_write(allocation, result == avifResult_AVIF_RESULT_OK);
         if result != avifResult_AVIF_RESULT_OK {
-            Err(AvifError::from(result))
+            Err(self.diagnostic_error(Operation::Write, result))
         } else {
-            Ok(output)
+            self.image_added();
+            Ok(())
+        }
+    }
+
+    /// Encodes a single image of any size, splitting it into a grid if needed.
+    ///
+    /// Images larger than the AVIF Baseline profile limits (8192x4352 per
+    /// coded image) are split into equally sized cells, with a smaller last
+    /// column and row, and written with `add_image_grid`. The cells borrow the
+    /// image's planes, so no pixels are copied. Smaller images are encoded as
+    /// with `write`.
+    ///
+    /// # Arguments
+    /// * `image` - The image to encode
+    ///
+    /// # Returns
+    /// The encoded AVIF data, `AvifError::InvalidImageGrid` if the image
+    /// needs more than 256 cells per axis or its last column or row would be
+    /// narrower than 64 pixels, or an error if encoding fails.
+    pub fn write_large(&mut self, image: &Image) -> Result<RwData> {
+        let (width, height) = (image.width(), image.height());
+        if width <= MAX_CELL_WIDTH && height <= MAX_CELL_HEIGHT {
+            return self.write(image);
+        }
+
+        let cell_size = |size: u32, max: u32| {
+            let count = size.div_ceil(max);
+            let cell = size.div_ceil(count).next_multiple_of(CELL_ALIGNMENT);
+            (size.div_ceil(cell), cell)
+        };
+        let (cols, cell_width) = cell_size(width, MAX_CELL_WIDTH);
+        let (rows, cell_height) = cell_size(height, MAX_CELL_HEIGHT);
+        // A grid has at most 256 cells per axis, and its last column and row
+        // must still be at least 64 pixels.
+        for (axis, size, count, cell) in [
+            ("width", width, cols, cell_width),
+            ("height", height, rows, cell_height),
+        ] {
+            let last = size - (count - 1) * cell;
+            if count > 256 || (count > 1 && last < 64) {
+                return Err(AvifError::InvalidImageGrid.with_detail(
+                    Operation::AddImageGrid,
+                    format!(
+                        "{} {} needs {} cells of {} pixels with a last cell of {}; grids \
+                         allow at most 256 cells of at least 64 pixels",
+                        axis, size, count, cell, last
+                    ),
+                ));
+            }
         }
+
+        let mut cells = Vec::with_capacity(cols as usize * rows as usize);
+        for row in 0..rows {
+            for col in 0..cols {
+                let (x, y) = (col * cell_width, row * cell_height);
+                let rect = Rect::new(x, y, cell_width.min(width - x), cell_height.min(height - y));
+                cells.push(image.view(rect)?);
+            }
+        }
+        let cell_refs: Vec<&Image> = cells.iter().map(|cell| &**cell).collect();
+        self.add_image_grid(cols, rows, &cell_refs, AddImageFlags::SINGLE)?;
+        self.finish()
+    }
+
+    /// Encodes one large RGB image as a grid of separately coded cells.
+    ///
+    /// The image is split into `cols` x `rows` cells of equal size, except
+    /// that the last column and row take the remainder. Cells must be at
+    /// least 64x64 pixels, cells before the last column or row must have even
+    /// dimensions where `yuv_format` subsamples chroma, and a grid has at
+    /// most 256 columns and rows.
+    ///
+    /// Each cell is converted to YUV on its own, in parallel with the `rayon`
+    /// feature. libavif encodes all cells of a grid together, though, so the
+    /// converted cells are all held until encoding ends and together take as
+    /// much memory as a full-size YUV image. `ScanlineGrid` also avoids
+    /// holding the whole RGB image.
+    ///
+    /// # Arguments
+    /// * `rgb` - The image to encode
+    /// * `cols` - Number of columns in the grid
+    /// * `rows` - Number of rows in the grid
+    /// * `yuv_format` - The YUV format to encode the cells as
+    ///
+    /// # Returns
+    /// The encoded AVIF data, or `AvifError::InvalidImageGrid` if the image
+    /// cannot be split into a valid grid.
+    pub fn write_grid_rgb(
+        &mut self,
+        rgb: &RgbImage,
+        cols: u32,
+        rows: u32,
+        yuv_format: PixelFormat,
+    ) -> Result<RwData> {
+        let (width, height) = (rgb.width(), rgb.height());
+        let (cell_width, cell_height) = grid_cell_size(width, height, cols, rows, yuv_format)?;
+
+        // Cells are converted in parallel with the `rayon` feature.
+        let cells = par::map_range(cols as usize * rows as usize, |i| {
+            let (col, row) = (i as u32 % cols, i as u32 / cols);
+            let (x, y) = (col * cell_width, row * cell_height);
+            let rect = Rect::new(x, y, cell_width.min(width - x), cell_height.min(height - y));
+            rgb.region_to_yuv_image(rect, yuv_format)
+        })
+        .into_iter()
+        .collect::<Result<Vec<Image>>>()?;
+        let cell_refs: Vec<&Image> = cells.iter().collect();
+        self.add_image_grid(cols, rows, &cell_refs, AddImageFlags::SINGLE)?;
+        self.finish()
+    }
+
+    /// Returns size statistics of the data produced by the last `finish` or
+    /// `write` call.
+    ///
+    /// This allows monitoring, for example, the alpha channel unexpectedly
+    /// dominating the file size.
+    pub fn stats(&self) -> EncodeStats {
+        unsafe { (*self.inner).ioStats.into() }
+    }
+
+    /// Encodes a single image and writes the AVIF data to a writer.
+    ///
+    /// # Arguments
+    /// * `image` - The image to encode
+    /// * `writer` - Destination of the encoded data, such as a `File`
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if encoding or writing fails. Encoding
+    /// errors are wrapped in an `io::Error` of kind `Other`.
+    pub fn write_to<W: io::Write>(&mut self, image: &Image, mut writer: W) -> io::Result<()> {
+        let output = self.write(image)?;
+        writer.write_all(output.as_slice())
+    }
+
+    /// Finalizes encoding and writes the AVIF data to a writer.
+    ///
+    /// # Arguments
+    /// * `writer` - Destination of the encoded data, such as a `File`
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if encoding or writing fails. Encoding
+    /// errors are wrapped in an `io::Error` of kind `Other`.
+    pub fn finish_to<W: io::Write>(&mut self, mut writer: W) -> io::Result<()> {
+        let output = self.finish()?;
+        writer.write_all(output.as_slice())
     }
 
     /// Sets a codec-specific option.
@@ -333,6 +2103,12 @@ impl Encoder {
     /// These options are passed directly to the underlying codec and
     /// can be used to fine-tune encoding behavior.
     ///
+    /// Options may be changed between `add_image` calls: they are handed to
+    /// the codec with the next added frame and stay in effect for later
+    /// frames until set to another value. This allows per-scene tuning within
+    /// one sequence, for example a lower `cq-level` for keyframes. Keys may
+    /// be prefixed with `color:` or `alpha:` to target one plane type.
+    ///
     /// # Arguments
     /// * `key` - The option name
     /// * `value` - The option value
@@ -348,20 +2124,312 @@ impl Encoder {
         };
 
         if result != avifResult_AVIF_RESULT_OK {
-            Err(AvifError::from(result))
+            Err(AvifError::from(result)
+                .with_detail(Operation::SetCodecOption, format!("key \"{}\"", key)))
         } else {
+            self.codec_options.push((key.to_owned(), value.to_owned()));
             Ok(())
         }
     }
 
+    /// Discards codec-specific options that have not been applied yet.
+    ///
+    /// Options set since the last `add_image` call are dropped, and options
+    /// are no longer carried over by `encode_to_size` and
+    /// `encode_to_quality`. Options already applied to earlier frames stay in
+    /// effect in the codec; set them explicitly to change them back.
+    pub fn clear_codec_specific_options(&mut self) {
+        for (key, _) in self.codec_options.drain(..) {
+            if let Ok(key) = CString::new(key) {
+                // A null value removes the key from the pending options.
+                unsafe {
+                    avifEncoderSetCodecSpecificOption(self.inner, key.as_ptr(), std::ptr::null());
+                }
+            }
+        }
+    }
+
+    /// Selects a codec and applies typed codec-specific options to it.
+    ///
+    /// # Arguments
+    /// * `options` - Options for one of the encoders in [`crate::codec`]
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if an option is out of range or
+    /// rejected by libavif.
+    pub fn set_codec_options(&mut self, options: &impl CodecOptions) -> Result<()> {
+        options.validate()?;
+        self.set_codec_choice(options.codec());
+        for (key, value) in options.to_pairs() {
+            self.set_codec_specific_option(key, &value)?;
+        }
+        Ok(())
+    }
+
+    /// Enables film grain synthesis with the selected codec.
+    ///
+    /// The encoder denoises the source and signals grain parameters, so the
+    /// grain is synthesized at decode time instead of spending bits on noise.
+    /// The strength is mapped to the codec's own option: `denoise-noise-level`
+    /// for aom and `film-grain` for SVT-AV1. rav1e's C API has no film grain
+    /// option, so rav1e is rejected.
+    ///
+    /// # Arguments
+    /// * `strength` - Grain strength from 0 (disabled) to 50
+    ///
+    /// # Returns
+    /// Ok(()) on success, `AvifError::NotImplemented` if the selected codec
+    /// is rav1e or another codec without film grain, or an error if the
+    /// strength is out of range or no encoder is available.
+    pub fn set_film_grain(&mut self, strength: u8) -> Result<()> {
+        if strength > 50 {
+            return Err(AvifError::InvalidArgument);
+        }
+        let name = unsafe {
+            avifCodecName(
+                (*self.inner).codecChoice,
+                avifCodecFlag_AVIF_CODEC_FLAG_CAN_ENCODE as avifCodecFlags,
+            )
+        };
+        if name.is_null() {
+            return Err(AvifError::NoCodecAvailable);
+        }
+        match unsafe { CStr::from_ptr(name) }.to_bytes() {
+            b"aom" => self.set_codec_specific_option("denoise-noise-level", &strength.to_string()),
+            b"svt" => self.set_codec_specific_option("film-grain", &strength.to_string()),
+            _ => Err(AvifError::NotImplemented),
+        }
+    }
+
+    /// Encodes an image at the highest quality whose output fits a size
+    /// budget.
+    ///
+    /// The color quality is searched by bisection, encoding the image once
+    /// per step with this encoder's other settings. The search stops early
+    /// once a result is within `tolerance` of the budget. On success, this
+    /// encoder's quality is set to the chosen value and `stats` describes the
+    /// returned data.
+    ///
+    /// # Arguments
+    /// * `image` - The image to encode
+    /// * `target_bytes` - Maximum size of the encoded data
+    /// * `tolerance` - Acceptable fraction below the budget, such as 0.05
+    ///
+    /// # Returns
+    /// The encoded AVIF data, `AvifError::SizeBudgetExceeded` if even the
+    /// lowest quality does not fit, or an error if encoding fails.
+    pub fn encode_to_size(
+        &mut self,
+        image: &Image,
+        target_bytes: usize,
+        tolerance: f32,
+    ) -> Result<RwData> {
+        let good_enough = (target_bytes as f64 * (1.0 - tolerance.clamp(0.0, 1.0) as f64)) as usize;
+        let (mut low, mut high) = (0u8, 100u8);
+        let mut best: Option<(RwData, u8, avifIOStats)> = None;
+        let mut smallest = usize::MAX;
+        while low <= high {
+            self.check_cancelled()?;
+            let quality = low + (high - low) / 2;
+            let mut attempt = self.fresh()?;
+            attempt.set_quality(quality);
+            let output = attempt.write(image)?;
+            let size = output.as_slice().len();
+            smallest = smallest.min(size);
+            if size <= target_bytes {
+                let stats = unsafe { (*attempt.inner).ioStats };
+                best = Some((output, quality, stats));
+                if size >= good_enough || quality == 100 {
+                    break;
+                }
+                low = quality + 1;
+            } else if quality == 0 {
+                break;
+            } else {
+                high = quality - 1;
+            }
+        }
+
+        let (output, quality, stats) = best.ok_or(AvifError::SizeBudgetExceeded {
+            size: smallest,
+            budget: target_bytes,
+        })?;
+        self.set_quality(quality);
+        unsafe { (*self.inner).ioStats = stats };
+        Ok(output)
+    }
+
+    /// Encodes an image at the lowest quality that meets an objective
+    /// quality target.
+    ///
+    /// The color quality is searched by bisection; each step encodes the
+    /// image with this encoder's other settings, decodes the result and
+    /// measures it against `image`. On success, this encoder's quality is set
+    /// to the chosen value and `stats` describes the returned data.
+    ///
+    /// # Arguments
+    /// * `image` - The image to encode
+    /// * `target` - The metric and its minimum score
+    ///
+    /// # Returns
+    /// The smallest encoded AVIF data meeting the target,
+    /// `AvifError::QualityTargetNotMet` if even quality 100 falls short, or an
+    /// error if encoding or decoding fails.
+    pub fn encode_to_quality(&mut self, image: &Image, target: QualityTarget) -> Result<RwData> {
+        let mut decoder = Decoder::new()?;
+        let (mut low, mut high) = (0u8, 100u8);
+        let mut best: Option<(RwData, u8, avifIOStats)> = None;
+        while low <= high {
+            self.check_cancelled()?;
+            let quality = low + (high - low) / 2;
+            let mut attempt = self.fresh()?;
+            attempt.set_quality(quality);
+            let output = attempt.write(image)?;
+            let decoded = decoder.decode(output.as_slice())?;
+            if target.is_met(image, &decoded)? {
+                let stats = unsafe { (*attempt.inner).ioStats };
+                best = Some((output, quality, stats));
+                if quality == 0 {
+                    break;
+                }
+                high = quality - 1;
+            } else {
+                low = quality + 1;
+            }
+        }
+
+        let (output, quality, stats) = best.ok_or(AvifError::QualityTargetNotMet)?;
+        self.set_quality(quality);
+        unsafe { (*self.inner).ioStats = stats };
+        Ok(output)
+    }
+
+    /// Creates a new encoder with the same settings and codec-specific
+    /// options as this one, but no added images.
+    fn fresh(&self) -> Result<Encoder> {
+        let mut encoder = Encoder::new()?;
+        self.copy_settings(&mut encoder);
+        for (key, value) in &self.codec_options {
+            encoder.set_codec_specific_option(key, value)?;
+        }
+        Ok(encoder)
+    }
+
+    /// Clears the encoder's state so it can be used for a new output.
+    ///
+    /// Added images are discarded, while settings such as quality, speed and
+    /// tiling are kept. Codec-specific options are cleared so they do not
+    /// carry over between unrelated jobs, and a new cancel token is created.
+    /// This allows pooling pre-configured encoders.
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if the underlying encoder cannot be
+    /// recreated.
+    pub fn reset(&mut self) -> Result<()> {
+        let mut fresh = Encoder::new()?;
+        self.copy_settings(&mut fresh);
+        std::mem::swap(&mut self.inner, &mut fresh.inner);
+        self.codec_options.clear();
+        self.images_added = 0;
+        self.resolved_tiling = None;
+        self.cancel = CancelToken::default();
+        if let Some(deadline) = self.deadline.as_mut() {
+            deadline.started = None;
+        }
+        if let Some(tracker) = self.keyframes.as_mut() {
+            tracker.index = 0;
+            tracker.since_keyframe = 0;
+            tracker.previous_luma = None;
+        }
+        Ok(())
+    }
+
+    /// Copies the settings exposed by `avifEncoder` to another encoder.
+    fn copy_settings(&self, encoder: &mut Encoder) {
+        unsafe {
+            let (from, to) = (&*self.inner, &mut *encoder.inner);
+            to.codecChoice = from.codecChoice;
+            to.maxThreads = from.maxThreads;
+            to.speed = from.speed;
+            to.keyframeInterval = from.keyframeInterval;
+            to.timescale = from.timescale;
+            to.repetitionCount = from.repetitionCount;
+            to.extraLayerCount = from.extraLayerCount;
+            to.quality = from.quality;
+            to.qualityAlpha = from.qualityAlpha;
+            to.minQuantizer = from.minQuantizer;
+            to.maxQuantizer = from.maxQuantizer;
+            to.minQuantizerAlpha = from.minQuantizerAlpha;
+            to.maxQuantizerAlpha = from.maxQuantizerAlpha;
+            to.tileRowsLog2 = from.tileRowsLog2;
+            to.tileColsLog2 = from.tileColsLog2;
+            to.autoTiling = from.autoTiling;
+            to.scalingMode = from.scalingMode;
+            to.headerFormat = from.headerFormat;
+            #[cfg(feature = "gain-map")]
+            {
+                to.qualityGainMap = from.qualityGainMap;
+            }
+            #[cfg(feature = "sample-transform")]
+            {
+                to.sampleTransformRecipe = from.sampleTransformRecipe;
+            }
+        }
+        encoder.frame_duration = self.frame_duration;
+    }
+
     /// Returns the size of the gain map in bytes.
     ///
     /// Gain maps are used for HDR image support.
+    #[cfg(feature = "gain-map")]
     pub fn get_gain_map_size_bytes(&self) -> usize {
         unsafe { avifEncoderGetGainMapSizeBytes(self.inner) }
     }
 }
 
+/// Decodes an AVIF file and re-encodes it with new settings.
+///
+/// Both still images and image sequences are supported. Frame durations,
+/// the loop count, CICP values, the ICC profile, and Exif and XMP metadata
+/// are carried over from the input. The timescale and repetition count of
+/// the input are used unless `settings` sets them explicitly; durations are
+/// rescaled when the timescale changes.
+///
+/// # Arguments
+/// * `input` - The AVIF data to transcode
+/// * `settings` - Encoder settings for the output
+///
+/// # Returns
+/// The re-encoded AVIF data, or an error if decoding or encoding fails.
+pub fn transcode(input: &[u8], settings: &EncoderBuilder) -> Result<RwData> {
+    let mut encoder = settings.build()?;
+    let mut decoder = Decoder::new()?;
+    let mut first = true;
+    decoder.for_each_frame(input, |image, info| {
+        if info.image_count <= 1 {
+            return encoder.add_image(image, 1, AddImageFlags::SINGLE);
+        }
+        if first {
+            first = false;
+            if settings.timescale.is_none() {
+                encoder.set_timescale(info.timescale);
+            }
+            if settings.repetition_count.is_none() {
+                encoder.set_repetition_count(RepetitionCount::from(info.repetition_count))?;
+            }
+        }
+        let duration = if info.timescale == 0 || encoder.timescale() == info.timescale {
+            info.duration_in_timescales
+        } else {
+            let scaled = info.duration_in_timescales as u128 * encoder.timescale() as u128
+                / info.timescale as u128;
+            (scaled as u64).max(1)
+        };
+        encoder.add_image(image, duration, AddImageFlags::NONE)
+    })?;
+    encoder.finish()
+}
+
 impl Drop for Encoder {
     fn drop(&mut self) {
         if !self.inner.is_null() {
@@ -372,8 +2440,67 @@ impl Drop for Encoder {
     }
 }
 
+/// Creating the encoder can fail on allocation failure, in which case
+/// `default()` panics. Prefer `Encoder::new` or `Encoder::try_default` in
+/// library code.
 impl Default for Encoder {
     fn default() -> Self {
         Self::new().expect("Failed to create encoder")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantizer_from_quality_matches_libavif() {
+        assert_eq!(quantizer_from_quality(QUALITY_BEST), QUANTIZER_BEST_QUALITY);
+        assert_eq!(
+            quantizer_from_quality(QUALITY_WORST),
+            QUANTIZER_WORST_QUALITY
+        );
+        assert_eq!(quantizer_from_quality(50), 32);
+        assert_eq!(quantizer_from_quality(255), QUANTIZER_BEST_QUALITY);
+        for quality in 1..=QUALITY_BEST {
+            assert!(quantizer_from_quality(quality) <= quantizer_from_quality(quality - 1));
+        }
+    }
+
+    #[test]
+    fn quality_from_quantizer_inverts_quantizer_from_quality() {
+        assert_eq!(quality_from_quantizer(QUANTIZER_BEST_QUALITY), QUALITY_BEST);
+        assert_eq!(
+            quality_from_quantizer(QUANTIZER_WORST_QUALITY),
+            QUALITY_WORST
+        );
+        assert_eq!(quality_from_quantizer(255), QUALITY_WORST);
+        for quantizer in QUANTIZER_BEST_QUALITY..=QUANTIZER_WORST_QUALITY {
+            let quality = quality_from_quantizer(quantizer);
+            assert_eq!(quantizer_from_quality(quality), quantizer, "{}", quantizer);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "codec-rav1e")]
+    fn film_grain_is_rejected_for_rav1e() {
+        let mut encoder = Encoder::new().unwrap();
+        encoder.set_codec_choice(EncoderCodecChoice::Rav1e);
+        assert!(matches!(
+            encoder.set_film_grain(10),
+            Err(AvifError::NotImplemented)
+        ));
+    }
+
+    #[test]
+    fn quality_from_jpeg_interpolates_the_map() {
+        for (jpeg, avif) in JPEG_QUALITY_MAP {
+            assert_eq!(quality_from_jpeg(jpeg), avif);
+        }
+        assert_eq!(quality_from_jpeg(80), 64);
+        assert_eq!(quality_from_jpeg(255), quality_from_jpeg(100));
+        for quality in 1..=100 {
+            assert!(quality_from_jpeg(quality) >= quality_from_jpeg(quality - 1));
+        }
+    }
+}
@@ -2,19 +2,36 @@
 //!
 //! This module defines the `AvifError` enum which represents all possible
 //! error conditions that can occur during AVIF encoding and decoding operations.
+//!
+//! # Matching errors
+//!
+//! Errors from encoding, decoding and conversion calls are wrapped in
+//! `AvifError::Context`, which records the operation that failed. Patterns
+//! and comparisons against a specific kind, such as
+//! `Err(AvifError::InvalidImageGrid)` or `error == AvifError::IoError`, no
+//! longer match the wrapped error. Match on `error.without_context()`
+//! instead, which returns the innermost error whether or not it is wrapped,
+//! or use a classification helper such as `is_invalid_input`.
 
 #![allow(non_upper_case_globals)]
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
 use libavif_sys::*;
+use std::ffi::CStr;
 use std::fmt;
 
 /// Error types that can occur during AVIF operations.
 ///
 /// This enum represents all possible error conditions that can arise
 /// when encoding or decoding AVIF images using libavif.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// Failures of libavif calls carry libavif's diagnostic message, along with
+/// the operation that failed, in `AvifError::Context`. The boxed context
+/// makes `AvifError` `Clone` but not `Copy`, so code that copied errors out
+/// of a reference has to clone them.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum AvifError {
     /// An unknown error occurred
     UnknownError,
@@ -78,6 +95,318 @@ pub enum AvifError {
     InvalidToneMappedImage,
     /// Unknown error type with raw code
     UnknownType(u32),
+    /// A pixel buffer is smaller than its dimensions and format require
+    BufferTooSmall {
+        /// Minimum buffer size in bytes
+        required: usize,
+        /// Actual buffer size in bytes
+        actual: usize,
+    },
+    /// No encoding fits the requested size budget
+    SizeBudgetExceeded {
+        /// Size in bytes of the smallest encoding produced
+        size: usize,
+        /// Requested maximum size in bytes
+        budget: usize,
+    },
+    /// The operation was cancelled by the caller
+    Cancelled,
+    /// No encoding reaches the requested objective quality
+    QualityTargetNotMet,
+    /// The encode did not finish within the time budget
+    DeadlineExceeded,
+    /// An error annotated with the operation that failed; match on
+    /// `without_context()` to inspect the underlying error
+    Context(Box<Error>),
+    /// A row stride is smaller than one packed row of pixels
+    RowBytesTooSmall {
+        /// Minimum row stride in bytes
+        required: u32,
+        /// Actual row stride in bytes
+        actual: u32,
+    },
+    /// Image dimensions are too large for the buffer sizes derived from them
+    Overflow,
+}
+
+/// Operations whose failures are annotated with [`Error`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Adding an image or frame to the encoder
+    AddImage,
+    /// Adding a grid of images to the encoder
+    AddImageGrid,
+    /// Encoding a single image
+    Write,
+    /// Finishing an encode
+    Finish,
+    /// Setting a codec-specific option
+    SetCodecOption,
+    /// Converting RGB pixels to YUV
+    RgbToYuv,
+    /// Converting YUV pixels to RGB
+    YuvToRgb,
+    /// Parsing AVIF data
+    Parse,
+    /// Decoding an image or frame
+    Decode,
+    /// Reading an input image in another format
+    ReadInput,
+    /// Scaling a decoded image
+    Scale,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::AddImage => write!(f, "Add image"),
+            Operation::AddImageGrid => write!(f, "Add image grid"),
+            Operation::Write => write!(f, "Write"),
+            Operation::Finish => write!(f, "Finish"),
+            Operation::SetCodecOption => write!(f, "Set codec option"),
+            Operation::RgbToYuv => write!(f, "RGB to YUV conversion"),
+            Operation::YuvToRgb => write!(f, "YUV to RGB conversion"),
+            Operation::Parse => write!(f, "Parse"),
+            Operation::Decode => write!(f, "Decode"),
+            Operation::ReadInput => write!(f, "Read input"),
+            Operation::Scale => write!(f, "Scale"),
+        }
+    }
+}
+
+/// An error together with the operation that failed.
+///
+/// Library calls return these wrapped in `AvifError::Context`, so logs
+/// show, for example, whether an encode failed while converting RGB to YUV
+/// or while encoding alpha.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    /// The operation that failed
+    pub op: Operation,
+    /// The underlying error
+    pub source: AvifError,
+    /// Additional detail, such as libavif's diagnostic message
+    pub detail: Option<String>,
+}
+
+// The underlying error is left out, since it is also returned by `source`
+// and error reporters print the whole chain.
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed", self.op)?;
+        if let Some(detail) = &self.detail {
+            write!(f, " ({})", detail)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl AvifError {
+    /// Annotates the error with the operation that failed.
+    ///
+    /// Errors that already carry a context are returned unchanged, so the
+    /// innermost, most specific operation is kept.
+    pub(crate) fn context(self, op: Operation) -> Self {
+        match self {
+            AvifError::Context(_) => self,
+            source => AvifError::Context(Box::new(Error {
+                op,
+                source,
+                detail: None,
+            })),
+        }
+    }
+
+    /// Annotates the error with the operation that failed and a detail
+    /// message, such as the message of a third-party decoder.
+    pub(crate) fn with_detail(self, op: Operation, detail: impl Into<String>) -> Self {
+        AvifError::Context(Box::new(Error {
+            op,
+            source: self,
+            detail: Some(detail.into()),
+        }))
+    }
+
+    /// Creates an error from a libavif result and the diagnostics recorded
+    /// alongside it.
+    ///
+    /// The diagnostic message is attached as detail if libavif wrote one.
+    pub(crate) fn with_diagnostics(
+        op: Operation,
+        result: avifResult,
+        diag: &avifDiagnostics,
+    ) -> Self {
+        let message = unsafe { CStr::from_ptr(diag.error.as_ptr()) }.to_string_lossy();
+        AvifError::Context(Box::new(Error {
+            op,
+            source: AvifError::from(result),
+            detail: (!message.is_empty()).then(|| message.into_owned()),
+        }))
+    }
+
+    /// Returns the operation that failed, if known.
+    pub fn operation(&self) -> Option<Operation> {
+        match self {
+            AvifError::Context(context) => Some(context.op),
+            _ => None,
+        }
+    }
+
+    /// Returns libavif's diagnostic message or other detail, if attached.
+    pub fn diagnostic(&self) -> Option<&str> {
+        match self {
+            AvifError::Context(context) => context.detail.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the error without its context, for matching on the kind of
+    /// failure.
+    ///
+    /// Errors that are not wrapped in `AvifError::Context` are returned
+    /// unchanged, so this is safe to call on any error.
+    pub fn without_context(&self) -> &AvifError {
+        match self {
+            AvifError::Context(context) => context.source.without_context(),
+            other => other,
+        }
+    }
+
+    /// Returns the libavif result code corresponding to this error.
+    ///
+    /// Errors raised by this crate rather than libavif map to the closest
+    /// libavif code: invalid buffers to `AVIF_RESULT_INVALID_ARGUMENT`, and
+    /// size budget, quality target, deadline and cancellation failures to
+    /// `AVIF_RESULT_UNKNOWN_ERROR`. Context is discarded.
+    pub fn code(&self) -> u32 {
+        let result = match self.without_context() {
+            AvifError::UnknownError => avifResult_AVIF_RESULT_UNKNOWN_ERROR,
+            AvifError::InvalidFtyp => avifResult_AVIF_RESULT_INVALID_FTYP,
+            AvifError::NoContent => avifResult_AVIF_RESULT_NO_CONTENT,
+            AvifError::NoYuvFormatSelected => avifResult_AVIF_RESULT_NO_YUV_FORMAT_SELECTED,
+            AvifError::ReformatFailed => avifResult_AVIF_RESULT_REFORMAT_FAILED,
+            AvifError::UnsupportedDepth => avifResult_AVIF_RESULT_UNSUPPORTED_DEPTH,
+            AvifError::EncodeColorFailed => avifResult_AVIF_RESULT_ENCODE_COLOR_FAILED,
+            AvifError::EncodeAlphaFailed => avifResult_AVIF_RESULT_ENCODE_ALPHA_FAILED,
+            AvifError::BmffParseFailed => avifResult_AVIF_RESULT_BMFF_PARSE_FAILED,
+            AvifError::MissingImageItem => avifResult_AVIF_RESULT_MISSING_IMAGE_ITEM,
+            AvifError::DecodeColorFailed => avifResult_AVIF_RESULT_DECODE_COLOR_FAILED,
+            AvifError::DecodeAlphaFailed => avifResult_AVIF_RESULT_DECODE_ALPHA_FAILED,
+            AvifError::ColorAlphaSizeMismatch => avifResult_AVIF_RESULT_COLOR_ALPHA_SIZE_MISMATCH,
+            AvifError::IspeSizeMismatch => avifResult_AVIF_RESULT_ISPE_SIZE_MISMATCH,
+            AvifError::NoCodecAvailable => avifResult_AVIF_RESULT_NO_CODEC_AVAILABLE,
+            AvifError::NoImagesRemaining => avifResult_AVIF_RESULT_NO_IMAGES_REMAINING,
+            AvifError::InvalidExifPayload => avifResult_AVIF_RESULT_INVALID_EXIF_PAYLOAD,
+            AvifError::InvalidImageGrid => avifResult_AVIF_RESULT_INVALID_IMAGE_GRID,
+            AvifError::InvalidCodecSpecificOption => {
+                avifResult_AVIF_RESULT_INVALID_CODEC_SPECIFIC_OPTION
+            }
+            AvifError::TruncatedData => avifResult_AVIF_RESULT_TRUNCATED_DATA,
+            AvifError::IoNotSet => avifResult_AVIF_RESULT_IO_NOT_SET,
+            AvifError::IoError => avifResult_AVIF_RESULT_IO_ERROR,
+            AvifError::WaitingOnIo => avifResult_AVIF_RESULT_WAITING_ON_IO,
+            AvifError::InvalidArgument => avifResult_AVIF_RESULT_INVALID_ARGUMENT,
+            AvifError::NotImplemented => avifResult_AVIF_RESULT_NOT_IMPLEMENTED,
+            AvifError::OutOfMemory => avifResult_AVIF_RESULT_OUT_OF_MEMORY,
+            AvifError::IncompatibleImage => avifResult_AVIF_RESULT_INCOMPATIBLE_IMAGE,
+            AvifError::EncodeGainMapFailed => avifResult_AVIF_RESULT_ENCODE_GAIN_MAP_FAILED,
+            AvifError::DecodeGainMapFailed => avifResult_AVIF_RESULT_DECODE_GAIN_MAP_FAILED,
+            AvifError::InvalidToneMappedImage => avifResult_AVIF_RESULT_INVALID_TONE_MAPPED_IMAGE,
+            AvifError::UnknownType(code) => return *code,
+            AvifError::BufferTooSmall { .. }
+            | AvifError::RowBytesTooSmall { .. }
+            | AvifError::Overflow => avifResult_AVIF_RESULT_INVALID_ARGUMENT,
+            AvifError::SizeBudgetExceeded { .. }
+            | AvifError::Cancelled
+            | AvifError::QualityTargetNotMet
+            | AvifError::DeadlineExceeded
+            | AvifError::Context(_) => avifResult_AVIF_RESULT_UNKNOWN_ERROR,
+        };
+        result as u32
+    }
+
+    /// Creates an error from a libavif result code.
+    ///
+    /// This is the inverse of `code` for libavif errors. `From<avifResult>`
+    /// cannot be complemented by `TryFrom<u32>`, since `avifResult` is
+    /// itself a `u32` on most targets.
+    ///
+    /// # Returns
+    /// The error, or `None` for `AVIF_RESULT_OK`.
+    pub fn from_code(code: u32) -> Option<Self> {
+        let result = code as avifResult;
+        (result != avifResult_AVIF_RESULT_OK).then(|| AvifError::from(result))
+    }
+
+    /// Returns true if an allocation failed.
+    pub fn is_out_of_memory(&self) -> bool {
+        matches!(self.without_context(), AvifError::OutOfMemory)
+    }
+
+    /// Returns true if reading the input failed or the input is incomplete.
+    ///
+    /// `WaitingOnIo` means more data may still arrive, so retrying later can
+    /// succeed.
+    pub fn is_io(&self) -> bool {
+        matches!(
+            self.without_context(),
+            AvifError::IoNotSet
+                | AvifError::IoError
+                | AvifError::WaitingOnIo
+                | AvifError::TruncatedData
+        )
+    }
+
+    /// Returns true if the AV1 codec failed or none is available.
+    ///
+    /// These errors depend on the codec rather than on the input, so another
+    /// codec or different codec settings may succeed.
+    pub fn is_codec_failure(&self) -> bool {
+        matches!(
+            self.without_context(),
+            AvifError::NoCodecAvailable
+                | AvifError::EncodeColorFailed
+                | AvifError::EncodeAlphaFailed
+                | AvifError::DecodeColorFailed
+                | AvifError::DecodeAlphaFailed
+                | AvifError::EncodeGainMapFailed
+                | AvifError::DecodeGainMapFailed
+        )
+    }
+
+    /// Returns true if the input data, image or arguments are invalid.
+    ///
+    /// Retrying with the same input fails again, so such inputs should be
+    /// rejected.
+    pub fn is_invalid_input(&self) -> bool {
+        matches!(
+            self.without_context(),
+            AvifError::InvalidFtyp
+                | AvifError::NoContent
+                | AvifError::NoYuvFormatSelected
+                | AvifError::UnsupportedDepth
+                | AvifError::BmffParseFailed
+                | AvifError::MissingImageItem
+                | AvifError::ColorAlphaSizeMismatch
+                | AvifError::IspeSizeMismatch
+                | AvifError::InvalidExifPayload
+                | AvifError::InvalidImageGrid
+                | AvifError::InvalidCodecSpecificOption
+                | AvifError::InvalidArgument
+                | AvifError::IncompatibleImage
+                | AvifError::InvalidToneMappedImage
+                | AvifError::BufferTooSmall { .. }
+                | AvifError::RowBytesTooSmall { .. }
+                | AvifError::Overflow
+        )
+    }
 }
 
 impl From<avifResult> for AvifError {
@@ -156,9 +485,63 @@ impl fmt::Display for AvifError {
             AvifError::EncodeGainMapFailed => write!(f, "Encode gain map failed"),
             AvifError::DecodeGainMapFailed => write!(f, "Decode gain map failed"),
             AvifError::InvalidToneMappedImage => write!(f, "Invalid tone mapped image"),
-            AvifError::UnknownType(code) => write!(f, "Unknown error type: {}", code),
+            AvifError::UnknownType(code) => {
+                let name = unsafe { CStr::from_ptr(avifResultToString(*code as avifResult)) };
+                write!(f, "{} (code {})", name.to_string_lossy(), code)
+            }
+            AvifError::BufferTooSmall { required, actual } => write!(
+                f,
+                "Buffer too small: {} bytes required, {} provided",
+                required, actual
+            ),
+            AvifError::SizeBudgetExceeded { size, budget } => write!(
+                f,
+                "Size budget exceeded: smallest encoding is {} bytes, budget is {}",
+                size, budget
+            ),
+            AvifError::Cancelled => write!(f, "Cancelled"),
+            AvifError::QualityTargetNotMet => write!(f, "Quality target not met"),
+            AvifError::DeadlineExceeded => write!(f, "Deadline exceeded"),
+            AvifError::Context(context) => write!(f, "{}", context),
+            AvifError::RowBytesTooSmall { required, actual } => write!(
+                f,
+                "Row bytes too small: at least {} required, {} provided",
+                required, actual
+            ),
+            AvifError::Overflow => write!(f, "Image dimensions overflow"),
         }
     }
 }
 
-impl std::error::Error for AvifError {}
+impl std::error::Error for AvifError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AvifError::Context(context) => Some(&context.source),
+            _ => None,
+        }
+    }
+}
+
+impl From<AvifError> for std::io::Error {
+    fn from(error: AvifError) -> Self {
+        std::io::Error::other(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn context_display_leaves_the_cause_to_source() {
+        let error = AvifError::IoError.with_detail(Operation::ReadInput, "unexpected end of file");
+        assert_eq!(
+            error.to_string(),
+            "Read input failed (unexpected end of file)"
+        );
+        let source = error.source().unwrap();
+        assert_eq!(source.to_string(), AvifError::IoError.to_string());
+        assert!(source.source().is_none());
+    }
+}
@@ -0,0 +1,280 @@
+//! Exif orientation handling.
+//!
+//! AVIF expresses orientation with the `irot` and `imir` transform
+//! properties, while images from cameras and other formats carry it in the
+//! Exif orientation tag. The methods here read that tag and move it to the
+//! transform properties.
+
+use crate::{Axis, Image, Result, Rotation};
+
+/// Tag number of the orientation entry in the Exif IFD0.
+const ORIENTATION_TAG: u16 = 0x0112;
+/// Type code of a TIFF SHORT value.
+const TIFF_SHORT: u16 = 3;
+
+/// Locates the orientation value in an Exif payload.
+///
+/// The payload starts with the TIFF header, optionally preceded by the
+/// `Exif\0\0` identifier used in JPEG files.
+///
+/// # Returns
+/// The byte offset of the value and whether the payload is big-endian, or
+/// `None` if the payload has no orientation entry or it is cut off.
+fn orientation_offset(exif: &[u8]) -> Option<(usize, bool)> {
+    let tiff = if exif.starts_with(b"Exif\0\0") { 6 } else { 0 };
+    let big_endian = match exif.get(tiff..tiff + 4)? {
+        b"MM\0*" => true,
+        b"II*\0" => false,
+        _ => return None,
+    };
+    let read_u16 = |at: usize| {
+        let bytes = [*exif.get(at)?, *exif.get(at + 1)?];
+        Some(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+    let read_u32 = |at: usize| {
+        let (high, low) = (read_u16(at)? as u32, read_u16(at + 2)? as u32);
+        Some(if big_endian {
+            high << 16 | low
+        } else {
+            low << 16 | high
+        })
+    };
+
+    let ifd = tiff.checked_add(read_u32(tiff + 4)? as usize)?;
+    let entries = read_u16(ifd)? as usize;
+    (0..entries)
+        .map(|i| ifd + 2 + i * 12)
+        .find(|&entry| read_u16(entry) == Some(ORIENTATION_TAG))
+        .filter(|&entry| read_u16(entry + 2) == Some(TIFF_SHORT))
+        .map(|entry| entry + 8)
+        .filter(|&value| read_u16(value).is_some())
+        .map(|value| (value, big_endian))
+}
+
+/// Returns the `irot` and `imir` transforms equivalent to an Exif
+/// orientation, with the rotation applied before the mirror.
+fn orientation_transform(orientation: u8) -> (Rotation, Option<Axis>) {
+    match orientation {
+        2 => (Rotation::None, Some(Axis::Vertical)),
+        3 => (Rotation::Ccw180, None),
+        4 => (Rotation::None, Some(Axis::Horizontal)),
+        5 => (Rotation::Ccw90, Some(Axis::Horizontal)),
+        6 => (Rotation::Ccw270, None),
+        7 => (Rotation::Ccw270, Some(Axis::Horizontal)),
+        8 => (Rotation::Ccw90, None),
+        _ => (Rotation::None, None),
+    }
+}
+
+impl Image {
+    /// Returns the orientation stored in the Exif metadata.
+    ///
+    /// # Returns
+    /// The orientation from 1 to 8, or `None` if the image has no Exif
+    /// metadata or it has no valid orientation entry.
+    pub fn exif_orientation(&self) -> Option<u8> {
+        let exif = self.exif();
+        let (offset, big_endian) = orientation_offset(exif)?;
+        let bytes = [*exif.get(offset)?, *exif.get(offset + 1)?];
+        let value = if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        };
+        (1..=8).contains(&value).then_some(value as u8)
+    }
+
+    /// Moves the Exif orientation to the `irot` and `imir` properties.
+    ///
+    /// The rotation and mirror are replaced by the equivalent of the Exif
+    /// orientation, and the orientation tag is reset to 1 so that viewers
+    /// honoring both do not apply it twice.
+    ///
+    /// # Returns
+    /// Whether an orientation was found, or an error if the metadata cannot
+    /// be updated.
+    pub fn extract_exif_orientation(&mut self) -> Result<bool> {
+        let Some(orientation) = self.exif_orientation() else {
+            return Ok(false);
+        };
+        let (rotation, mirror) = orientation_transform(orientation);
+        self.set_rotation(rotation);
+        self.set_mirror(mirror);
+        if orientation != 1 {
+            self.reset_exif_orientation()?;
+        }
+        Ok(true)
+    }
+
+    /// Sets the Exif orientation tag to 1, if present.
+    pub(crate) fn reset_exif_orientation(&mut self) -> Result<()> {
+        let mut exif = self.exif().to_vec();
+        if let Some((offset, big_endian)) = orientation_offset(&exif) {
+            let value = if big_endian {
+                1u16.to_be_bytes()
+            } else {
+                1u16.to_le_bytes()
+            };
+            exif[offset..offset + 2].copy_from_slice(&value);
+            self.set_exif(&exif)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a TIFF payload whose IFD0 holds an `ImageWidth` entry followed
+    /// by the orientation entry.
+    fn tiff(big_endian: bool, orientation: u16) -> Vec<u8> {
+        let u16_bytes = |v: u16| {
+            if big_endian {
+                v.to_be_bytes()
+            } else {
+                v.to_le_bytes()
+            }
+        };
+        let u32_bytes = |v: u32| {
+            if big_endian {
+                v.to_be_bytes()
+            } else {
+                v.to_le_bytes()
+            }
+        };
+        let mut data = if big_endian {
+            b"MM\0*".to_vec()
+        } else {
+            b"II*\0".to_vec()
+        };
+        data.extend_from_slice(&u32_bytes(8));
+        data.extend_from_slice(&u16_bytes(2));
+        for (tag, value) in [(0x0100, 640), (ORIENTATION_TAG, orientation)] {
+            data.extend_from_slice(&u16_bytes(tag));
+            data.extend_from_slice(&u16_bytes(TIFF_SHORT));
+            data.extend_from_slice(&u32_bytes(1));
+            data.extend_from_slice(&u16_bytes(value));
+            data.extend_from_slice(&[0, 0]);
+        }
+        data.extend_from_slice(&u32_bytes(0));
+        data
+    }
+
+    #[test]
+    fn finds_orientation_in_both_byte_orders() {
+        for big_endian in [false, true] {
+            let data = tiff(big_endian, 6);
+            assert_eq!(orientation_offset(&data), Some((30, big_endian)));
+
+            let data = [&b"Exif\0\0"[..], &data].concat();
+            assert_eq!(orientation_offset(&data), Some((36, big_endian)));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_payloads() {
+        assert_eq!(orientation_offset(&[]), None);
+        assert_eq!(orientation_offset(b"Exif\0\0"), None);
+
+        let mut data = tiff(true, 6);
+        data[..4].copy_from_slice(b"MM*\0");
+        assert_eq!(orientation_offset(&data), None);
+
+        // The IFD offset points past the payload.
+        let mut data = tiff(false, 6);
+        data[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(orientation_offset(&data), None);
+
+        // The orientation entry is not a SHORT.
+        let mut data = tiff(false, 6);
+        data[24] = 4;
+        assert_eq!(orientation_offset(&data), None);
+
+        // The entry count claims more entries than the payload holds.
+        let mut data = tiff(false, 6);
+        data[8..10].copy_from_slice(&1000u16.to_le_bytes());
+        assert_eq!(orientation_offset(&data), Some((30, false)));
+        data[8..10].copy_from_slice(&1u16.to_le_bytes());
+        assert_eq!(orientation_offset(&data), None);
+    }
+
+    #[test]
+    fn rejects_truncated_payloads() {
+        for big_endian in [false, true] {
+            let data = tiff(big_endian, 6);
+            for len in 0..32 {
+                assert_eq!(orientation_offset(&data[..len]), None, "{} bytes", len);
+            }
+            assert_eq!(orientation_offset(&data[..32]), Some((30, big_endian)));
+        }
+    }
+
+    type Grid = Vec<Vec<u8>>;
+
+    fn transpose(grid: &Grid) -> Grid {
+        (0..grid[0].len())
+            .map(|x| grid.iter().map(|row| row[x]).collect())
+            .collect()
+    }
+
+    fn flip_left_right(grid: &Grid) -> Grid {
+        grid.iter()
+            .map(|row| row.iter().rev().copied().collect())
+            .collect()
+    }
+
+    fn flip_top_bottom(grid: &Grid) -> Grid {
+        grid.iter().rev().cloned().collect()
+    }
+
+    fn rotate_ccw(grid: &Grid) -> Grid {
+        flip_top_bottom(&transpose(grid))
+    }
+
+    /// Applies `irot` and then `imir`, as a decoder displays the image.
+    fn apply_transform(grid: &Grid, rotation: Rotation, mirror: Option<Axis>) -> Grid {
+        let turns = match rotation {
+            Rotation::None => 0,
+            Rotation::Ccw90 => 1,
+            Rotation::Ccw180 => 2,
+            Rotation::Ccw270 => 3,
+        };
+        let grid = (0..turns).fold(grid.clone(), |grid, _| rotate_ccw(&grid));
+        match mirror {
+            Some(Axis::Horizontal) => flip_top_bottom(&grid),
+            Some(Axis::Vertical) => flip_left_right(&grid),
+            None => grid,
+        }
+    }
+
+    #[test]
+    fn transforms_match_exif_orientations() {
+        let stored: Grid = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        // How the stored image is displayed under each orientation, as
+        // defined by the Exif specification.
+        let expected: [Grid; 8] = [
+            stored.clone(),
+            flip_left_right(&stored),
+            flip_top_bottom(&flip_left_right(&stored)),
+            flip_top_bottom(&stored),
+            transpose(&stored),
+            flip_left_right(&transpose(&stored)),
+            flip_top_bottom(&flip_left_right(&transpose(&stored))),
+            flip_top_bottom(&transpose(&stored)),
+        ];
+        for (orientation, expected) in (1..=8).zip(expected) {
+            let (rotation, mirror) = orientation_transform(orientation);
+            assert_eq!(
+                apply_transform(&stored, rotation, mirror),
+                expected,
+                "orientation {}",
+                orientation
+            );
+        }
+    }
+}
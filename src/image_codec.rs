@@ -0,0 +1,376 @@
+//! Integration with the `image` crate.
+//!
+//! This module implements the `image` crate's decoder and encoder traits on
+//! top of libavif, so applications built on `image` can read and write AVIF
+//! files through this crate. Call [`register_hooks`] once at startup to make
+//! `image::open` and `image::ImageReader` use it for `.avif` files.
+//!
+//! `Image::from_dynamic` and `Image::to_dynamic` convert between this
+//! crate's YUV images and `image::DynamicImage`.
+//!
+//! The `image` crate has no hook for encoders, so `save_with_format` keeps
+//! using its built-in AVIF encoder; pass an [`AvifEncoder`] to
+//! `DynamicImage::write_with_encoder` instead.
+
+use crate::rgb::image_from_rgb;
+use crate::{
+    AvifError, BitDepth, Channel, Decoder, EncoderBuilder, Image, PixelFormat, RgbFormat, RgbImage,
+};
+use image::error::{
+    DecodingError, EncodingError, ImageFormatHint, UnsupportedError, UnsupportedErrorKind,
+};
+use image::hooks::{register_decoding_hook, register_format_detection_hook};
+use image::{
+    ColorType, DynamicImage, ExtendedColorType, ImageBuffer, ImageDecoder, ImageEncoder,
+    ImageError, ImageFormat, ImageResult,
+};
+use std::io::{Read, Write};
+
+/// Signature of an `ftyp` box with the `avif` major brand.
+const AVIF_SIGNATURE: &[u8] = b"\0\0\0\0ftypavif";
+/// Signature of an `ftyp` box with the `avis` major brand.
+const AVIS_SIGNATURE: &[u8] = b"\0\0\0\0ftypavis";
+/// Mask ignoring the box size in the signatures.
+const SIGNATURE_MASK: &[u8] = b"\0\0\0\0\xff\xff\xff\xff\xff\xff\xff\xff";
+
+/// Registers this crate's AVIF decoder with the `image` crate.
+///
+/// Afterwards `image::open` and `image::ImageReader` decode files with the
+/// `.avif` extension, or whose contents look like AVIF, through libavif.
+///
+/// # Returns
+/// `false` if a decoder was already registered for AVIF, in which case it is
+/// kept.
+pub fn register_hooks() -> bool {
+    let registered = register_decoding_hook(
+        "avif".into(),
+        Box::new(|reader| Ok(Box::new(AvifDecoder::new(reader)?))),
+    );
+    if registered {
+        register_format_detection_hook("avif".into(), AVIF_SIGNATURE, Some(SIGNATURE_MASK));
+        register_format_detection_hook("avif".into(), AVIS_SIGNATURE, Some(SIGNATURE_MASK));
+    }
+    registered
+}
+
+/// Converts an error of this crate into an `image` decoding error.
+fn decoding_error(error: AvifError) -> ImageError {
+    ImageError::Decoding(DecodingError::new(
+        ImageFormatHint::Exact(ImageFormat::Avif),
+        error,
+    ))
+}
+
+/// Converts an error of this crate into an `image` encoding error.
+fn encoding_error(error: AvifError) -> ImageError {
+    ImageError::Encoding(EncodingError::new(
+        ImageFormatHint::Exact(ImageFormat::Avif),
+        error,
+    ))
+}
+
+/// Returns the RGB format matching an image's channels.
+fn rgb_format(image: &Image) -> RgbFormat {
+    let gray = image.plane(Channel::U).is_none();
+    match (gray, image.has_alpha()) {
+        (true, false) => RgbFormat::Gray,
+        (true, true) => RgbFormat::GrayA,
+        (false, false) => RgbFormat::Rgb,
+        (false, true) => RgbFormat::Rgba,
+    }
+}
+
+/// Converts an image to tightly packed pixels, with 8-bit samples for 8-bit
+/// images and full-range 16-bit samples otherwise.
+fn image_to_rgb(image: &Image, format: RgbFormat, buf: &mut [u8]) -> crate::Result<()> {
+    let mut rgb = RgbImage::for_image(image, format, buf)?;
+    if image.depth() != BitDepth::Eight {
+        // Samples above 8 bits occupy two bytes at any depth, so the buffer
+        // size stays the same.
+        rgb.inner.depth = 16;
+    }
+    rgb.convert_from_yuv(image)
+}
+
+impl Image {
+    /// Creates an image from an `image` crate image.
+    ///
+    /// 8-bit images produce 8-bit YUV and 16-bit images 10-bit YUV. Color
+    /// images are converted to `Yuv444` so no chroma detail is lost; luma
+    /// images produce `Yuv400`. Floating-point images are converted to 16
+    /// bits first.
+    ///
+    /// # Arguments
+    /// * `dynamic` - The image to convert
+    ///
+    /// # Returns
+    /// The YUV image, or an error if conversion fails.
+    pub fn from_dynamic(dynamic: &DynamicImage) -> crate::Result<Self> {
+        let (width, height) = (dynamic.width(), dynamic.height());
+        let convert = |buf: &[u8], format, wide| {
+            image_from_rgb(buf, width, height, format, wide, PixelFormat::Yuv444, false)
+        };
+        match dynamic {
+            DynamicImage::ImageLuma8(img) => convert(img.as_raw(), RgbFormat::Gray, false),
+            DynamicImage::ImageLumaA8(img) => convert(img.as_raw(), RgbFormat::GrayA, false),
+            DynamicImage::ImageRgb8(img) => convert(img.as_raw(), RgbFormat::Rgb, false),
+            DynamicImage::ImageRgba8(img) => convert(img.as_raw(), RgbFormat::Rgba, false),
+            DynamicImage::ImageLuma16(img) => {
+                convert(u16_bytes(img.as_raw()), RgbFormat::Gray, true)
+            }
+            DynamicImage::ImageLumaA16(img) => {
+                convert(u16_bytes(img.as_raw()), RgbFormat::GrayA, true)
+            }
+            DynamicImage::ImageRgb16(img) => convert(u16_bytes(img.as_raw()), RgbFormat::Rgb, true),
+            other => {
+                let img = other.to_rgba16();
+                convert(u16_bytes(img.as_raw()), RgbFormat::Rgba, true)
+            }
+        }
+    }
+
+    /// Converts the image to an `image` crate image.
+    ///
+    /// 8-bit images produce 8-bit pixels and deeper images 16-bit pixels
+    /// scaled to the full range. `Yuv400` images produce luma pixels, and
+    /// alpha is kept if present.
+    ///
+    /// # Returns
+    /// The converted image, or an error if conversion fails.
+    pub fn to_dynamic(&self) -> crate::Result<DynamicImage> {
+        let (width, height) = (self.width(), self.height());
+        let format = rgb_format(self);
+        let mut buf = vec![0u8; RgbImage::required_buffer_size(self, format)];
+        image_to_rgb(self, format, &mut buf)?;
+
+        let dynamic = if self.depth() == BitDepth::Eight {
+            match format {
+                RgbFormat::Gray => {
+                    ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageLuma8)
+                }
+                RgbFormat::GrayA => {
+                    ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageLumaA8)
+                }
+                RgbFormat::Rgb => {
+                    ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageRgb8)
+                }
+                _ => ImageBuffer::from_raw(width, height, buf).map(DynamicImage::ImageRgba8),
+            }
+        } else {
+            let samples: Vec<u16> = buf
+                .chunks_exact(2)
+                .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+                .collect();
+            match format {
+                RgbFormat::Gray => {
+                    ImageBuffer::from_raw(width, height, samples).map(DynamicImage::ImageLuma16)
+                }
+                RgbFormat::GrayA => {
+                    ImageBuffer::from_raw(width, height, samples).map(DynamicImage::ImageLumaA16)
+                }
+                RgbFormat::Rgb => {
+                    ImageBuffer::from_raw(width, height, samples).map(DynamicImage::ImageRgb16)
+                }
+                _ => ImageBuffer::from_raw(width, height, samples).map(DynamicImage::ImageRgba16),
+            }
+        };
+        // The buffer is sized by `required_buffer_size`, so this only fails
+        // if the image is inconsistent.
+        dynamic.ok_or(AvifError::IncompatibleImage)
+    }
+}
+
+/// Returns native-endian bytes of 16-bit samples.
+fn u16_bytes(samples: &[u16]) -> &[u8] {
+    // SAFETY: any initialized memory is a valid byte slice, and the length
+    // covers exactly the samples.
+    unsafe { std::slice::from_raw_parts(samples.as_ptr().cast(), std::mem::size_of_val(samples)) }
+}
+
+/// AVIF decoder implementing `image::ImageDecoder`.
+///
+/// The first image of the file is decoded when the decoder is created;
+/// `read_image` only converts it to the requested pixel layout. Grayscale
+/// images are returned as luma, and images deeper than 8 bits as 16-bit
+/// samples scaled to the full `u16` range.
+pub struct AvifDecoder {
+    image: Image,
+}
+
+impl AvifDecoder {
+    /// Reads and decodes AVIF data.
+    ///
+    /// # Arguments
+    /// * `reader` - Source of the encoded data; it is read to the end
+    ///
+    /// # Returns
+    /// The decoder, or an error if reading or decoding fails.
+    pub fn new<R: Read>(mut reader: R) -> ImageResult<Self> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut decoder = Decoder::new().map_err(decoding_error)?;
+        let image = decoder.decode(&data).map_err(decoding_error)?;
+        Ok(Self { image })
+    }
+
+    /// Returns the decoded image.
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+}
+
+impl ImageDecoder for AvifDecoder {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.image.width(), self.image.height())
+    }
+
+    fn color_type(&self) -> ColorType {
+        let wide = self.image.depth() != BitDepth::Eight;
+        match (rgb_format(&self.image), wide) {
+            (RgbFormat::Gray, false) => ColorType::L8,
+            (RgbFormat::Gray, true) => ColorType::L16,
+            (RgbFormat::GrayA, false) => ColorType::La8,
+            (RgbFormat::GrayA, true) => ColorType::La16,
+            (RgbFormat::Rgb, false) => ColorType::Rgb8,
+            (RgbFormat::Rgb, true) => ColorType::Rgb16,
+            (_, false) => ColorType::Rgba8,
+            (_, true) => ColorType::Rgba16,
+        }
+    }
+
+    fn icc_profile(&mut self) -> ImageResult<Option<Vec<u8>>> {
+        Ok(Some(self.image.icc().to_vec()).filter(|icc| !icc.is_empty()))
+    }
+
+    fn exif_metadata(&mut self) -> ImageResult<Option<Vec<u8>>> {
+        Ok(Some(self.image.exif().to_vec()).filter(|exif| !exif.is_empty()))
+    }
+
+    fn xmp_metadata(&mut self) -> ImageResult<Option<Vec<u8>>> {
+        Ok(Some(self.image.xmp().to_vec()).filter(|xmp| !xmp.is_empty()))
+    }
+
+    fn read_image(self, buf: &mut [u8]) -> ImageResult<()> {
+        image_to_rgb(&self.image, rgb_format(&self.image), buf).map_err(decoding_error)
+    }
+
+    fn read_image_boxed(self: Box<Self>, buf: &mut [u8]) -> ImageResult<()> {
+        (*self).read_image(buf)
+    }
+}
+
+/// AVIF encoder implementing `image::ImageEncoder`.
+///
+/// 8-bit input is encoded at 8 bits per sample and 16-bit input at 10 bits.
+/// Luma input is encoded as monochrome `Yuv400`; color input uses the
+/// configured YUV format, `Yuv420` by default.
+pub struct AvifEncoder<W: Write> {
+    writer: W,
+    settings: EncoderBuilder,
+    yuv_format: PixelFormat,
+    icc: Vec<u8>,
+    exif: Vec<u8>,
+}
+
+impl<W: Write> AvifEncoder<W> {
+    /// Creates an encoder with libavif's default settings.
+    ///
+    /// # Arguments
+    /// * `writer` - Destination of the encoded data
+    pub fn new(writer: W) -> Self {
+        Self::with_settings(writer, EncoderBuilder::new())
+    }
+
+    /// Creates an encoder with the given quality.
+    ///
+    /// # Arguments
+    /// * `writer` - Destination of the encoded data
+    /// * `quality` - Quality from 0 (lowest) to 100 (lossless)
+    pub fn with_quality(writer: W, quality: u8) -> Self {
+        Self::with_settings(writer, EncoderBuilder::new().quality(quality))
+    }
+
+    /// Creates an encoder with the given settings.
+    ///
+    /// # Arguments
+    /// * `writer` - Destination of the encoded data
+    /// * `settings` - Encoder settings, validated when the image is written
+    pub fn with_settings(writer: W, settings: EncoderBuilder) -> Self {
+        Self {
+            writer,
+            settings,
+            yuv_format: PixelFormat::Yuv420,
+            icc: Vec::new(),
+            exif: Vec::new(),
+        }
+    }
+
+    /// Sets the YUV format color images are encoded as.
+    pub fn with_yuv_format(mut self, yuv_format: PixelFormat) -> Self {
+        self.yuv_format = yuv_format;
+        self
+    }
+
+    /// Converts the pixels to a YUV image carrying the metadata to embed.
+    fn to_image(
+        &self,
+        buf: &[u8],
+        width: u32,
+        height: u32,
+        color_type: ExtendedColorType,
+    ) -> ImageResult<Image> {
+        let (format, wide) = match color_type {
+            ExtendedColorType::L8 => (RgbFormat::Gray, false),
+            ExtendedColorType::La8 => (RgbFormat::GrayA, false),
+            ExtendedColorType::Rgb8 => (RgbFormat::Rgb, false),
+            ExtendedColorType::Rgba8 => (RgbFormat::Rgba, false),
+            ExtendedColorType::L16 => (RgbFormat::Gray, true),
+            ExtendedColorType::La16 => (RgbFormat::GrayA, true),
+            ExtendedColorType::Rgb16 => (RgbFormat::Rgb, true),
+            ExtendedColorType::Rgba16 => (RgbFormat::Rgba, true),
+            _ => {
+                return Err(ImageError::Unsupported(
+                    UnsupportedError::from_format_and_kind(
+                        ImageFormat::Avif.into(),
+                        UnsupportedErrorKind::Color(color_type),
+                    ),
+                ));
+            }
+        };
+        let mut image = image_from_rgb(buf, width, height, format, wide, self.yuv_format, false)
+            .map_err(encoding_error)?;
+        if !self.icc.is_empty() {
+            image.set_icc(&self.icc).map_err(encoding_error)?;
+        }
+        if !self.exif.is_empty() {
+            image.set_exif(&self.exif).map_err(encoding_error)?;
+        }
+        Ok(image)
+    }
+}
+
+impl<W: Write> ImageEncoder for AvifEncoder<W> {
+    fn write_image(
+        mut self,
+        buf: &[u8],
+        width: u32,
+        height: u32,
+        color_type: ExtendedColorType,
+    ) -> ImageResult<()> {
+        let image = self.to_image(buf, width, height, color_type)?;
+        let mut encoder = self.settings.build().map_err(encoding_error)?;
+        let output = encoder.write(&image).map_err(encoding_error)?;
+        self.writer.write_all(output.as_slice())?;
+        Ok(())
+    }
+
+    fn set_icc_profile(&mut self, icc_profile: Vec<u8>) -> Result<(), UnsupportedError> {
+        self.icc = icc_profile;
+        Ok(())
+    }
+
+    fn set_exif_metadata(&mut self, exif: Vec<u8>) -> Result<(), UnsupportedError> {
+        self.exif = exif;
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+//! Version and capability information about the linked libavif.
+//!
+//! These functions report what the library was built with, so applications
+//! can display it or pick a codec at runtime instead of assuming one from the
+//! enabled cargo features.
+
+use crate::decoder::DecoderCodecChoice;
+use crate::encoder::EncoderCodecChoice;
+use libavif_sys::*;
+use std::ffi::{CStr, c_char};
+
+/// Every specific codec libavif knows about, in its order of preference.
+const CODEC_CHOICES: [avifCodecChoice; 6] = [
+    avifCodecChoice_AVIF_CODEC_CHOICE_AOM,
+    avifCodecChoice_AVIF_CODEC_CHOICE_DAV1D,
+    avifCodecChoice_AVIF_CODEC_CHOICE_LIBGAV1,
+    avifCodecChoice_AVIF_CODEC_CHOICE_RAV1E,
+    avifCodecChoice_AVIF_CODEC_CHOICE_SVT,
+    avifCodecChoice_AVIF_CODEC_CHOICE_AVM,
+];
+
+/// An AV1 codec compiled into libavif.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodecInfo {
+    /// Short name of the codec, such as `dav1d` or `rav1e`
+    pub name: &'static str,
+    /// Whether the codec can encode
+    pub can_encode: bool,
+    /// Whether the codec can decode
+    pub can_decode: bool,
+    choice: avifCodecChoice,
+}
+
+impl CodecInfo {
+    /// Returns the choice selecting this codec for encoding.
+    ///
+    /// # Returns
+    /// The choice, or `None` if the codec cannot encode.
+    pub fn encoder_choice(&self) -> Option<EncoderCodecChoice> {
+        self.can_encode
+            .then(|| EncoderCodecChoice::from(self.choice))
+    }
+
+    /// Returns the choice selecting this codec for decoding.
+    ///
+    /// # Returns
+    /// The choice, or `None` if the codec cannot decode.
+    pub fn decoder_choice(&self) -> Option<DecoderCodecChoice> {
+        self.can_decode
+            .then(|| DecoderCodecChoice::from(self.choice))
+    }
+}
+
+/// Returns the version of the linked libavif, such as `1.3.0`.
+pub fn version() -> &'static str {
+    unsafe { CStr::from_ptr(avifVersion()) }
+        .to_str()
+        .unwrap_or_default()
+}
+
+/// Returns the names and versions of the codecs compiled into libavif.
+///
+/// # Returns
+/// A human-readable list such as `dav1d [dec]:1.4.0, rav1e [enc]:0.7.1`.
+pub fn codec_versions() -> String {
+    // libavif documents the buffer as 256 bytes.
+    let mut buffer = [0 as c_char; 256];
+    unsafe {
+        avifCodecVersions(buffer.as_mut_ptr());
+        CStr::from_ptr(buffer.as_ptr())
+    }
+    .to_string_lossy()
+    .into_owned()
+}
+
+/// Returns the codecs compiled into libavif and what each can do.
+///
+/// # Returns
+/// The available codecs, in libavif's order of preference.
+pub fn available_codecs() -> Vec<CodecInfo> {
+    let name = |choice, flag: avifCodecFlag| {
+        let name = unsafe { avifCodecName(choice, flag as avifCodecFlags) };
+        (!name.is_null()).then(|| unsafe { CStr::from_ptr(name) }.to_str().unwrap_or_default())
+    };
+    CODEC_CHOICES
+        .into_iter()
+        .filter_map(|choice| {
+            let encoder = name(choice, avifCodecFlag_AVIF_CODEC_FLAG_CAN_ENCODE);
+            let decoder = name(choice, avifCodecFlag_AVIF_CODEC_FLAG_CAN_DECODE);
+            Some(CodecInfo {
+                name: encoder.or(decoder)?,
+                can_encode: encoder.is_some(),
+                can_decode: decoder.is_some(),
+                choice,
+            })
+        })
+        .collect()
+}
+
+/// Returns the version of libyuv compiled into libavif.
+///
+/// Without libyuv, which the `libyuv` feature enables, libavif falls back to
+/// its own much slower implementations of RGB conversion, scaling and
+/// chroma resampling.
+///
+/// # Returns
+/// The libyuv version number, or `None` if libyuv is not available.
+pub fn libyuv_version() -> Option<u32> {
+    let version = unsafe { avifLibYUVVersion() };
+    (version != 0).then_some(version as u32)
+}
+
+/// Returns the version of libsharpyuv compiled into libavif.
+///
+/// libsharpyuv, which the `sharpyuv` feature enables, implements
+/// `ChromaDownsampling::SharpYuv`.
+///
+/// # Returns
+/// The libsharpyuv version number, or `None` if libsharpyuv is not
+/// available.
+pub fn sharpyuv_version() -> Option<u32> {
+    let version = unsafe { avifLibSharpYUVVersion() };
+    (version != 0).then_some(version as u32)
+}
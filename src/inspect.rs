@@ -0,0 +1,1069 @@
+//! Inspection of the box structure of AVIF files.
+//!
+//! `inspect` walks the ISOBMFF boxes of a file without decoding any pixels
+//! and reports its brands, items, properties and tracks, in the manner of
+//! libavif's `avifinfo`. It is meant for debugging files that fail to decode
+//! or decode unexpectedly, so malformed boxes are recorded as warnings and
+//! parsing continues wherever possible.
+
+use crate::error::Operation;
+use crate::{AvifError, Axis, Result, Rotation};
+use std::fmt;
+
+/// A property associated with an item.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Property {
+    /// Image spatial extents (`ispe`)
+    Ispe {
+        /// Width in pixels
+        width: u32,
+        /// Height in pixels
+        height: u32,
+    },
+    /// Bits per channel (`pixi`)
+    Pixi {
+        /// Bit depth of each channel
+        depths: Vec<u8>,
+    },
+    /// Clean aperture (`clap`), as numerator and denominator pairs
+    Clap {
+        /// Width of the aperture
+        width: (u32, u32),
+        /// Height of the aperture
+        height: (u32, u32),
+        /// Horizontal offset of the aperture center from the image center
+        horizontal_offset: (i32, u32),
+        /// Vertical offset of the aperture center from the image center
+        vertical_offset: (i32, u32),
+    },
+    /// Rotation (`irot`)
+    Irot(Rotation),
+    /// Mirroring (`imir`)
+    Imir(Axis),
+    /// Pixel aspect ratio (`pasp`)
+    Pasp {
+        /// Relative width of a pixel
+        h_spacing: u32,
+        /// Relative height of a pixel
+        v_spacing: u32,
+    },
+    /// CICP color description (`colr` of type `nclx`)
+    Nclx {
+        /// Color primaries code point
+        color_primaries: u16,
+        /// Transfer characteristics code point
+        transfer_characteristics: u16,
+        /// Matrix coefficients code point
+        matrix_coefficients: u16,
+        /// Whether samples use the full range
+        full_range: bool,
+    },
+    /// ICC profile (`colr` of type `prof` or `rICC`)
+    Icc {
+        /// Size of the profile in bytes
+        size: usize,
+    },
+    /// AV1 codec configuration (`av1C`)
+    Av1c {
+        /// AV1 sequence profile
+        profile: u8,
+        /// AV1 level index of operating point 0
+        level: u8,
+        /// Bit depth
+        depth: u8,
+        /// Whether the sequence has no chroma planes
+        monochrome: bool,
+        /// Horizontal chroma subsampling
+        subsampling_x: bool,
+        /// Vertical chroma subsampling
+        subsampling_y: bool,
+    },
+    /// Auxiliary image type (`auxC`), such as the alpha URN
+    Auxc(String),
+    /// Any other property, by box type
+    Other(String),
+}
+
+/// An item reference (`iref`) from one item to others.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemReference {
+    /// Reference type, such as `auxl`, `cdsc` or `dimg`
+    pub kind: String,
+    /// IDs of the referenced items
+    pub to: Vec<u32>,
+}
+
+/// An item of the `meta` box.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ItemReport {
+    /// Item ID
+    pub id: u32,
+    /// Item type, such as `av01`, `grid` or `Exif`
+    pub item_type: String,
+    /// Item name, usually empty
+    pub name: String,
+    /// Content type of `mime` items, `application/rdf+xml` for XMP
+    pub content_type: String,
+    /// Total size of the item's extents in bytes
+    pub size: u64,
+    /// Number of extents the item's data is split into
+    pub extent_count: usize,
+    /// Associated properties, in association order
+    pub properties: Vec<Property>,
+    /// References from this item to others
+    pub references: Vec<ItemReference>,
+}
+
+/// A track of the `moov` box.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TrackReport {
+    /// Track ID
+    pub id: u32,
+    /// Handler type, `pict` for color and `auxv` for alpha tracks
+    pub handler: String,
+    /// Sample entry type, `av01` for AV1
+    pub codec: String,
+    /// Presentation width in pixels
+    pub width: u32,
+    /// Presentation height in pixels
+    pub height: u32,
+    /// Number of time units per second
+    pub timescale: u32,
+    /// Duration in time units
+    pub duration: u64,
+    /// Number of samples
+    pub sample_count: u32,
+}
+
+/// The structure of an AVIF file, as reported by `inspect`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FileReport {
+    /// Major brand of the `ftyp` box
+    pub major_brand: String,
+    /// Minor version of the `ftyp` box
+    pub minor_version: u32,
+    /// Compatible brands of the `ftyp` box
+    pub compatible_brands: Vec<String>,
+    /// Types of the top-level boxes, in file order
+    pub boxes: Vec<String>,
+    /// ID of the primary item (`pitm`), if any
+    pub primary_item: Option<u32>,
+    /// Items of the `meta` box, in ID order
+    pub items: Vec<ItemReport>,
+    /// Tracks of the `moov` box
+    pub tracks: Vec<TrackReport>,
+    /// Number of frames: the sample count of the first color track, or 1
+    /// for a still image
+    pub frame_count: u32,
+    /// Problems found while parsing
+    pub warnings: Vec<String>,
+}
+
+impl FileReport {
+    /// Returns the primary item, if any.
+    pub fn primary(&self) -> Option<&ItemReport> {
+        let id = self.primary_item?;
+        self.items.iter().find(|item| item.id == id)
+    }
+
+    /// Returns true if any item has an ICC profile.
+    pub fn has_icc(&self) -> bool {
+        self.items.iter().any(|item| {
+            item.properties
+                .iter()
+                .any(|p| matches!(p, Property::Icc { .. }))
+        })
+    }
+
+    /// Returns true if the file has an Exif item.
+    pub fn has_exif(&self) -> bool {
+        self.items.iter().any(|item| item.item_type == "Exif")
+    }
+
+    /// Returns true if the file has an XMP item.
+    pub fn has_xmp(&self) -> bool {
+        self.items
+            .iter()
+            .any(|item| item.item_type == "mime" && item.content_type == "application/rdf+xml")
+    }
+
+    /// Returns the item with the given ID, adding it if needed.
+    fn item_mut(&mut self, id: u32) -> &mut ItemReport {
+        let index = match self.items.binary_search_by_key(&id, |item| item.id) {
+            Ok(index) => index,
+            Err(index) => {
+                let item = ItemReport {
+                    id,
+                    ..Default::default()
+                };
+                self.items.insert(index, item);
+                index
+            }
+        };
+        &mut self.items[index]
+    }
+}
+
+/// Inspects the box structure of an AVIF file.
+///
+/// No pixels are decoded, and malformed boxes are recorded in
+/// `FileReport::warnings` rather than failing the inspection.
+///
+/// # Arguments
+/// * `data` - The AVIF file
+///
+/// # Returns
+/// The report, or an error if the data does not start with an `ftyp` box.
+pub fn inspect(data: &[u8]) -> Result<FileReport> {
+    let mut report = FileReport::default();
+    for_each_box(data, &mut report, |kind, payload, report| {
+        report.boxes.push(fourcc(kind));
+        match &kind {
+            b"ftyp" => parse_ftyp(payload, report),
+            b"meta" => parse_meta(payload, report),
+            b"moov" => {
+                for_each_box(payload, report, |kind, payload, report| {
+                    if &kind == b"trak" {
+                        parse_trak(payload, report);
+                    }
+                    Some(())
+                });
+                Some(())
+            }
+            _ => Some(()),
+        }
+    });
+    if report.boxes.first().map(String::as_str) != Some("ftyp") {
+        return Err(AvifError::InvalidFtyp.context(Operation::Parse));
+    }
+
+    report.frame_count = match report.tracks.iter().find(|track| track.handler == "pict") {
+        Some(track) => track.sample_count,
+        None => u32::from(report.primary_item.is_some()),
+    };
+    Ok(report)
+}
+
+/// Formats a box type for display, replacing unprintable bytes with `.`.
+fn fourcc(kind: [u8; 4]) -> String {
+    kind.iter()
+        .map(|&c| {
+            if c.is_ascii_graphic() || c == b' ' {
+                c as char
+            } else {
+                '.'
+            }
+        })
+        .collect()
+}
+
+/// Big-endian reader over a box payload.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn bytes(&mut self, count: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(count)?)?;
+        self.pos += count;
+        Some(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.bytes(N)?.try_into().ok()
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_be_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_be_bytes(self.array()?))
+    }
+
+    /// Reads an unsigned integer of 0, 4 or 8 bytes, as used by `iloc`.
+    fn uint(&mut self, size: u8) -> Option<u64> {
+        match size {
+            0 => Some(0),
+            4 => self.u32().map(u64::from),
+            8 => self.u64(),
+            _ => None,
+        }
+    }
+
+    /// Reads an item ID of 16 bits, or 32 bits if `wide`.
+    fn id(&mut self, wide: bool) -> Option<u32> {
+        if wide {
+            self.u32()
+        } else {
+            self.u16().map(u32::from)
+        }
+    }
+
+    /// Reads the version and flags of a full box.
+    fn full_box(&mut self) -> Option<(u8, u32)> {
+        let header = self.u32()?;
+        Some(((header >> 24) as u8, header & 0xFF_FFFF))
+    }
+
+    /// Reads a null-terminated string, or the rest of the payload if it has
+    /// no terminator.
+    fn string(&mut self) -> String {
+        let rest = &self.data[self.pos.min(self.data.len())..];
+        let len = rest.iter().position(|&c| c == 0).unwrap_or(rest.len());
+        self.pos += (len + 1).min(rest.len());
+        String::from_utf8_lossy(&rest[..len]).into_owned()
+    }
+}
+
+/// Calls `f` with the type and payload of each box in `data`.
+///
+/// A box extending past `data` ends the iteration, and it and any box for
+/// which `f` returns `None` are recorded as warnings.
+fn for_each_box<F>(data: &[u8], report: &mut FileReport, mut f: F)
+where
+    F: FnMut([u8; 4], &[u8], &mut FileReport) -> Option<()>,
+{
+    let mut reader = Reader::new(data);
+    while !reader.is_empty() {
+        let start = reader.pos;
+        let (Some(size), Some(kind)) = (reader.u32(), reader.array::<4>()) else {
+            report.warnings.push("truncated box header".to_string());
+            break;
+        };
+        let size = match size {
+            0 => (data.len() - start) as u64,
+            1 => match reader.u64() {
+                Some(size) => size,
+                None => {
+                    report.warnings.push("truncated box header".to_string());
+                    break;
+                }
+            },
+            size => size as u64,
+        };
+        let header = (reader.pos - start) as u64;
+        let Some(payload) = size
+            .checked_sub(header)
+            .and_then(|len| reader.bytes(usize::try_from(len).ok()?))
+        else {
+            report
+                .warnings
+                .push(format!("{} box extends past its parent", fourcc(kind)));
+            break;
+        };
+        if f(kind, payload, report).is_none() {
+            report
+                .warnings
+                .push(format!("malformed {} box", fourcc(kind)));
+        }
+    }
+}
+
+fn parse_ftyp(payload: &[u8], report: &mut FileReport) -> Option<()> {
+    let mut reader = Reader::new(payload);
+    report.major_brand = fourcc(reader.array()?);
+    report.minor_version = reader.u32()?;
+    while let Some(brand) = reader.array() {
+        report.compatible_brands.push(fourcc(brand));
+    }
+    Some(())
+}
+
+fn parse_meta(payload: &[u8], report: &mut FileReport) -> Option<()> {
+    let mut reader = Reader::new(payload);
+    reader.full_box()?;
+    let mut properties = Vec::new();
+    let mut associations = Vec::new();
+    for_each_box(&payload[reader.pos..], report, |kind, payload, report| {
+        let mut reader = Reader::new(payload);
+        match &kind {
+            b"pitm" => {
+                let (version, _) = reader.full_box()?;
+                report.primary_item = Some(reader.id(version > 0)?);
+            }
+            b"iinf" => {
+                let (version, _) = reader.full_box()?;
+                reader.id(version > 0)?;
+                for_each_box(&payload[reader.pos..], report, |kind, payload, report| {
+                    if &kind == b"infe" {
+                        parse_infe(payload, report)?;
+                    }
+                    Some(())
+                });
+            }
+            b"iloc" => parse_iloc(payload, report)?,
+            b"iref" => {
+                let (version, _) = reader.full_box()?;
+                for_each_box(&payload[reader.pos..], report, |kind, payload, report| {
+                    let mut reader = Reader::new(payload);
+                    let from = reader.id(version > 0)?;
+                    let count = reader.u16()?;
+                    let to = (0..count)
+                        .map(|_| reader.id(version > 0))
+                        .collect::<Option<_>>()?;
+                    report.item_mut(from).references.push(ItemReference {
+                        kind: fourcc(kind),
+                        to,
+                    });
+                    Some(())
+                });
+            }
+            b"iprp" => {
+                for_each_box(payload, report, |kind, payload, report| {
+                    match &kind {
+                        b"ipco" => for_each_box(payload, report, |kind, payload, _| {
+                            properties.push(parse_property(kind, payload));
+                            Some(())
+                        }),
+                        b"ipma" => parse_ipma(payload, &mut associations)?,
+                        _ => {}
+                    }
+                    Some(())
+                });
+            }
+            _ => {}
+        }
+        Some(())
+    });
+
+    for (id, index) in associations {
+        match index.checked_sub(1).and_then(|i| properties.get(i)) {
+            Some(property) => report.item_mut(id).properties.push(property.clone()),
+            None => report.warnings.push(format!(
+                "item {} is associated with missing property {}",
+                id, index
+            )),
+        }
+    }
+    Some(())
+}
+
+fn parse_infe(payload: &[u8], report: &mut FileReport) -> Option<()> {
+    let mut reader = Reader::new(payload);
+    let (version, _) = reader.full_box()?;
+    if version < 2 {
+        report
+            .warnings
+            .push(format!("unsupported infe box version {}", version));
+        return Some(());
+    }
+    let id = reader.id(version > 2)?;
+    reader.u16()?;
+    let item_type = fourcc(reader.array()?);
+    let name = reader.string();
+    let content_type = if item_type == "mime" {
+        reader.string()
+    } else {
+        String::new()
+    };
+    let item = report.item_mut(id);
+    item.item_type = item_type;
+    item.name = name;
+    item.content_type = content_type;
+    Some(())
+}
+
+fn parse_iloc(payload: &[u8], report: &mut FileReport) -> Option<()> {
+    let mut reader = Reader::new(payload);
+    let (version, _) = reader.full_box()?;
+    let sizes = reader.u8()?;
+    let (offset_size, length_size) = (sizes >> 4, sizes & 0xF);
+    let sizes = reader.u8()?;
+    let base_offset_size = sizes >> 4;
+    let index_size = if version > 0 { sizes & 0xF } else { 0 };
+    if [offset_size, length_size, base_offset_size, index_size]
+        .iter()
+        .any(|size| !matches!(size, 0 | 4 | 8))
+    {
+        return None;
+    }
+    let extent_bytes = usize::from(index_size + offset_size + length_size);
+    let count = reader.id(version > 1)?;
+    for _ in 0..count {
+        let id = reader.id(version > 1)?;
+        if version > 0 {
+            reader.u16()?;
+        }
+        reader.u16()?;
+        reader.uint(base_offset_size)?;
+        let extent_count = reader.u16()?;
+        // Take all extents at once, so a count the payload cannot hold fails
+        // without looping, and extents without fields are not looped over.
+        let mut extents = Reader::new(reader.bytes(extent_bytes * extent_count as usize)?);
+        let mut size = 0u64;
+        if length_size > 0 {
+            for _ in 0..extent_count {
+                extents.uint(index_size)?;
+                extents.uint(offset_size)?;
+                size = size.saturating_add(extents.uint(length_size)?);
+            }
+        }
+        let item = report.item_mut(id);
+        item.size = size;
+        item.extent_count = extent_count as usize;
+    }
+    Some(())
+}
+
+/// Collects the 1-based property indices associated with each item.
+fn parse_ipma(payload: &[u8], associations: &mut Vec<(u32, usize)>) -> Option<()> {
+    let mut reader = Reader::new(payload);
+    let (version, flags) = reader.full_box()?;
+    let count = reader.u32()?;
+    for _ in 0..count {
+        let id = reader.id(version > 0)?;
+        for _ in 0..reader.u8()? {
+            // The high bit marks the property as essential.
+            let index = if flags & 1 != 0 {
+                (reader.u16()? & 0x7FFF) as usize
+            } else {
+                (reader.u8()? & 0x7F) as usize
+            };
+            associations.push((id, index));
+        }
+    }
+    Some(())
+}
+
+/// Parses an item property, falling back to `Property::Other` for unknown or
+/// malformed boxes.
+fn parse_property(kind: [u8; 4], payload: &[u8]) -> Property {
+    known_property(kind, payload).unwrap_or_else(|| Property::Other(fourcc(kind)))
+}
+
+fn known_property(kind: [u8; 4], payload: &[u8]) -> Option<Property> {
+    let mut reader = Reader::new(payload);
+    let property = match &kind {
+        b"ispe" => {
+            reader.full_box()?;
+            Property::Ispe {
+                width: reader.u32()?,
+                height: reader.u32()?,
+            }
+        }
+        b"pixi" => {
+            reader.full_box()?;
+            let count = reader.u8()?;
+            Property::Pixi {
+                depths: reader.bytes(count as usize)?.to_vec(),
+            }
+        }
+        b"clap" => Property::Clap {
+            width: (reader.u32()?, reader.u32()?),
+            height: (reader.u32()?, reader.u32()?),
+            horizontal_offset: (reader.u32()? as i32, reader.u32()?),
+            vertical_offset: (reader.u32()? as i32, reader.u32()?),
+        },
+        b"irot" => Property::Irot(match reader.u8()? & 3 {
+            1 => Rotation::Ccw90,
+            2 => Rotation::Ccw180,
+            3 => Rotation::Ccw270,
+            _ => Rotation::None,
+        }),
+        b"imir" => Property::Imir(if reader.u8()? & 1 == 0 {
+            Axis::Horizontal
+        } else {
+            Axis::Vertical
+        }),
+        b"pasp" => Property::Pasp {
+            h_spacing: reader.u32()?,
+            v_spacing: reader.u32()?,
+        },
+        b"colr" => match &reader.array::<4>()? {
+            b"nclx" => Property::Nclx {
+                color_primaries: reader.u16()?,
+                transfer_characteristics: reader.u16()?,
+                matrix_coefficients: reader.u16()?,
+                full_range: reader.u8()? & 0x80 != 0,
+            },
+            b"prof" | b"rICC" => Property::Icc {
+                size: payload.len() - 4,
+            },
+            _ => return None,
+        },
+        b"av1C" => {
+            reader.u8()?;
+            let [profile, flags] = reader.array()?;
+            let depth = match (flags & 0x40 != 0, flags & 0x20 != 0) {
+                (true, true) => 12,
+                (true, false) => 10,
+                _ => 8,
+            };
+            Property::Av1c {
+                profile: profile >> 5,
+                level: profile & 0x1F,
+                depth,
+                monochrome: flags & 0x10 != 0,
+                subsampling_x: flags & 0x08 != 0,
+                subsampling_y: flags & 0x04 != 0,
+            }
+        }
+        b"auxC" => {
+            reader.full_box()?;
+            Property::Auxc(reader.string())
+        }
+        _ => return None,
+    };
+    Some(property)
+}
+
+fn parse_trak(payload: &[u8], report: &mut FileReport) {
+    let mut track = TrackReport::default();
+    for_each_box(payload, report, |kind, payload, report| {
+        let mut reader = Reader::new(payload);
+        match &kind {
+            b"tkhd" => {
+                let (version, _) = reader.full_box()?;
+                let time_size = if version == 1 { 8 } else { 4 };
+                reader.bytes(2 * time_size)?;
+                track.id = reader.u32()?;
+                // Skip the duration and the reserved, layer, alternate group,
+                // volume and matrix fields.
+                reader.bytes(4 + time_size + 8 + 8 + 36)?;
+                track.width = reader.u32()? >> 16;
+                track.height = reader.u32()? >> 16;
+            }
+            b"mdia" => for_each_box(payload, report, |kind, payload, report| {
+                let mut reader = Reader::new(payload);
+                match &kind {
+                    b"mdhd" => {
+                        let (version, _) = reader.full_box()?;
+                        if version == 1 {
+                            reader.bytes(16)?;
+                            track.timescale = reader.u32()?;
+                            track.duration = reader.u64()?;
+                        } else {
+                            reader.bytes(8)?;
+                            track.timescale = reader.u32()?;
+                            track.duration = reader.u32()?.into();
+                        }
+                    }
+                    b"hdlr" => {
+                        reader.full_box()?;
+                        reader.u32()?;
+                        track.handler = fourcc(reader.array()?);
+                    }
+                    b"minf" => parse_minf(payload, report, &mut track),
+                    _ => {}
+                }
+                Some(())
+            }),
+            _ => {}
+        }
+        Some(())
+    });
+    report.tracks.push(track);
+}
+
+fn parse_minf(payload: &[u8], report: &mut FileReport, track: &mut TrackReport) {
+    for_each_box(payload, report, |kind, payload, report| {
+        if &kind != b"stbl" {
+            return Some(());
+        }
+        for_each_box(payload, report, |kind, payload, _| {
+            let mut reader = Reader::new(payload);
+            match &kind {
+                b"stsd" => {
+                    reader.full_box()?;
+                    reader.u32()?;
+                    reader.u32()?;
+                    track.codec = fourcc(reader.array()?);
+                }
+                b"stsz" => {
+                    reader.full_box()?;
+                    reader.u32()?;
+                    track.sample_count = reader.u32()?;
+                }
+                _ => {}
+            }
+            Some(())
+        });
+        Some(())
+    });
+}
+
+impl fmt::Display for Property {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Property::Ispe { width, height } => write!(f, "ispe {}x{}", width, height),
+            Property::Pixi { depths } => write!(f, "pixi {:?}", depths),
+            Property::Clap {
+                width,
+                height,
+                horizontal_offset,
+                vertical_offset,
+            } => write!(
+                f,
+                "clap {}/{} x {}/{} offset {}/{}, {}/{}",
+                width.0,
+                width.1,
+                height.0,
+                height.1,
+                horizontal_offset.0,
+                horizontal_offset.1,
+                vertical_offset.0,
+                vertical_offset.1
+            ),
+            Property::Irot(rotation) => write!(f, "irot {:?}", rotation),
+            Property::Imir(axis) => write!(f, "imir {:?}", axis),
+            Property::Pasp {
+                h_spacing,
+                v_spacing,
+            } => write!(f, "pasp {}:{}", h_spacing, v_spacing),
+            Property::Nclx {
+                color_primaries,
+                transfer_characteristics,
+                matrix_coefficients,
+                full_range,
+            } => write!(
+                f,
+                "colr nclx {}/{}/{} {} range",
+                color_primaries,
+                transfer_characteristics,
+                matrix_coefficients,
+                if *full_range { "full" } else { "limited" }
+            ),
+            Property::Icc { size } => write!(f, "colr ICC ({} bytes)", size),
+            Property::Av1c {
+                profile,
+                level,
+                depth,
+                monochrome,
+                subsampling_x,
+                subsampling_y,
+            } => write!(
+                f,
+                "av1C profile {} level {} {}-bit {}",
+                profile,
+                level,
+                depth,
+                match (monochrome, subsampling_x, subsampling_y) {
+                    (true, _, _) => "4:0:0",
+                    (false, true, true) => "4:2:0",
+                    (false, true, false) => "4:2:2",
+                    _ => "4:4:4",
+                }
+            ),
+            Property::Auxc(aux_type) => write!(f, "auxC {}", aux_type),
+            Property::Other(kind) => write!(f, "{}", kind),
+        }
+    }
+}
+
+impl fmt::Display for FileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Brands: {} (minor version {}), compatible: {}",
+            self.major_brand,
+            self.minor_version,
+            self.compatible_brands.join(", ")
+        )?;
+        writeln!(f, "Boxes: {}", self.boxes.join(", "))?;
+        if let Some(id) = self.primary_item {
+            writeln!(f, "Primary item: {}", id)?;
+        }
+        writeln!(f, "Items: {}", self.items.len())?;
+        for item in &self.items {
+            write!(
+                f,
+                "  #{} {} {} bytes in {} extent(s)",
+                item.id, item.item_type, item.size, item.extent_count
+            )?;
+            if !item.name.is_empty() {
+                write!(f, " \"{}\"", item.name)?;
+            }
+            writeln!(f)?;
+            for property in &item.properties {
+                writeln!(f, "    {}", property)?;
+            }
+            for reference in &item.references {
+                writeln!(f, "    {} -> {:?}", reference.kind, reference.to)?;
+            }
+        }
+        writeln!(f, "Tracks: {}", self.tracks.len())?;
+        for track in &self.tracks {
+            writeln!(
+                f,
+                "  #{} {} {} {}x{}, {} samples, duration {}/{}",
+                track.id,
+                track.handler,
+                track.codec,
+                track.width,
+                track.height,
+                track.sample_count,
+                track.duration,
+                track.timescale
+            )?;
+        }
+        writeln!(f, "Frames: {}", self.frame_count)?;
+        writeln!(
+            f,
+            "ICC: {}, Exif: {}, XMP: {}",
+            self.has_icc(),
+            self.has_exif(),
+            self.has_xmp()
+        )?;
+        for warning in &self.warnings {
+            writeln!(f, "Warning: {}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = (8 + payload.len() as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(kind);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    fn full_box(kind: &[u8; 4], version: u8, payload: &[u8]) -> Vec<u8> {
+        make_box(kind, &[&[version, 0, 0, 0], payload].concat())
+    }
+
+    fn ftyp() -> Vec<u8> {
+        make_box(b"ftyp", b"avif\0\0\0\0avifmif1miaf")
+    }
+
+    /// A still image with one `av01` item of 100 bytes, sized by `ispe` and
+    /// cropped by `clap`.
+    fn still_image() -> Vec<u8> {
+        let pitm = full_box(b"pitm", 0, &[0, 1]);
+        let infe = full_box(b"infe", 2, b"\0\x01\0\0av01Color\0");
+        let iinf = full_box(b"iinf", 0, &[&[0, 1][..], &infe].concat());
+        let iloc = full_box(
+            b"iloc",
+            0,
+            &[0x44, 0x00, 0, 1, 0, 1, 0, 0, 0, 1, 0, 0, 1, 0, 0, 0, 0, 100],
+        );
+        let ispe = full_box(b"ispe", 0, &[0, 0, 0, 64, 0, 0, 0, 48]);
+        let clap = make_box(
+            b"clap",
+            &[60u32, 1, 40, 1, (-2i32) as u32, 1, 0, 1]
+                .iter()
+                .flat_map(|v| v.to_be_bytes())
+                .collect::<Vec<_>>(),
+        );
+        let ipco = make_box(b"ipco", &[ispe, clap].concat());
+        let ipma = full_box(b"ipma", 0, &[0, 0, 0, 1, 0, 1, 2, 0x81, 0x02]);
+        let iprp = make_box(b"iprp", &[ipco, ipma].concat());
+        let meta = full_box(b"meta", 0, &[pitm, iinf, iloc, iprp].concat());
+        [ftyp(), meta, make_box(b"mdat", &[0; 100])].concat()
+    }
+
+    #[test]
+    fn inspects_still_image() {
+        let report = inspect(&still_image()).unwrap();
+        assert_eq!(report.major_brand, "avif");
+        assert_eq!(report.compatible_brands, ["avif", "mif1", "miaf"]);
+        assert_eq!(report.boxes, ["ftyp", "meta", "mdat"]);
+        assert_eq!(report.frame_count, 1);
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+
+        let item = report.primary().unwrap();
+        assert_eq!(item.item_type, "av01");
+        assert_eq!(item.name, "Color");
+        assert_eq!((item.size, item.extent_count), (100, 1));
+        assert_eq!(
+            item.properties,
+            [
+                Property::Ispe {
+                    width: 64,
+                    height: 48
+                },
+                Property::Clap {
+                    width: (60, 1),
+                    height: (40, 1),
+                    horizontal_offset: (-2, 1),
+                    vertical_offset: (0, 1),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_missing_ftyp() {
+        assert!(inspect(&[]).is_err());
+        assert!(inspect(&make_box(b"meta", &[0; 4])).is_err());
+    }
+
+    #[test]
+    fn truncated_files_do_not_panic() {
+        let data = still_image();
+        // Cutting the file between top-level boxes leaves it well-formed.
+        let boundaries = [ftyp().len(), data.len() - 108];
+        for len in 0..data.len() {
+            match inspect(&data[..len]) {
+                Ok(report) if !boundaries.contains(&len) => {
+                    assert!(!report.warnings.is_empty(), "no warning at {} bytes", len);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn reports_boxes_past_their_parent() {
+        let mut data = ftyp();
+        data.extend_from_slice(&make_box(b"mdat", &[0; 16])[..12]);
+        let report = inspect(&data).unwrap();
+        assert_eq!(report.boxes, ["ftyp"]);
+        assert_eq!(report.warnings, ["mdat box extends past its parent"]);
+
+        // A size smaller than the box header.
+        let mut data = ftyp();
+        data.extend_from_slice(&[0, 0, 0, 4]);
+        data.extend_from_slice(b"free");
+        let report = inspect(&data).unwrap();
+        assert_eq!(report.warnings, ["free box extends past its parent"]);
+
+        // A 64-bit size missing its last bytes.
+        let mut data = ftyp();
+        data.extend_from_slice(&[0, 0, 0, 1]);
+        data.extend_from_slice(b"mdat");
+        data.extend_from_slice(&[0, 0, 0]);
+        let report = inspect(&data).unwrap();
+        assert_eq!(report.warnings, ["truncated box header"]);
+    }
+
+    #[test]
+    fn handles_box_sizes_of_zero_and_one() {
+        let mut data = ftyp();
+        data.extend_from_slice(&[0, 0, 0, 1]);
+        data.extend_from_slice(b"free");
+        data.extend_from_slice(&20u64.to_be_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(b"mdat");
+        data.extend_from_slice(&[0; 32]);
+        let report = inspect(&data).unwrap();
+        assert_eq!(report.boxes, ["ftyp", "free", "mdat"]);
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    }
+
+    #[test]
+    fn reports_malformed_boxes() {
+        let ftyp_only = make_box(b"ftyp", b"av");
+        let report = inspect(&ftyp_only).unwrap();
+        assert_eq!(report.warnings, ["malformed ftyp box"]);
+
+        // The association refers to a property that does not exist, and the
+        // iloc box is cut off inside its item list.
+        let ipma = full_box(b"ipma", 0, &[0, 0, 0, 1, 0, 1, 1, 0x83]);
+        let iprp = make_box(b"iprp", &make_box(b"ipco", &[]));
+        let iprp = [iprp, ipma].concat();
+        let iprp = make_box(b"iprp", &iprp[8..]);
+        let iloc = full_box(b"iloc", 0, &[0x44, 0x00, 0, 1, 0]);
+        let meta = full_box(b"meta", 0, &[iloc, iprp].concat());
+        let report = inspect(&[ftyp(), meta].concat()).unwrap();
+        assert_eq!(
+            report.warnings,
+            [
+                "malformed iloc box",
+                "item 1 is associated with missing property 3"
+            ]
+        );
+    }
+
+    #[test]
+    fn bounds_iloc_extent_counts() {
+        // 65535 extents claimed with no data for them.
+        let iloc = full_box(b"iloc", 0, &[0x44, 0x00, 0, 1, 0, 1, 0, 0, 0xFF, 0xFF]);
+        let meta = full_box(b"meta", 0, &iloc);
+        let report = inspect(&[ftyp(), meta].concat()).unwrap();
+        assert_eq!(report.warnings, ["malformed iloc box"]);
+
+        // Extents without offset or length fields take no input.
+        let iloc = full_box(b"iloc", 0, &[0x00, 0x00, 0, 1, 0, 1, 0, 0, 0xFF, 0xFF]);
+        let meta = full_box(b"meta", 0, &iloc);
+        let report = inspect(&[ftyp(), meta].concat()).unwrap();
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        assert_eq!(
+            (report.items[0].size, report.items[0].extent_count),
+            (0, 65535)
+        );
+
+        let iloc = full_box(b"iloc", 0, &[0x33, 0x00, 0, 0]);
+        let meta = full_box(b"meta", 0, &iloc);
+        let report = inspect(&[ftyp(), meta].concat()).unwrap();
+        assert_eq!(report.warnings, ["malformed iloc box"]);
+    }
+
+    #[test]
+    fn falls_back_for_malformed_properties() {
+        assert_eq!(
+            parse_property(*b"ispe", &[0, 0, 0, 0, 0, 0]),
+            Property::Other("ispe".to_string())
+        );
+        assert_eq!(
+            parse_property(*b"colr", b"abcd"),
+            Property::Other("colr".to_string())
+        );
+        assert_eq!(
+            parse_property(*b"colr", b"prof\x01\x02\x03"),
+            Property::Icc { size: 3 }
+        );
+        assert_eq!(
+            parse_property(*b"irot", &[3]),
+            Property::Irot(Rotation::Ccw270)
+        );
+        assert_eq!(
+            parse_property(*b"av1C", &[0x81, 0x2D, 0x4C]),
+            Property::Av1c {
+                profile: 1,
+                level: 13,
+                depth: 10,
+                monochrome: false,
+                subsampling_x: true,
+                subsampling_y: true,
+            }
+        );
+    }
+
+    #[test]
+    fn reader_stops_at_the_end() {
+        let mut reader = Reader::new(&[1, 2, 3]);
+        assert_eq!(reader.u16(), Some(0x0102));
+        assert_eq!(reader.u16(), None);
+        assert_eq!(reader.u8(), Some(3));
+        assert!(reader.is_empty());
+        assert_eq!(reader.bytes(usize::MAX), None);
+        assert_eq!(reader.uint(2), None);
+
+        let mut reader = Reader::new(b"name\0rest");
+        assert_eq!(reader.string(), "name");
+        assert_eq!(reader.string(), "rest");
+        assert!(reader.is_empty());
+        assert_eq!(reader.string(), "");
+    }
+
+    #[test]
+    fn formats_unprintable_box_types() {
+        assert_eq!(fourcc(*b"av1C"), "av1C");
+        assert_eq!(fourcc([0xA9, b'x', 0, b'z']), ".x.z");
+    }
+}
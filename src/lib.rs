@@ -7,15 +7,65 @@
 #![allow(non_upper_case_globals)]
 
 use libavif_sys::*;
-use std::ptr::null_mut;
+use std::{
+    marker::PhantomData,
+    ops::{self, Deref},
+    ptr::null_mut,
+};
 
+#[cfg(feature = "ndarray")]
+mod array;
+#[cfg(all(feature = "rayon", not(target_family = "wasm")))]
+pub mod batch;
+pub mod codec;
+#[cfg(feature = "lcms2")]
+mod color;
+pub mod composite;
+#[cfg(any(feature = "png", feature = "jpeg"))]
+pub mod convert;
+pub mod decoder;
+pub mod dither;
 pub mod encoder;
 pub mod error;
+mod exif;
+#[cfg(feature = "image")]
+pub mod image_codec;
+pub mod info;
+pub mod inspect;
+pub mod metrics;
+mod par;
+pub mod pool;
 pub mod rgb;
+pub mod scanline;
+mod threads;
+pub mod transform;
+#[cfg(all(feature = "simd-yuv", not(feature = "libyuv")))]
+mod yuv;
 
-pub use encoder::Encoder;
+pub use decoder::{Decoder, DecoderCodecChoice, StrictFlags};
+pub use dither::Dither;
+pub use encoder::{Encoder, EncoderBuilder, Preset, QualityTarget};
 pub use error::AvifError;
-pub use rgb::{ChromaDownsampling, ChromaUpsampling, RgbFormat, RgbImage};
+pub use info::{
+    CodecInfo, available_codecs, codec_versions, libyuv_version, sharpyuv_version, version,
+};
+pub use inspect::{FileReport, inspect};
+pub use pool::{ImagePool, PooledImage};
+pub use rgb::{ChromaDownsampling, ChromaUpsampling, OwnedRgbImage, RgbFormat, RgbImage};
+pub use scanline::ScanlineGrid;
+pub use threads::{set_thread_budget, thread_budget};
+
+/// The raw libavif bindings, for exchanging objects with other libavif users
+/// through the `from_raw` and `into_raw` methods.
+pub use libavif_sys as sys;
+
+/// Upper bound for thread counts passed to libavif. WebAssembly builds are
+/// single-threaded.
+pub(crate) const MAX_THREADS: u32 = if cfg!(target_family = "wasm") {
+    1
+} else {
+    1024
+};
 
 /// A convenience type alias for Results with AvifError.
 pub type Result<T> = std::result::Result<T, AvifError>;
@@ -26,6 +76,8 @@ pub type Result<T> = std::result::Result<T, AvifError>;
 /// - 8-bit: Standard precision, widely supported
 /// - 10-bit: Higher precision, better for HDR content
 /// - 12-bit: Maximum precision, professional use
+/// - 16-bit: Beyond what AV1 codes directly; YUV images of this depth need
+///   the `sample-transform` feature
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BitDepth {
     /// 8-bit depth (standard precision)
@@ -34,6 +86,8 @@ pub enum BitDepth {
     Ten = 10,
     /// 12-bit depth (maximum precision)
     Twelve = 12,
+    /// 16-bit depth, encoded with a sample transform
+    Sixteen = 16,
 }
 
 impl From<BitDepth> for u32 {
@@ -50,6 +104,7 @@ impl TryFrom<u32> for BitDepth {
             8 => Ok(BitDepth::Eight),
             10 => Ok(BitDepth::Ten),
             12 => Ok(BitDepth::Twelve),
+            16 => Ok(BitDepth::Sixteen),
             _ => Err(AvifError::InvalidArgument),
         }
     }
@@ -62,23 +117,46 @@ impl TryFrom<u32> for BitDepth {
 /// - YUV422: Half chroma width resolution
 /// - YUV420: Half chroma width and height resolution (most common)
 /// - YUV400: Grayscale (no chroma information)
+#[non_exhaustive]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PixelFormat {
     /// No format specified
-    None = avifPixelFormat_AVIF_PIXEL_FORMAT_NONE as isize,
+    None,
     /// Full chroma resolution (4:4:4)
-    Yuv444 = avifPixelFormat_AVIF_PIXEL_FORMAT_YUV444 as isize,
+    Yuv444,
     /// Half chroma width (4:2:2)
-    Yuv422 = avifPixelFormat_AVIF_PIXEL_FORMAT_YUV422 as isize,
+    Yuv422,
     /// Half chroma width and height (4:2:0) - most common
-    Yuv420 = avifPixelFormat_AVIF_PIXEL_FORMAT_YUV420 as isize,
+    Yuv420,
     /// Grayscale (4:0:0)
-    Yuv400 = avifPixelFormat_AVIF_PIXEL_FORMAT_YUV400 as isize,
+    Yuv400,
+    /// A format not known to this version of the crate
+    Unknown(avifPixelFormat),
+}
+
+impl PixelFormat {
+    /// Returns the horizontal and vertical chroma subsampling shifts,
+    /// or `None` if the format has no chroma planes.
+    pub(crate) fn chroma_shift(self) -> Option<(u32, u32)> {
+        match self {
+            PixelFormat::Yuv444 => Some((0, 0)),
+            PixelFormat::Yuv422 => Some((1, 0)),
+            PixelFormat::Yuv420 => Some((1, 1)),
+            PixelFormat::Yuv400 | PixelFormat::None | PixelFormat::Unknown(_) => None,
+        }
+    }
 }
 
 impl From<PixelFormat> for avifPixelFormat {
     fn from(format: PixelFormat) -> Self {
-        format as _
+        match format {
+            PixelFormat::None => avifPixelFormat_AVIF_PIXEL_FORMAT_NONE,
+            PixelFormat::Yuv444 => avifPixelFormat_AVIF_PIXEL_FORMAT_YUV444,
+            PixelFormat::Yuv422 => avifPixelFormat_AVIF_PIXEL_FORMAT_YUV422,
+            PixelFormat::Yuv420 => avifPixelFormat_AVIF_PIXEL_FORMAT_YUV420,
+            PixelFormat::Yuv400 => avifPixelFormat_AVIF_PIXEL_FORMAT_YUV400,
+            PixelFormat::Unknown(format) => format,
+        }
     }
 }
 
@@ -89,17 +167,196 @@ impl From<avifPixelFormat> for PixelFormat {
             avifPixelFormat_AVIF_PIXEL_FORMAT_YUV422 => PixelFormat::Yuv422,
             avifPixelFormat_AVIF_PIXEL_FORMAT_YUV420 => PixelFormat::Yuv420,
             avifPixelFormat_AVIF_PIXEL_FORMAT_YUV400 => PixelFormat::Yuv400,
-            _ => PixelFormat::None,
+            avifPixelFormat_AVIF_PIXEL_FORMAT_NONE => PixelFormat::None,
+            other => PixelFormat::Unknown(other),
+        }
+    }
+}
+
+/// YUV sample value range.
+///
+/// Limited ("video") range reserves footroom and headroom around the nominal
+/// black and white levels, while full range uses every code value.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YuvRange {
+    /// Limited range (16-235 for 8-bit luma)
+    Limited,
+    /// Full range (0-255 for 8-bit)
+    Full,
+    /// A range not known to this version of the crate
+    Unknown(avifRange),
+}
+
+impl From<YuvRange> for avifRange {
+    fn from(range: YuvRange) -> Self {
+        match range {
+            YuvRange::Limited => avifRange_AVIF_RANGE_LIMITED,
+            YuvRange::Full => avifRange_AVIF_RANGE_FULL,
+            YuvRange::Unknown(range) => range,
         }
     }
 }
 
+impl From<avifRange> for YuvRange {
+    fn from(range: avifRange) -> Self {
+        match range {
+            avifRange_AVIF_RANGE_LIMITED => YuvRange::Limited,
+            avifRange_AVIF_RANGE_FULL => YuvRange::Full,
+            other => YuvRange::Unknown(other),
+        }
+    }
+}
+
+/// Rotation of an image, in counter-clockwise steps of 90 degrees.
+///
+/// This matches the semantics of the `irot` transform property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    /// No rotation
+    None = 0,
+    /// 90 degrees counter-clockwise
+    Ccw90 = 1,
+    /// 180 degrees
+    Ccw180 = 2,
+    /// 270 degrees counter-clockwise (90 degrees clockwise)
+    Ccw270 = 3,
+}
+
+/// Mirroring axis of an image.
+///
+/// This matches the semantics of the `imir` transform property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    /// Mirror about the horizontal axis, exchanging top and bottom
+    Horizontal = 0,
+    /// Mirror about the vertical axis, exchanging left and right
+    Vertical = 1,
+}
+
+/// A rectangular region of an image, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rect {
+    /// Horizontal offset of the left edge
+    pub x: u32,
+    /// Vertical offset of the top edge
+    pub y: u32,
+    /// Region width
+    pub width: u32,
+    /// Region height
+    pub height: u32,
+}
+
+impl Rect {
+    /// Creates a new rectangle.
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns true if the rectangle lies within an image of the given size.
+    pub fn fits_within(&self, width: u32, height: u32) -> bool {
+        self.x
+            .checked_add(self.width)
+            .is_some_and(|right| right <= width)
+            && self
+                .y
+                .checked_add(self.height)
+                .is_some_and(|bottom| bottom <= height)
+    }
+}
+
+impl From<Rect> for avifCropRect {
+    fn from(rect: Rect) -> Self {
+        avifCropRect {
+            x: rect.x,
+            y: rect.y,
+            width: rect.width,
+            height: rect.height,
+        }
+    }
+}
+
+/// Flags selecting which image planes an operation applies to.
+///
+/// These flags can be combined using the bitwise OR operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Planes(u32);
+
+impl Planes {
+    /// The Y, U and V planes
+    pub const YUV: Self = Self(avifPlanesFlag_AVIF_PLANES_YUV as u32);
+    /// The alpha plane
+    pub const A: Self = Self(avifPlanesFlag_AVIF_PLANES_A as u32);
+    /// All planes
+    pub const ALL: Self = Self(avifPlanesFlag_AVIF_PLANES_ALL as u32);
+}
+
+impl Planes {
+    /// Returns the raw flag bits.
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if all flags in `other` are set.
+    pub const fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl Default for Planes {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl ops::BitOr for Planes {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self::Output {
+        Self(self.0 | other.0)
+    }
+}
+
+/// Image channels, each stored in its own plane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// Luma
+    Y = avifChannelIndex_AVIF_CHAN_Y as isize,
+    /// Blue-difference chroma
+    U = avifChannelIndex_AVIF_CHAN_U as isize,
+    /// Red-difference chroma
+    V = avifChannelIndex_AVIF_CHAN_V as isize,
+    /// Alpha
+    A = avifChannelIndex_AVIF_CHAN_A as isize,
+}
+
+impl Channel {
+    /// All channels, in plane order.
+    pub const ALL: [Channel; 4] = [Channel::Y, Channel::U, Channel::V, Channel::A];
+}
+
+impl From<Channel> for avifChannelIndex {
+    fn from(channel: Channel) -> Self {
+        channel as _
+    }
+}
+
 /// A wrapper around libavif's RWData structure for managing read/write data buffers.
 ///
 /// This structure automatically manages the memory lifecycle of data buffers
-/// used for encoding and decoding AVIF images.
+/// used for encoding and decoding AVIF images. A buffer passed to
+/// `Encoder::write_into` keeps its allocation between encodes, so reusing one
+/// avoids reallocating output memory for every image.
 pub struct RwData {
     pub(crate) inner: avifRWData,
+    /// Size of the allocation behind `inner.data`, which may exceed
+    /// `inner.size` after a smaller output was written into it.
+    capacity: usize,
 }
 
 impl RwData {
@@ -110,22 +367,91 @@ impl RwData {
                 data: null_mut(),
                 size: 0,
             },
+            capacity: 0,
         }
     }
 
+    /// Creates an empty buffer with room for `capacity` bytes.
+    ///
+    /// # Arguments
+    /// * `capacity` - Number of bytes to allocate up front
+    ///
+    /// # Returns
+    /// The buffer, or an error if the allocation fails.
+    pub fn with_capacity(capacity: usize) -> Result<Self> {
+        let mut data = Self::new();
+        if capacity > 0 {
+            let result = unsafe { avifRWDataRealloc(&mut data.inner, capacity) };
+            if result != avifResult_AVIF_RESULT_OK {
+                return Err(AvifError::from(result));
+            }
+            data.capacity = capacity;
+            data.inner.size = 0;
+        }
+        Ok(data)
+    }
+
     /// Returns the data as a byte slice.
     ///
     /// This provides safe access to the underlying buffer data without
     /// transferring ownership.
     pub fn as_slice(&self) -> &[u8] {
-        if self.inner.data.is_null() || self.inner.size == 0 {
-            &[]
-        } else {
-            unsafe { std::slice::from_raw_parts(self.inner.data, self.inner.size) }
+        rw_data_slice(&self.inner)
+    }
+
+    /// Returns the number of bytes the buffer can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.capacity.max(self.inner.size)
+    }
+
+    /// Empties the buffer while keeping its allocation.
+    pub fn clear(&mut self) {
+        self.capacity = self.capacity();
+        self.inner.size = 0;
+    }
+
+    /// Exposes the whole allocation to libavif before it writes into the
+    /// buffer.
+    ///
+    /// libavif only grows a buffer when its size is too small, so presenting
+    /// the capacity as the size lets it write into the existing allocation.
+    ///
+    /// # Returns
+    /// The current allocation, to be passed to `end_write`.
+    pub(crate) fn begin_write(&mut self) -> *mut u8 {
+        self.inner.size = self.capacity();
+        self.inner.data
+    }
+
+    /// Records the outcome of a write started with `begin_write`.
+    ///
+    /// On failure the contents are discarded but the allocation is kept.
+    pub(crate) fn end_write(&mut self, allocation: *mut u8, succeeded: bool) {
+        // After a reallocation, only the written size is known to be
+        // allocated.
+        if self.inner.data != allocation {
+            self.capacity = 0;
+        }
+        self.capacity = self.capacity();
+        if !succeeded || self.inner.data.is_null() {
+            self.inner.size = 0;
         }
     }
 }
 
+/// Borrows the contents of a raw avifRWData buffer as a byte slice.
+pub(crate) fn rw_data_slice(data: &avifRWData) -> &[u8] {
+    if data.data.is_null() || data.size == 0 {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(data.data, data.size) }
+    }
+}
+
+// SAFETY: RwData exclusively owns its libavif-allocated buffer.
+unsafe impl Send for RwData {}
+unsafe impl Sync for RwData {}
+
 impl Drop for RwData {
     fn drop(&mut self) {
         unsafe {
@@ -134,6 +460,22 @@ impl Drop for RwData {
     }
 }
 
+impl Clone for RwData {
+    fn clone(&self) -> Self {
+        let mut copy = Self::new();
+        let data = self.as_slice();
+        if !data.is_empty() {
+            let result = unsafe { avifRWDataSet(&mut copy.inner, data.as_ptr(), data.len()) };
+            assert!(
+                result == avifResult_AVIF_RESULT_OK,
+                "Failed to copy data: {}",
+                AvifError::from(result)
+            );
+        }
+        copy
+    }
+}
+
 impl Default for RwData {
     fn default() -> Self {
         Self::new()
@@ -158,8 +500,13 @@ impl Image {
     /// * `yuv_format` - YUV pixel format
     ///
     /// # Returns
-    /// A new Image instance or an error if creation fails.
+    /// A new Image instance or an error if creation fails. 16-bit images
+    /// are rejected with `UnsupportedDepth` without the `sample-transform`
+    /// feature.
     pub fn new(width: u32, height: u32, depth: BitDepth, yuv_format: PixelFormat) -> Result<Self> {
+        if depth == BitDepth::Sixteen && !cfg!(feature = "sample-transform") {
+            return Err(AvifError::UnsupportedDepth);
+        }
         let inner = unsafe { avifImageCreate(width, height, depth.into(), yuv_format.into()) };
         if inner.is_null() {
             Err(AvifError::OutOfMemory)
@@ -168,6 +515,179 @@ impl Image {
         }
     }
 
+    /// Estimates the number of bytes `allocate_planes` would allocate for an
+    /// image with the given geometry.
+    ///
+    /// This allows callers to reject oversized, user-controlled dimensions
+    /// before any memory is committed.
+    ///
+    /// # Arguments
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    /// * `depth` - Bit depth (8, 10, or 12 bits)
+    /// * `yuv_format` - YUV pixel format
+    /// * `alpha` - Whether an alpha plane would be allocated
+    ///
+    /// # Returns
+    /// The estimated size in bytes, or `None` if it does not fit in `usize`.
+    pub fn estimate_bytes(
+        width: u32,
+        height: u32,
+        depth: BitDepth,
+        yuv_format: PixelFormat,
+        alpha: bool,
+    ) -> Option<usize> {
+        let sample_size: usize = if depth == BitDepth::Eight { 1 } else { 2 };
+        let plane_size = |w: u32, h: u32| -> Option<usize> {
+            (w as usize)
+                .checked_mul(h as usize)?
+                .checked_mul(sample_size)
+        };
+
+        let luma = plane_size(width, height)?;
+        let mut total = luma;
+        if let Some((shift_x, shift_y)) = yuv_format.chroma_shift() {
+            let chroma_width = width.div_ceil(1 << shift_x);
+            let chroma_height = height.div_ceil(1 << shift_y);
+            let chroma = plane_size(chroma_width, chroma_height)?;
+            total = total.checked_add(chroma.checked_mul(2)?)?;
+        }
+        if alpha {
+            total = total.checked_add(luma)?;
+        }
+        Some(total)
+    }
+
+    /// Returns the number of bytes currently allocated for the image planes.
+    ///
+    /// Only planes that are present are counted, and row padding is included.
+    /// The count saturates at `usize::MAX` rather than overflowing.
+    pub fn allocated_bytes(&self) -> usize {
+        let image = unsafe { &*self.inner };
+        let format = PixelFormat::from(image.yuvFormat);
+        let height = image.height as usize;
+        let chroma_height = match format.chroma_shift() {
+            Some((_, 1)) => height.div_ceil(2),
+            _ => height,
+        };
+
+        let plane_bytes = |row_bytes: u32, rows: usize| (row_bytes as usize).saturating_mul(rows);
+        let mut total = 0usize;
+        if !image.yuvPlanes[0].is_null() {
+            total = total.saturating_add(plane_bytes(image.yuvRowBytes[0], height));
+        }
+        for i in 1..3 {
+            if !image.yuvPlanes[i].is_null() {
+                total = total.saturating_add(plane_bytes(image.yuvRowBytes[i], chroma_height));
+            }
+        }
+        if !image.alphaPlane.is_null() {
+            total = total.saturating_add(plane_bytes(image.alphaRowBytes, height));
+        }
+        total
+    }
+
+    /// Wraps caller-owned planes in an image view without copying them.
+    ///
+    /// This is useful for encoding frames produced by other libraries (for
+    /// example video decoders) whose planes are already laid out as YUV.
+    ///
+    /// Each plane must hold `row_bytes` bytes for every row, including the
+    /// last, since [`Image::plane`] and the conversions built on it read whole
+    /// rows. For depths above 8 bits, samples are native-endian `u16`, so the
+    /// data must be aligned for `u16` and `row_bytes` must be even.
+    ///
+    /// # Arguments
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    /// * `depth` - Bit depth (8, 10, or 12 bits)
+    /// * `yuv_format` - YUV pixel format
+    /// * `yuv` - The Y plane for `Yuv400`, or the Y, U and V planes otherwise
+    /// * `alpha` - Optional alpha plane with the same dimensions as the Y plane
+    ///
+    /// # Returns
+    /// A view borrowing the planes, or an error if a plane is too small or
+    /// misaligned for the given geometry and depth.
+    pub fn from_raw_planes<'a>(
+        width: u32,
+        height: u32,
+        depth: BitDepth,
+        yuv_format: PixelFormat,
+        yuv: &[RawPlane<'a>],
+        alpha: Option<RawPlane<'a>>,
+    ) -> Result<ImageView<'a>> {
+        let expected_planes = match yuv_format {
+            PixelFormat::None => return Err(AvifError::NoYuvFormatSelected),
+            PixelFormat::Yuv400 => 1,
+            _ => 3,
+        };
+        if yuv.len() != expected_planes {
+            return Err(AvifError::InvalidArgument);
+        }
+
+        let image = Self::new(width, height, depth, yuv_format)?;
+        let sample_size = if depth == BitDepth::Eight { 1 } else { 2 };
+        let (shift_x, shift_y) = yuv_format.chroma_shift().unwrap_or((0, 0));
+
+        for (i, plane) in yuv.iter().enumerate() {
+            let (plane_width, plane_height) = if i == 0 {
+                (width, height)
+            } else {
+                (width.div_ceil(1 << shift_x), height.div_ceil(1 << shift_y))
+            };
+            plane.validate(plane_width, plane_height, sample_size)?;
+            unsafe {
+                (*image.inner).yuvPlanes[i] = plane.data.as_ptr() as *mut u8;
+                (*image.inner).yuvRowBytes[i] = plane.row_bytes;
+            }
+        }
+        if let Some(plane) = alpha {
+            plane.validate(width, height, sample_size)?;
+            unsafe {
+                (*image.inner).alphaPlane = plane.data.as_ptr() as *mut u8;
+                (*image.inner).alphaRowBytes = plane.row_bytes;
+            }
+        }
+        unsafe {
+            (*image.inner).imageOwnsYUVPlanes = 0;
+            (*image.inner).imageOwnsAlphaPlane = 0;
+        }
+
+        Ok(ImageView {
+            image,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Creates a view of a region of this image without copying its planes.
+    ///
+    /// For subsampled formats, the region's offset must be aligned to the chroma
+    /// subsampling (even `x` for 4:2:2 and 4:2:0, even `y` for 4:2:0).
+    ///
+    /// # Arguments
+    /// * `rect` - The region to view
+    ///
+    /// # Returns
+    /// A view borrowing this image's planes, or an error if the region is out
+    /// of bounds or misaligned.
+    pub fn view(&self, rect: Rect) -> Result<ImageView<'_>> {
+        let inner = unsafe { avifImageCreateEmpty() };
+        if inner.is_null() {
+            return Err(AvifError::OutOfMemory);
+        }
+        let image = Self { inner };
+        let crop_rect = avifCropRect::from(rect);
+        let result = unsafe { avifImageSetViewRect(image.inner, self.inner, &crop_rect) };
+        if result != avifResult_AVIF_RESULT_OK {
+            return Err(AvifError::from(result));
+        }
+
+        Ok(ImageView {
+            image,
+            _marker: PhantomData,
+        })
+    }
+
     /// Returns the image width in pixels.
     pub fn width(&self) -> u32 {
         unsafe { (*self.inner).width as u32 }
@@ -205,13 +725,13 @@ impl Image {
     }
 
     /// Returns the YUV range (full or limited).
-    pub fn yuv_range(&self) -> avifRange {
-        unsafe { (*self.inner).yuvRange }
+    pub fn yuv_range(&self) -> YuvRange {
+        unsafe { (*self.inner).yuvRange.into() }
     }
 
     /// Sets the YUV range (full or limited).
-    pub fn set_yuv_range(&mut self, range: avifRange) {
-        unsafe { (*self.inner).yuvRange = range };
+    pub fn set_yuv_range(&mut self, range: YuvRange) {
+        unsafe { (*self.inner).yuvRange = range.into() };
     }
 
     /// Returns the color primaries.
@@ -244,16 +764,114 @@ impl Image {
         unsafe { (*self.inner).matrixCoefficients = mc };
     }
 
-    /// Allocates memory for the image planes (YUV and alpha).
+    /// Returns the rotation signaled by the `irot` transform property.
+    pub fn rotation(&self) -> Rotation {
+        let image = unsafe { &*self.inner };
+        if image.transformFlags & avifTransformFlag_AVIF_TRANSFORM_IROT as avifTransformFlags == 0 {
+            return Rotation::None;
+        }
+        match image.irot.angle & 3 {
+            1 => Rotation::Ccw90,
+            2 => Rotation::Ccw180,
+            3 => Rotation::Ccw270,
+            _ => Rotation::None,
+        }
+    }
+
+    /// Sets the `irot` transform property, which viewers apply on display.
+    ///
+    /// This does not modify the pixel data; see `rotate` for that.
+    pub fn set_rotation(&mut self, rotation: Rotation) {
+        let image = unsafe { &mut *self.inner };
+        let flag = avifTransformFlag_AVIF_TRANSFORM_IROT as avifTransformFlags;
+        image.irot.angle = rotation as u8;
+        if rotation == Rotation::None {
+            image.transformFlags &= !flag;
+        } else {
+            image.transformFlags |= flag;
+        }
+    }
+
+    /// Returns the mirroring signaled by the `imir` transform property.
+    pub fn mirror(&self) -> Option<Axis> {
+        let image = unsafe { &*self.inner };
+        if image.transformFlags & avifTransformFlag_AVIF_TRANSFORM_IMIR as avifTransformFlags == 0 {
+            return None;
+        }
+        match image.imir.axis {
+            0 => Some(Axis::Horizontal),
+            _ => Some(Axis::Vertical),
+        }
+    }
+
+    /// Sets the `imir` transform property, which viewers apply on display.
+    ///
+    /// This does not modify the pixel data; see `flip` for that.
+    pub fn set_mirror(&mut self, axis: Option<Axis>) {
+        let image = unsafe { &mut *self.inner };
+        let flag = avifTransformFlag_AVIF_TRANSFORM_IMIR as avifTransformFlags;
+        match axis {
+            Some(axis) => {
+                image.imir.axis = axis as u8;
+                image.transformFlags |= flag;
+            }
+            None => image.transformFlags &= !flag,
+        }
+    }
+
+    /// Returns the embedded ICC profile, or an empty slice if there is none.
+    pub fn icc(&self) -> &[u8] {
+        unsafe { rw_data_slice(&(*self.inner).icc) }
+    }
+
+    /// Sets the ICC profile, replacing any existing one.
+    pub fn set_icc(&mut self, icc: &[u8]) -> Result<()> {
+        let result = unsafe { avifImageSetProfileICC(self.inner, icc.as_ptr(), icc.len()) };
+        if result != avifResult_AVIF_RESULT_OK {
+            Err(AvifError::from(result))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the EXIF metadata payload, or an empty slice if there is none.
+    pub fn exif(&self) -> &[u8] {
+        unsafe { rw_data_slice(&(*self.inner).exif) }
+    }
+
+    /// Sets the EXIF metadata payload, replacing any existing one.
+    pub fn set_exif(&mut self, exif: &[u8]) -> Result<()> {
+        let result = unsafe { avifImageSetMetadataExif(self.inner, exif.as_ptr(), exif.len()) };
+        if result != avifResult_AVIF_RESULT_OK {
+            Err(AvifError::from(result))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the XMP metadata payload, or an empty slice if there is none.
+    pub fn xmp(&self) -> &[u8] {
+        unsafe { rw_data_slice(&(*self.inner).xmp) }
+    }
+
+    /// Sets the XMP metadata payload, replacing any existing one.
+    pub fn set_xmp(&mut self, xmp: &[u8]) -> Result<()> {
+        let result = unsafe { avifImageSetMetadataXMP(self.inner, xmp.as_ptr(), xmp.len()) };
+        if result != avifResult_AVIF_RESULT_OK {
+            Err(AvifError::from(result))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Allocates memory for the selected image planes.
     ///
     /// This must be called before writing pixel data to the image.
-    pub fn allocate_planes(&mut self) -> Result<()> {
-        let result = unsafe {
-            avifImageAllocatePlanes(
-                self.inner,
-                avifPlanesFlag_AVIF_PLANES_ALL as avifPlanesFlags,
-            )
-        };
+    ///
+    /// # Arguments
+    /// * `planes` - The planes to allocate
+    pub fn allocate_planes(&mut self, planes: Planes) -> Result<()> {
+        let result = unsafe { avifImageAllocatePlanes(self.inner, planes.bits()) };
         if result != avifResult_AVIF_RESULT_OK {
             Err(AvifError::from(result))
         } else {
@@ -261,41 +879,40 @@ impl Image {
         }
     }
 
-    /// Frees the memory used by the image planes.
-    pub fn free_planes(&mut self) {
-        unsafe {
-            avifImageFreePlanes(
-                self.inner,
-                avifPlanesFlag_AVIF_PLANES_ALL as avifPlanesFlags,
-            )
-        };
+    /// Frees the memory used by the selected image planes.
+    ///
+    /// # Arguments
+    /// * `planes` - The planes to free
+    pub fn free_planes(&mut self, planes: Planes) {
+        unsafe { avifImageFreePlanes(self.inner, planes.bits()) };
     }
 
-    /// Transfers ownership of planes from this image to another image.
+    /// Transfers ownership of the selected planes from this image to another image.
     ///
-    /// After this operation, this image will have empty planes.
-    pub fn steal_planes(&mut self, to_image: &mut Self) {
-        unsafe {
-            avifImageStealPlanes(
-                to_image.inner,
-                self.inner,
-                avifPlanesFlag_AVIF_PLANES_ALL as avifPlanesFlags,
-            )
-        };
+    /// After this operation, the selected planes of this image will be empty.
+    ///
+    /// # Arguments
+    /// * `to_image` - The image receiving the planes
+    /// * `planes` - The planes to transfer
+    pub fn steal_planes(&mut self, to_image: &mut Self, planes: Planes) {
+        unsafe { avifImageStealPlanes(to_image.inner, self.inner, planes.bits()) };
     }
 
     /// Creates a copy of this image.
     ///
-    /// This creates a new image with the same dimensions and copies all pixel data.
+    /// This copies all pixel data together with everything else the image
+    /// carries: ICC profile, EXIF and XMP metadata, CICP values, content light
+    /// level information, and transform properties (pasp, clap, irot, imir).
+    ///
+    /// Unlike `clone`, which panics, this reports a failure to allocate the
+    /// copy as an error.
     pub fn copy(&self) -> Result<Self> {
-        let copy = Self::new(self.width(), self.height(), self.depth(), self.yuv_format())?;
-        let result = unsafe {
-            avifImageCopy(
-                copy.inner,
-                self.inner,
-                avifPlanesFlag_AVIF_PLANES_ALL as avifPlanesFlags,
-            )
-        };
+        let inner = unsafe { avifImageCreateEmpty() };
+        if inner.is_null() {
+            return Err(AvifError::OutOfMemory);
+        }
+        let copy = Self { inner };
+        let result = unsafe { avifImageCopy(copy.inner, self.inner, Planes::ALL.bits()) };
         if result != avifResult_AVIF_RESULT_OK {
             Err(AvifError::from(result))
         } else {
@@ -326,8 +943,124 @@ impl Image {
     pub fn uses_u16(&self) -> bool {
         unsafe { avifImageUsesU16(self.inner) != 0 }
     }
+
+    /// Returns true if the image has an allocated alpha plane.
+    pub fn has_alpha(&self) -> bool {
+        unsafe { !(*self.inner).alphaPlane.is_null() }
+    }
+
+    /// Returns the width in samples of the given channel's plane.
+    ///
+    /// Returns 0 if the image has no such plane.
+    pub fn plane_width(&self, channel: Channel) -> u32 {
+        unsafe { avifImagePlaneWidth(self.inner, avifChannelIndex::from(channel) as i32) }
+    }
+
+    /// Returns the height in samples of the given channel's plane.
+    ///
+    /// Returns 0 if the image has no such plane.
+    pub fn plane_height(&self, channel: Channel) -> u32 {
+        unsafe { avifImagePlaneHeight(self.inner, avifChannelIndex::from(channel) as i32) }
+    }
+
+    /// Returns the number of bytes per row of the given channel's plane.
+    pub fn plane_row_bytes(&self, channel: Channel) -> u32 {
+        unsafe { avifImagePlaneRowBytes(self.inner, avifChannelIndex::from(channel) as i32) }
+    }
+
+    /// Returns the sample data of the given channel's plane.
+    ///
+    /// Samples are `u8` for 8-bit images and native-endian `u16` otherwise.
+    /// Returns `None` if the plane is not allocated.
+    pub fn plane(&self, channel: Channel) -> Option<&[u8]> {
+        let ptr = unsafe { avifImagePlane(self.inner, avifChannelIndex::from(channel) as i32) };
+        if ptr.is_null() {
+            return None;
+        }
+        let size = self.plane_row_bytes(channel) as usize * self.plane_height(channel) as usize;
+        Some(unsafe { std::slice::from_raw_parts(ptr, size) })
+    }
+
+    /// Returns the mutable sample data of the given channel's plane.
+    ///
+    /// Returns `None` if the plane is not allocated.
+    pub fn plane_mut(&mut self, channel: Channel) -> Option<&mut [u8]> {
+        let ptr = unsafe { avifImagePlane(self.inner, avifChannelIndex::from(channel) as i32) };
+        if ptr.is_null() {
+            return None;
+        }
+        let size = self.plane_row_bytes(channel) as usize * self.plane_height(channel) as usize;
+        Some(unsafe { std::slice::from_raw_parts_mut(ptr, size) })
+    }
+
+    /// Takes ownership of an image created by libavif.
+    ///
+    /// This lets images created by other components using libavif be used
+    /// with this crate.
+    ///
+    /// # Safety
+    /// `image` must be a valid, non-null pointer returned by
+    /// `avifImageCreate` or `avifImageCreateEmpty` of the libavif linked into
+    /// this crate, and must not be used or destroyed elsewhere afterwards.
+    /// In particular, no copy of the pointer may be kept by C code or other
+    /// non-Rust threads: `Image` is `Send` and `Sync`, so Rust code may read
+    /// or modify the image from any thread without synchronizing with them.
+    /// The image is destroyed when the returned value is dropped.
+    pub unsafe fn from_raw(image: *mut avifImage) -> Self {
+        Self { inner: image }
+    }
+
+    /// Releases ownership of the underlying libavif image.
+    ///
+    /// # Returns
+    /// The image, which the caller must destroy with `avifImageDestroy` or
+    /// pass back to `Image::from_raw`.
+    pub fn into_raw(mut self) -> *mut avifImage {
+        std::mem::replace(&mut self.inner, null_mut())
+    }
+
+    /// Returns the underlying libavif image without giving up ownership.
+    ///
+    /// The pointer is valid as long as this image is, and must not be
+    /// destroyed.
+    pub fn as_raw(&self) -> *const avifImage {
+        self.inner
+    }
+
+    /// Returns the underlying libavif image for modification without giving
+    /// up ownership.
+    ///
+    /// The pointer is valid as long as this image is, and must not be
+    /// destroyed.
+    pub fn as_raw_mut(&mut self) -> *mut avifImage {
+        self.inner
+    }
+}
+
+/// Cloning copies the image with `Image::copy`.
+///
+/// # Panics
+/// Panics if the copy fails, such as when its planes cannot be allocated.
+/// Use `Image::copy` to handle that error instead.
+impl Clone for Image {
+    fn clone(&self) -> Self {
+        self.copy().expect("Failed to copy image")
+    }
 }
 
+// SAFETY: Image exclusively owns its avifImage, as `from_raw` requires, so
+// sending it moves the only handle to the image. Sharing it is sound because
+// no `&self` path writes to the avifImage or its planes:
+// - accessors read fields or call avifImagePlane*, avifImageIsOpaque and
+//   similar functions that only read;
+// - avifImageCopy, avifImageSetViewRect, avifImageYUVToRGB and the
+//   encoder's avifEncoderAddImage* take the image as a const source;
+// - setters, plane allocation, scaling and orientation changes take
+//   `&mut self`.
+// libavif keeps no caches inside an avifImage that reads would update.
+unsafe impl Send for Image {}
+unsafe impl Sync for Image {}
+
 impl Drop for Image {
     fn drop(&mut self) {
         if !self.inner.is_null() {
@@ -337,3 +1070,62 @@ impl Drop for Image {
         }
     }
 }
+
+/// A single caller-owned image plane, used with [`Image::from_raw_planes`].
+#[derive(Debug, Clone, Copy)]
+pub struct RawPlane<'a> {
+    /// Sample data, starting at the top-left sample
+    pub data: &'a [u8],
+    /// Number of bytes between the starts of consecutive rows
+    pub row_bytes: u32,
+}
+
+impl<'a> RawPlane<'a> {
+    /// Creates a plane description from sample data and its stride.
+    pub fn new(data: &'a [u8], row_bytes: u32) -> Self {
+        Self { data, row_bytes }
+    }
+
+    fn validate(&self, width: u32, height: u32, sample_size: u32) -> Result<()> {
+        let min_row_bytes = width.checked_mul(sample_size).ok_or(AvifError::Overflow)?;
+        if self.row_bytes < min_row_bytes {
+            return Err(AvifError::InvalidArgument);
+        }
+        // libavif reads deeper samples as `u16`, so every row must start on
+        // a `u16` boundary.
+        if sample_size == 2
+            && !(self.row_bytes.is_multiple_of(2) && self.data.as_ptr().cast::<u16>().is_aligned())
+        {
+            return Err(AvifError::InvalidArgument);
+        }
+        // The last row must be padded too: `Image::plane` exposes
+        // `row_bytes * height` bytes of every plane.
+        let required = (self.row_bytes as usize)
+            .checked_mul(height as usize)
+            .ok_or(AvifError::Overflow)?;
+        if self.data.len() < required {
+            return Err(AvifError::InvalidArgument);
+        }
+        Ok(())
+    }
+}
+
+/// An image whose planes are borrowed from caller-owned buffers or from
+/// another image.
+///
+/// Created by [`Image::from_raw_planes`] and [`Image::view`]. The view
+/// dereferences to [`Image`],
+/// so it can be passed to the encoder like any other image, while the borrow
+/// keeps the underlying buffers alive and unmodified.
+pub struct ImageView<'a> {
+    image: Image,
+    _marker: PhantomData<&'a [u8]>,
+}
+
+impl Deref for ImageView<'_> {
+    type Target = Image;
+
+    fn deref(&self) -> &Image {
+        &self.image
+    }
+}
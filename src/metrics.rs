@@ -0,0 +1,137 @@
+//! Objective image quality metrics.
+//!
+//! This module extends `Image` with metrics comparing an image to a reference,
+//! typically the source of an encode against its decoded result. Metrics are
+//! computed on the YUV samples, without converting to RGB.
+
+use crate::par;
+use crate::transform::PlaneData;
+use crate::{AvifError, Channel, Image, Result};
+
+/// Side length of the windows SSIM statistics are computed over.
+const SSIM_WINDOW: usize = 8;
+/// Distance between consecutive SSIM windows.
+const SSIM_STEP: usize = 4;
+
+impl Image {
+    /// Computes the peak signal-to-noise ratio against another image, in dB.
+    ///
+    /// The mean squared error is taken over all samples of the Y, U and V
+    /// planes; alpha is ignored. Identical images yield infinity.
+    ///
+    /// # Arguments
+    /// * `other` - The image to compare with; must have the same dimensions,
+    ///   bit depth and YUV format
+    ///
+    /// # Returns
+    /// The PSNR, or an error if the images are incompatible or have no pixels.
+    pub fn psnr(&self, other: &Image) -> Result<f64> {
+        self.check_comparable(other)?;
+
+        let (mut sum, mut count) = (0.0f64, 0usize);
+        for channel in [Channel::Y, Channel::U, Channel::V] {
+            let (Some(a), Some(b)) = (
+                PlaneData::new(self, channel),
+                PlaneData::new(other, channel),
+            ) else {
+                continue;
+            };
+            let rows = par::map_range(a.height, |y| {
+                (0..a.width)
+                    .map(|x| {
+                        let diff = a.get(x, y) as f64 - b.get(x, y) as f64;
+                        diff * diff
+                    })
+                    .sum::<f64>()
+            });
+            sum += rows.iter().sum::<f64>();
+            count += a.width * a.height;
+        }
+        if count == 0 {
+            return Err(AvifError::NoContent);
+        }
+
+        let max = ((1u32 << u32::from(self.depth())) - 1) as f64;
+        let mse = sum / count as f64;
+        Ok(if mse == 0.0 {
+            f64::INFINITY
+        } else {
+            10.0 * (max * max / mse).log10()
+        })
+    }
+
+    /// Computes the structural similarity index against another image.
+    ///
+    /// SSIM is evaluated on the luma plane over 8x8 windows and averaged,
+    /// yielding a value up to 1.0 for identical images.
+    ///
+    /// # Arguments
+    /// * `other` - The image to compare with; must have the same dimensions,
+    ///   bit depth and YUV format
+    ///
+    /// # Returns
+    /// The mean SSIM, or an error if the images are incompatible or have no
+    /// pixels.
+    pub fn ssim(&self, other: &Image) -> Result<f64> {
+        self.check_comparable(other)?;
+        let (Some(a), Some(b)) = (
+            PlaneData::new(self, Channel::Y),
+            PlaneData::new(other, Channel::Y),
+        ) else {
+            return Err(AvifError::NoContent);
+        };
+
+        let max = ((1u32 << u32::from(self.depth())) - 1) as f64;
+        let c1 = (0.01 * max).powi(2);
+        let c2 = (0.03 * max).powi(2);
+        let window_w = SSIM_WINDOW.min(a.width);
+        let window_h = SSIM_WINDOW.min(a.height);
+        let n = (window_w * window_h) as f64;
+
+        let window_rows = (a.height - window_h) / SSIM_STEP + 1;
+        let rows = par::map_range(window_rows, |row| {
+            let y0 = row * SSIM_STEP;
+            let (mut total, mut windows) = (0.0f64, 0usize);
+            for x0 in (0..=a.width - window_w).step_by(SSIM_STEP) {
+                let (mut sa, mut sb, mut saa, mut sbb, mut sab) = (0.0, 0.0, 0.0, 0.0, 0.0);
+                for y in y0..y0 + window_h {
+                    for x in x0..x0 + window_w {
+                        let va = a.get(x, y) as f64;
+                        let vb = b.get(x, y) as f64;
+                        sa += va;
+                        sb += vb;
+                        saa += va * va;
+                        sbb += vb * vb;
+                        sab += va * vb;
+                    }
+                }
+                let (mean_a, mean_b) = (sa / n, sb / n);
+                let var_a = saa / n - mean_a * mean_a;
+                let var_b = sbb / n - mean_b * mean_b;
+                let covariance = sab / n - mean_a * mean_b;
+                total += ((2.0 * mean_a * mean_b + c1) * (2.0 * covariance + c2))
+                    / ((mean_a * mean_a + mean_b * mean_b + c1) * (var_a + var_b + c2));
+                windows += 1;
+            }
+            (total, windows)
+        });
+        let total: f64 = rows.iter().map(|&(total, _)| total).sum();
+        let windows: usize = rows.iter().map(|&(_, windows)| windows).sum();
+        Ok(total / windows as f64)
+    }
+
+    /// Checks that two images can be compared sample by sample.
+    fn check_comparable(&self, other: &Image) -> Result<()> {
+        if self.width() != other.width()
+            || self.height() != other.height()
+            || self.depth() != other.depth()
+            || self.yuv_format() != other.yuv_format()
+        {
+            return Err(AvifError::IncompatibleImage);
+        }
+        if self.width() == 0 || self.height() == 0 {
+            return Err(AvifError::NoContent);
+        }
+        Ok(())
+    }
+}
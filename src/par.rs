@@ -0,0 +1,80 @@
+//! Optional parallelism for crate-side loops.
+//!
+//! With the `rayon` feature, these helpers run on the current rayon thread
+//! pool: the global pool by default, or an application's own pool when the
+//! calling code runs inside `rayon::ThreadPool::install`. This lets an
+//! application bound the crate's parallelism together with its own work.
+//! Without the feature, the loops run sequentially on the calling thread.
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Calls `f` with each index in `0..count` and collects the results in
+/// order.
+pub(crate) fn map_range<R, F>(count: usize, f: F) -> Vec<R>
+where
+    R: Send,
+    F: Fn(usize) -> R + Sync + Send,
+{
+    #[cfg(feature = "rayon")]
+    {
+        (0..count).into_par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        (0..count).map(f).collect()
+    }
+}
+
+/// Calls `f` with the index and bytes of each row of a plane.
+pub(crate) fn for_each_row<F>(plane: &mut [u8], row_bytes: usize, f: F)
+where
+    F: Fn(usize, &mut [u8]) + Sync + Send,
+{
+    if row_bytes == 0 {
+        return;
+    }
+    #[cfg(feature = "rayon")]
+    {
+        plane
+            .par_chunks_mut(row_bytes)
+            .enumerate()
+            .for_each(|(y, row)| f(y, row));
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        plane
+            .chunks_mut(row_bytes)
+            .enumerate()
+            .for_each(|(y, row)| f(y, row));
+    }
+}
+
+/// Calls `f` with the index and bytes of each row of a plane, together with
+/// scratch state from `init` that is reused across the rows a thread
+/// handles.
+#[cfg(all(feature = "simd-yuv", not(feature = "libyuv")))]
+pub(crate) fn for_each_row_with<T, I, F>(plane: &mut [u8], row_bytes: usize, init: I, f: F)
+where
+    I: Fn() -> T + Sync + Send,
+    F: Fn(&mut T, usize, &mut [u8]) + Sync + Send,
+{
+    if row_bytes == 0 {
+        return;
+    }
+    #[cfg(feature = "rayon")]
+    {
+        plane
+            .par_chunks_mut(row_bytes)
+            .enumerate()
+            .for_each_init(init, |state, (y, row)| f(state, y, row));
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        let mut state = init();
+        plane
+            .chunks_mut(row_bytes)
+            .enumerate()
+            .for_each(|(y, row)| f(&mut state, y, row));
+    }
+}
@@ -0,0 +1,214 @@
+//! Reusable images of a fixed geometry.
+//!
+//! Services that convert and encode frame after frame of the same size would
+//! otherwise allocate and free full-size planes for every frame. An
+//! `ImagePool` keeps images that are no longer used and hands them out
+//! again, so steady-state processing allocates no plane memory.
+
+use crate::{AvifError, BitDepth, Channel, Image, PixelFormat, Planes, Result};
+use std::ops;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Geometry shared by the images of a pool, and the images not in use.
+struct Shared {
+    width: u32,
+    height: u32,
+    depth: BitDepth,
+    yuv_format: PixelFormat,
+    planes: Planes,
+    max_idle: AtomicUsize,
+    idle: Mutex<Vec<Image>>,
+}
+
+impl Shared {
+    /// Allocates a new image with the pool's geometry.
+    fn allocate(&self) -> Result<Image> {
+        let mut image = Image::new(self.width, self.height, self.depth, self.yuv_format)?;
+        image.allocate_planes(self.planes)?;
+        Ok(image)
+    }
+
+    /// Checks whether `image` still has the pool's geometry and planes.
+    fn fits(&self, image: &Image) -> bool {
+        let alpha = self.planes.contains(Planes::A);
+        image.width() == self.width
+            && image.height() == self.height
+            && image.depth() == self.depth
+            && image.yuv_format() == self.yuv_format
+            && (!self.planes.contains(Planes::YUV) || image.plane(Channel::Y).is_some())
+            && image.plane(Channel::A).is_some() == alpha
+    }
+
+    fn idle(&self) -> std::sync::MutexGuard<'_, Vec<Image>> {
+        // The list stays consistent even if a holder panicked.
+        self.idle.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// A pool of images with the same size, depth, YUV format and planes.
+///
+/// Images are taken with `get` and return to the pool when the
+/// `PooledImage` is dropped. Cloning the pool is cheap, and clones share
+/// their images, so one pool can serve several threads.
+#[derive(Clone)]
+pub struct ImagePool {
+    shared: Arc<Shared>,
+}
+
+impl ImagePool {
+    /// Creates an empty pool.
+    ///
+    /// # Arguments
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    /// * `depth` - Bit depth of the images
+    /// * `yuv_format` - YUV pixel format of the images
+    /// * `planes` - Planes to allocate in every image
+    ///
+    /// # Returns
+    /// A new pool, or `AvifError::InvalidArgument` if the dimensions are 0
+    /// or `yuv_format` is `PixelFormat::None`.
+    pub fn new(
+        width: u32,
+        height: u32,
+        depth: BitDepth,
+        yuv_format: PixelFormat,
+        planes: Planes,
+    ) -> Result<Self> {
+        if width == 0 || height == 0 || yuv_format == PixelFormat::None {
+            return Err(AvifError::InvalidArgument);
+        }
+        Ok(Self {
+            shared: Arc::new(Shared {
+                width,
+                height,
+                depth,
+                yuv_format,
+                planes,
+                max_idle: AtomicUsize::new(usize::MAX),
+                idle: Mutex::new(Vec::new()),
+            }),
+        })
+    }
+
+    /// Limits how many unused images the pool keeps.
+    ///
+    /// Images returned while the pool already holds this many are freed,
+    /// which bounds the memory held after a burst. Unused images beyond the
+    /// limit are freed immediately. There is no limit by default.
+    ///
+    /// # Arguments
+    /// * `count` - Maximum number of unused images to keep
+    pub fn set_max_idle(&self, count: usize) {
+        self.shared.max_idle.store(count, Ordering::Relaxed);
+        self.shared.idle().truncate(count);
+    }
+
+    /// Allocates images up front so that the first `count` calls to `get`
+    /// do not allocate.
+    ///
+    /// # Arguments
+    /// * `count` - Number of unused images the pool should hold
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if allocation fails.
+    pub fn preallocate(&self, count: usize) -> Result<()> {
+        let count = count.min(self.shared.max_idle.load(Ordering::Relaxed));
+        let missing = count.saturating_sub(self.idle_count());
+        let images = (0..missing)
+            .map(|_| self.shared.allocate())
+            .collect::<Result<Vec<_>>>()?;
+        self.shared.idle().extend(images);
+        Ok(())
+    }
+
+    /// Takes an image from the pool, allocating one if none is unused.
+    ///
+    /// A reused image keeps the samples and properties it had when it was
+    /// returned, so callers should set every property they rely on.
+    ///
+    /// # Returns
+    /// An image with the pool's geometry, or an error if allocation fails.
+    pub fn get(&self) -> Result<PooledImage> {
+        let image = match self.shared.idle().pop() {
+            Some(image) => image,
+            None => self.shared.allocate()?,
+        };
+        Ok(PooledImage {
+            image: Some(image),
+            shared: Arc::clone(&self.shared),
+        })
+    }
+
+    /// Returns the number of unused images the pool holds.
+    pub fn idle_count(&self) -> usize {
+        self.shared.idle().len()
+    }
+
+    /// Frees all unused images.
+    pub fn clear(&self) {
+        self.shared.idle().clear();
+    }
+
+    /// Returns the width of the pool's images in pixels.
+    pub fn width(&self) -> u32 {
+        self.shared.width
+    }
+
+    /// Returns the height of the pool's images in pixels.
+    pub fn height(&self) -> u32 {
+        self.shared.height
+    }
+}
+
+/// An image taken from an `ImagePool`.
+///
+/// It dereferences to `Image` and returns to its pool when dropped, unless
+/// its size, depth, YUV format or planes were changed meanwhile.
+pub struct PooledImage {
+    image: Option<Image>,
+    shared: Arc<Shared>,
+}
+
+impl PooledImage {
+    /// Removes the image from the pool's management.
+    ///
+    /// Use this to hand the image to code that keeps it, such as
+    /// `Frame::yuv`. The image is not returned to the pool afterwards.
+    ///
+    /// # Returns
+    /// The image.
+    pub fn detach(mut self) -> Image {
+        self.image.take().expect("pooled image already taken")
+    }
+}
+
+impl ops::Deref for PooledImage {
+    type Target = Image;
+
+    fn deref(&self) -> &Image {
+        self.image.as_ref().expect("pooled image already taken")
+    }
+}
+
+impl ops::DerefMut for PooledImage {
+    fn deref_mut(&mut self) -> &mut Image {
+        self.image.as_mut().expect("pooled image already taken")
+    }
+}
+
+impl Drop for PooledImage {
+    fn drop(&mut self) {
+        let Some(image) = self.image.take() else {
+            return;
+        };
+        if !self.shared.fits(&image) {
+            return;
+        }
+        let mut idle = self.shared.idle();
+        if idle.len() < self.shared.max_idle.load(Ordering::Relaxed) {
+            idle.push(image);
+        }
+    }
+}
@@ -6,41 +6,79 @@
 
 #![allow(non_upper_case_globals)]
 
-use crate::{AvifError, Image, Result};
+use crate::dither::{self, Dither};
+use crate::error::Operation;
+use crate::transform::write_sample;
+use crate::{AvifError, Image, Planes, Rect, Result};
 use libavif_sys::*;
 use std::slice;
 
+/// Sets the number of threads newly created RGB images use for color
+/// conversion.
+///
+/// This is the crate's thread budget, so it also applies to encoders,
+/// decoders and batch encoders created afterwards. Passing 0 removes it.
+#[deprecated(note = "use `set_thread_budget`, which sets this default")]
+pub fn set_default_max_threads(threads: u32) {
+    crate::set_thread_budget(threads);
+}
+
+/// Returns the number of threads newly created RGB images use for color
+/// conversion.
+///
+/// This is the crate's thread budget if one is set with
+/// `set_thread_budget`, or else the number of available CPUs. Use
+/// `RgbImage::set_max_threads` to override a single image.
+pub fn default_max_threads() -> u32 {
+    crate::threads::budget_or_available()
+}
+
 /// RGB pixel formats supported by AVIF.
 ///
 /// These formats define the order and arrangement of color channels
 /// in RGB pixel data.
+#[non_exhaustive]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RgbFormat {
     /// 24-bit RGB format (red, green, blue)
-    Rgb = avifRGBFormat_AVIF_RGB_FORMAT_RGB as isize,
+    Rgb,
     /// 32-bit RGBA format (red, green, blue, alpha)
-    Rgba = avifRGBFormat_AVIF_RGB_FORMAT_RGBA as isize,
+    Rgba,
     /// 32-bit ARGB format (alpha, red, green, blue)
-    Argb = avifRGBFormat_AVIF_RGB_FORMAT_ARGB as isize,
+    Argb,
     /// 24-bit BGR format (blue, green, red)
-    Bgr = avifRGBFormat_AVIF_RGB_FORMAT_BGR as isize,
+    Bgr,
     /// 32-bit BGRA format (blue, green, red, alpha)
-    Bgra = avifRGBFormat_AVIF_RGB_FORMAT_BGRA as isize,
+    Bgra,
     /// 32-bit ABGR format (alpha, blue, green, red)
-    Abgr = avifRGBFormat_AVIF_RGB_FORMAT_ABGR as isize,
+    Abgr,
     /// 16-bit RGB 565 format (5-bit red, 6-bit green, 5-bit blue)
-    Rgb565 = avifRGBFormat_AVIF_RGB_FORMAT_RGB_565 as isize,
+    Rgb565,
     /// 8-bit grayscale format
-    Gray = avifRGBFormat_AVIF_RGB_FORMAT_GRAY as isize,
+    Gray,
     /// 16-bit grayscale with alpha format
-    GrayA = avifRGBFormat_AVIF_RGB_FORMAT_GRAYA as isize,
+    GrayA,
     /// 16-bit alpha with grayscale format
-    AGray = avifRGBFormat_AVIF_RGB_FORMAT_AGRAY as isize,
+    AGray,
+    /// A format not known to this version of the crate
+    Unknown(avifRGBFormat),
 }
 
 impl From<RgbFormat> for avifRGBFormat {
     fn from(format: RgbFormat) -> Self {
-        format as _
+        match format {
+            RgbFormat::Rgb => avifRGBFormat_AVIF_RGB_FORMAT_RGB,
+            RgbFormat::Rgba => avifRGBFormat_AVIF_RGB_FORMAT_RGBA,
+            RgbFormat::Argb => avifRGBFormat_AVIF_RGB_FORMAT_ARGB,
+            RgbFormat::Bgr => avifRGBFormat_AVIF_RGB_FORMAT_BGR,
+            RgbFormat::Bgra => avifRGBFormat_AVIF_RGB_FORMAT_BGRA,
+            RgbFormat::Abgr => avifRGBFormat_AVIF_RGB_FORMAT_ABGR,
+            RgbFormat::Rgb565 => avifRGBFormat_AVIF_RGB_FORMAT_RGB_565,
+            RgbFormat::Gray => avifRGBFormat_AVIF_RGB_FORMAT_GRAY,
+            RgbFormat::GrayA => avifRGBFormat_AVIF_RGB_FORMAT_GRAYA,
+            RgbFormat::AGray => avifRGBFormat_AVIF_RGB_FORMAT_AGRAY,
+            RgbFormat::Unknown(format) => format,
+        }
     }
 }
 
@@ -57,7 +95,7 @@ impl From<avifRGBFormat> for RgbFormat {
             avifRGBFormat_AVIF_RGB_FORMAT_GRAY => RgbFormat::Gray,
             avifRGBFormat_AVIF_RGB_FORMAT_GRAYA => RgbFormat::GrayA,
             avifRGBFormat_AVIF_RGB_FORMAT_AGRAY => RgbFormat::AGray,
-            _ => RgbFormat::Rgb,
+            other => RgbFormat::Unknown(other),
         }
     }
 }
@@ -66,6 +104,7 @@ impl From<avifRGBFormat> for RgbFormat {
 ///
 /// When converting from subsampled YUV formats to RGB, chroma channels
 /// need to be upsampled to match the luma resolution.
+#[non_exhaustive]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ChromaUpsampling {
     /// Automatically choose the best method
@@ -90,6 +129,7 @@ impl From<ChromaUpsampling> for avifChromaUpsampling {
 ///
 /// When converting from RGB to subsampled YUV formats, chroma channels
 /// need to be downsampled to reduce resolution.
+#[non_exhaustive]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ChromaDownsampling {
     /// Automatically choose the best method
@@ -101,6 +141,9 @@ pub enum ChromaDownsampling {
     /// Simple averaging
     Average = avifChromaDownsampling_AVIF_CHROMA_DOWNSAMPLING_AVERAGE as isize,
     /// Sharp YUV downsampling (preserves edges better)
+    ///
+    /// Needs libsharpyuv, which the `sharpyuv` feature enables. Without it,
+    /// conversions fail with `AvifError::NotImplemented`.
     SharpYuv = avifChromaDownsampling_AVIF_CHROMA_DOWNSAMPLING_SHARP_YUV as isize,
 }
 
@@ -121,12 +164,26 @@ impl From<ChromaDownsampling> for avifChromaDownsampling {
 /// for the duration of the RgbImage's existence.
 pub struct RgbImage<'a> {
     pub(crate) inner: avifRGBImage,
+    dither: Dither,
     _marker: std::marker::PhantomData<&'a [u8]>,
 }
 
+// SAFETY: RgbImage only borrows its pixel buffer for `'a`, or shares the
+// lifetime of the buffer an OwnedRgbImage owns, and its avifRGBImage is a
+// plain struct rather than a libavif-managed object, so it can be sent
+// between threads. Sharing it is sound because no `&self` path writes to the
+// pixels or the struct: accessors only read, and conversions to YUV pass the
+// avifRGBImage to avifImageRGBToYUV and libyuv as a const source. Writing
+// pixels, including conversions from YUV, takes `&mut self`.
+unsafe impl Send for RgbImage<'_> {}
+unsafe impl Sync for RgbImage<'_> {}
+
 impl<'a> RgbImage<'a> {
     /// Creates an RGB image from existing pixel data.
     ///
+    /// Rows are assumed to be tightly packed. For depths above 8 bits, each
+    /// channel occupies two bytes.
+    ///
     /// # Arguments
     /// * `width` - Image width in pixels
     /// * `height` - Image height in pixels  
@@ -135,7 +192,8 @@ impl<'a> RgbImage<'a> {
     /// * `pixels` - Mutable slice containing pixel data
     ///
     /// # Returns
-    /// A new RgbImage instance or an error if the pixel data is insufficient.
+    /// A new RgbImage instance, `AvifError::Overflow` if the dimensions are
+    /// too large to address, or an error if the pixel data is insufficient.
     pub fn from_pixels(
         width: u32,
         height: u32,
@@ -143,34 +201,372 @@ impl<'a> RgbImage<'a> {
         format: RgbFormat,
         pixels: &'a mut [u8],
     ) -> Result<Self> {
-        let pixel_size = unsafe { avifRGBFormatChannelCount(format.into()) };
-        let expected_row_bytes = width * pixel_size;
-        let expected_size = (expected_row_bytes * height) as usize;
+        let inner = Self::raw(
+            width,
+            height,
+            depth.into(),
+            format,
+            false,
+            pixels.as_mut_ptr(),
+        );
+        Self::validated(inner, None, pixels.len())
+    }
+
+    /// Creates an RGB image from existing pixel data with an explicit row stride.
+    ///
+    /// This allows using buffers whose rows are padded, such as GPU readbacks,
+    /// Cairo surfaces, or Windows DIBs, without repacking them.
+    ///
+    /// # Arguments
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    /// * `depth` - Bit depth of the image
+    /// * `format` - RGB pixel format
+    /// * `row_bytes` - Number of bytes between the starts of consecutive rows
+    /// * `pixels` - Mutable slice containing pixel data
+    ///
+    /// # Returns
+    /// A new RgbImage instance or an error if `row_bytes` is smaller than a
+    /// packed row or the pixel data is insufficient.
+    pub fn from_pixels_with_row_bytes(
+        width: u32,
+        height: u32,
+        depth: crate::BitDepth,
+        format: RgbFormat,
+        row_bytes: u32,
+        pixels: &'a mut [u8],
+    ) -> Result<Self> {
+        let inner = Self::raw(
+            width,
+            height,
+            depth.into(),
+            format,
+            false,
+            pixels.as_mut_ptr(),
+        );
+        Self::validated(inner, Some(row_bytes), pixels.len())
+    }
 
-        if pixels.len() < expected_size {
+    /// Creates a grayscale image from existing single-channel pixel data.
+    ///
+    /// This is the monochrome counterpart of `from_pixels`; convert the result
+    /// with `to_gray_image` to get a `Yuv400` image without chroma planes.
+    ///
+    /// # Arguments
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    /// * `depth` - Bit depth of the image
+    /// * `pixels` - Mutable slice containing one sample per pixel
+    ///
+    /// # Returns
+    /// A new RgbImage instance or an error if the pixel data is insufficient.
+    pub fn from_gray(
+        width: u32,
+        height: u32,
+        depth: crate::BitDepth,
+        pixels: &'a mut [u8],
+    ) -> Result<Self> {
+        Self::from_pixels(width, height, depth, RgbFormat::Gray, pixels)
+    }
+
+    /// Creates an RGB image matching a YUV image, for converting it to RGB.
+    ///
+    /// Dimensions, bit depth and alpha premultiplication are taken from
+    /// `image`, so the result can be passed to `convert_from_yuv` without
+    /// mismatches. Rows are tightly packed; use `required_buffer_size` to size
+    /// `pixels`.
+    ///
+    /// # Arguments
+    /// * `image` - The YUV image the RGB image will be converted from
+    /// * `format` - RGB pixel format
+    /// * `pixels` - Mutable slice receiving the pixel data
+    ///
+    /// # Returns
+    /// A new RgbImage instance or an error if the pixel data is insufficient.
+    pub fn for_image(image: &Image, format: RgbFormat, pixels: &'a mut [u8]) -> Result<Self> {
+        let mut inner = Self::defaults_for(image, format);
+        inner.pixels = pixels.as_mut_ptr();
+        Self::validated(inner, None, pixels.len())
+    }
+
+    /// Returns the buffer size in bytes needed by `for_image`.
+    ///
+    /// # Arguments
+    /// * `image` - The YUV image the RGB image will be converted from
+    /// * `format` - RGB pixel format
+    pub fn required_buffer_size(image: &Image, format: RgbFormat) -> usize {
+        let inner = Self::defaults_for(image, format);
+        let row_bytes = inner.width as usize * unsafe { avifRGBImagePixelSize(&inner) } as usize;
+        row_bytes.saturating_mul(inner.height as usize)
+    }
+
+    fn defaults_for(image: &Image, format: RgbFormat) -> avifRGBImage {
+        let mut inner = unsafe { std::mem::zeroed::<avifRGBImage>() };
+        unsafe { avifRGBImageSetDefaults(&mut inner, image.inner) };
+        inner.format = format.into();
+        inner.maxThreads = default_max_threads() as i32;
+        inner
+    }
+
+    /// Creates an RGB image from existing 16-bit pixel data.
+    ///
+    /// This is the natural representation for 10- and 12-bit images, where each
+    /// channel is stored as one `u16` sample.
+    ///
+    /// # Arguments
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    /// * `depth` - Bit depth of the image (10 or 12 bits)
+    /// * `format` - RGB pixel format
+    /// * `pixels` - Mutable slice containing pixel samples
+    ///
+    /// # Returns
+    /// A new RgbImage instance or an error if the depth is 8 bits or the pixel
+    /// data is insufficient.
+    pub fn from_pixels_u16(
+        width: u32,
+        height: u32,
+        depth: crate::BitDepth,
+        format: RgbFormat,
+        pixels: &'a mut [u16],
+    ) -> Result<Self> {
+        if depth == crate::BitDepth::Eight || format == RgbFormat::Rgb565 {
             return Err(AvifError::InvalidArgument);
         }
+        let ptr = pixels.as_mut_ptr() as *mut u8;
+        let inner = Self::raw(width, height, depth.into(), format, false, ptr);
+        Self::validated(inner, None, std::mem::size_of_val(pixels))
+    }
 
+    /// Creates an RGB image from existing half-float pixel data.
+    ///
+    /// Half-float images are always 16 bits deep and are typically used for
+    /// linear-light HDR sources that are converted to 10- or 12-bit YUV.
+    ///
+    /// # Arguments
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    /// * `format` - RGB pixel format
+    /// * `pixels` - Mutable slice containing pixel samples
+    ///
+    /// # Returns
+    /// A new RgbImage instance or an error if the pixel data is insufficient.
+    #[cfg(feature = "half")]
+    pub fn from_pixels_f16(
+        width: u32,
+        height: u32,
+        format: RgbFormat,
+        pixels: &'a mut [half::f16],
+    ) -> Result<Self> {
+        if format == RgbFormat::Rgb565 {
+            return Err(AvifError::InvalidArgument);
+        }
+        let ptr = pixels.as_mut_ptr() as *mut u8;
+        let inner = Self::raw(width, height, 16, format, true, ptr);
+        Self::validated(inner, None, std::mem::size_of_val(pixels))
+    }
+
+    /// Creates an RGB image from `rgb` crate 8-bit RGB pixels.
+    ///
+    /// # Arguments
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    /// * `pixels` - Mutable slice containing one element per pixel
+    ///
+    /// # Returns
+    /// A new RgbImage instance or an error if the pixel data is insufficient.
+    #[cfg(feature = "rgb")]
+    pub fn from_rgb8(width: u32, height: u32, pixels: &'a mut [rgb::RGB8]) -> Result<Self> {
+        Self::from_pixel_slice(width, height, 8, RgbFormat::Rgb, pixels)
+    }
+
+    /// Creates an RGB image from `rgb` crate 8-bit RGBA pixels.
+    ///
+    /// # Arguments
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    /// * `pixels` - Mutable slice containing one element per pixel
+    ///
+    /// # Returns
+    /// A new RgbImage instance or an error if the pixel data is insufficient.
+    #[cfg(feature = "rgb")]
+    pub fn from_rgba8(width: u32, height: u32, pixels: &'a mut [rgb::RGBA8]) -> Result<Self> {
+        Self::from_pixel_slice(width, height, 8, RgbFormat::Rgba, pixels)
+    }
+
+    /// Creates an RGB image from `rgb` crate 16-bit RGB pixels.
+    ///
+    /// # Arguments
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    /// * `depth` - Bit depth of the samples (10 or 12 bits)
+    /// * `pixels` - Mutable slice containing one element per pixel
+    ///
+    /// # Returns
+    /// A new RgbImage instance or an error if the depth is 8 bits or the pixel
+    /// data is insufficient.
+    #[cfg(feature = "rgb")]
+    pub fn from_rgb16(
+        width: u32,
+        height: u32,
+        depth: crate::BitDepth,
+        pixels: &'a mut [rgb::RGB16],
+    ) -> Result<Self> {
+        if depth == crate::BitDepth::Eight {
+            return Err(AvifError::InvalidArgument);
+        }
+        Self::from_pixel_slice(width, height, depth.into(), RgbFormat::Rgb, pixels)
+    }
+
+    /// Creates an RGB image from `rgb` crate 16-bit RGBA pixels.
+    ///
+    /// # Arguments
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    /// * `depth` - Bit depth of the samples (10 or 12 bits)
+    /// * `pixels` - Mutable slice containing one element per pixel
+    ///
+    /// # Returns
+    /// A new RgbImage instance or an error if the depth is 8 bits or the pixel
+    /// data is insufficient.
+    #[cfg(feature = "rgb")]
+    pub fn from_rgba16(
+        width: u32,
+        height: u32,
+        depth: crate::BitDepth,
+        pixels: &'a mut [rgb::RGBA16],
+    ) -> Result<Self> {
+        if depth == crate::BitDepth::Eight {
+            return Err(AvifError::InvalidArgument);
+        }
+        Self::from_pixel_slice(width, height, depth.into(), RgbFormat::Rgba, pixels)
+    }
+
+    #[cfg(feature = "rgb")]
+    fn from_pixel_slice<P>(
+        width: u32,
+        height: u32,
+        depth: u32,
+        format: RgbFormat,
+        pixels: &'a mut [P],
+    ) -> Result<Self> {
+        let ptr = pixels.as_mut_ptr() as *mut u8;
+        let inner = Self::raw(width, height, depth, format, false, ptr);
+        Self::validated(inner, None, std::mem::size_of_val(pixels))
+    }
+
+    /// Creates an RGB image from a slice of any plain-old-data pixel type.
+    ///
+    /// The slice is reinterpreted as bytes, so `pixels` may be `[u16]`,
+    /// `[[u8; 4]]` or a `Pod` pixel struct laid out as `format` expects.
+    ///
+    /// # Arguments
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    /// * `depth` - Bit depth of the image
+    /// * `format` - RGB pixel format
+    /// * `pixels` - Mutable slice containing pixel data
+    ///
+    /// # Returns
+    /// A new RgbImage instance or an error if the pixel data is insufficient.
+    #[cfg(feature = "bytemuck")]
+    pub fn from_pod_pixels<P: bytemuck::Pod>(
+        width: u32,
+        height: u32,
+        depth: crate::BitDepth,
+        format: RgbFormat,
+        pixels: &'a mut [P],
+    ) -> Result<Self> {
+        let bytes: &'a mut [u8] = bytemuck::cast_slice_mut(pixels);
+        Self::from_pixels(width, height, depth, format, bytes)
+    }
+
+    /// Builds a raw RGB image with default conversion settings and no stride.
+    fn raw(
+        width: u32,
+        height: u32,
+        depth: u32,
+        format: RgbFormat,
+        is_float: bool,
+        pixels: *mut u8,
+    ) -> avifRGBImage {
+        avifRGBImage {
+            width,
+            height,
+            depth,
+            format: format.into(),
+            chromaUpsampling: ChromaUpsampling::Automatic.into(),
+            chromaDownsampling: ChromaDownsampling::Automatic.into(),
+            avoidLibYUV: 0,
+            ignoreAlpha: 0,
+            alphaPremultiplied: 0,
+            isFloat: if is_float { 1 } else { 0 },
+            maxThreads: default_max_threads() as i32,
+            pixels,
+            rowBytes: 0,
+        }
+    }
+
+    /// Sets the row stride of a raw RGB image and checks that a buffer of
+    /// `buffer_len` bytes holds every row.
+    ///
+    /// Without an explicit `row_bytes`, rows are assumed to be tightly packed.
+    fn validated(
+        mut inner: avifRGBImage,
+        row_bytes: Option<u32>,
+        buffer_len: usize,
+    ) -> Result<Self> {
+        let min_row_bytes = Self::min_row_bytes(&inner)?;
+        let row_bytes = row_bytes.unwrap_or(min_row_bytes);
+        if row_bytes < min_row_bytes {
+            return Err(AvifError::RowBytesTooSmall {
+                required: min_row_bytes,
+                actual: row_bytes,
+            });
+        }
+        let required = (row_bytes as usize)
+            .checked_mul(inner.height as usize)
+            .ok_or(AvifError::Overflow)?;
+        if buffer_len < required {
+            return Err(AvifError::BufferTooSmall {
+                required,
+                actual: buffer_len,
+            });
+        }
+
+        inner.rowBytes = row_bytes;
         Ok(Self {
-            inner: avifRGBImage {
-                width,
-                height,
-                depth: depth.into(),
-                format: format.into(),
-                chromaUpsampling: ChromaUpsampling::Automatic.into(),
-                chromaDownsampling: ChromaDownsampling::Automatic.into(),
-                avoidLibYUV: 0,
-                ignoreAlpha: 0,
-                alphaPremultiplied: 0,
-                isFloat: 0,
-                maxThreads: 1,
-                pixels: pixels.as_mut_ptr(),
-                rowBytes: expected_row_bytes,
-            },
+            inner,
+            dither: Dither::None,
             _marker: std::marker::PhantomData,
         })
     }
 
+    /// Returns the number of bytes in a row of pixels without padding.
+    fn min_row_bytes(inner: &avifRGBImage) -> Result<u32> {
+        let pixel_size = unsafe { avifRGBImagePixelSize(inner) };
+        inner
+            .width
+            .checked_mul(pixel_size)
+            .ok_or(AvifError::Overflow)
+    }
+
+    /// Checks that a row of pixels still fits in the row stride after a
+    /// change of pixel layout.
+    ///
+    /// The buffer was checked to hold `rowBytes * height` bytes on
+    /// construction, so this keeps every row inside it.
+    fn check_row_bytes(&self) -> Result<()> {
+        let min_row_bytes = Self::min_row_bytes(&self.inner)?;
+        if self.inner.rowBytes < min_row_bytes {
+            return Err(AvifError::RowBytesTooSmall {
+                required: min_row_bytes,
+                actual: self.inner.rowBytes,
+            });
+        }
+        Ok(())
+    }
+
     /// Returns the image width in pixels.
     pub fn width(&self) -> u32 {
         self.inner.width
@@ -192,8 +588,16 @@ impl<'a> RgbImage<'a> {
     }
 
     /// Sets the RGB pixel format.
-    pub fn set_format(&mut self, format: RgbFormat) {
+    ///
+    /// # Returns
+    /// An error if a row in the new format does not fit in the row stride of
+    /// the buffer, such as switching a tightly packed `Rgb` image to `Rgba`.
+    /// The format is left unchanged in that case.
+    pub fn set_format(&mut self, format: RgbFormat) -> Result<()> {
+        let previous = self.inner.format;
         self.inner.format = format.into();
+        self.check_row_bytes()
+            .inspect_err(|_| self.inner.format = previous)
     }
 
     /// Sets the chroma upsampling method.
@@ -222,13 +626,42 @@ impl<'a> RgbImage<'a> {
     }
 
     /// Sets whether the pixel data is floating point.
-    pub fn set_is_float(&mut self, is_float: bool) {
+    ///
+    /// # Returns
+    /// An error if a row of pixels no longer fits in the row stride of the
+    /// buffer, in which case the setting is left unchanged.
+    pub fn set_is_float(&mut self, is_float: bool) -> Result<()> {
+        let previous = self.inner.isFloat;
         self.inner.isFloat = if is_float { 1 } else { 0 };
+        self.check_row_bytes()
+            .inspect_err(|_| self.inner.isFloat = previous)
+    }
+
+    /// Returns the dithering applied when converting from YUV.
+    pub fn dither(&self) -> Dither {
+        self.dither
+    }
+
+    /// Sets the dithering applied when converting from YUV.
+    ///
+    /// Dithering applies when the output has less precision than the YUV
+    /// image: `Rgb565` output, or a lower bit depth, such as converting a
+    /// 10-bit HDR image to 8-bit RGBA. Plain rounding causes visible banding
+    /// on gradients such as skies in those cases.
+    pub fn set_dither(&mut self, dither: Dither) {
+        self.dither = dither;
+    }
+
+    /// Returns the maximum number of threads used for conversion.
+    pub fn max_threads(&self) -> u32 {
+        self.inner.maxThreads.max(1) as u32
     }
 
     /// Sets the maximum number of threads to use for conversion.
+    ///
+    /// Defaults to `default_max_threads()`.
     pub fn set_max_threads(&mut self, threads: u32) {
-        self.inner.maxThreads = threads.min(1024) as i32;
+        self.inner.maxThreads = threads.min(crate::MAX_THREADS) as i32;
     }
 
     /// Returns the size of a single pixel in bytes.
@@ -251,18 +684,205 @@ impl<'a> RgbImage<'a> {
         unsafe { avifRGBFormatIsGray(self.inner.format) != 0 }
     }
 
+    /// Returns the size of the pixel buffer in bytes.
+    ///
+    /// The product was checked to fit in `usize` when the image was created.
+    fn buffer_len(&self) -> usize {
+        self.inner.rowBytes as usize * self.inner.height as usize
+    }
+
     /// Returns the pixel data as a byte slice.
     pub fn pixels(&self) -> &[u8] {
-        let size = (self.inner.rowBytes * self.inner.height) as usize;
+        let size = self.buffer_len();
         unsafe { slice::from_raw_parts(self.inner.pixels, size) }
     }
 
     /// Returns the pixel data as a mutable byte slice.
     pub fn pixels_mut(&mut self) -> &mut [u8] {
-        let size = (self.inner.rowBytes * self.inner.height) as usize;
+        let size = self.buffer_len();
         unsafe { slice::from_raw_parts_mut(self.inner.pixels, size) }
     }
 
+    /// Returns the pixel data as 16-bit samples.
+    ///
+    /// Returns `None` for 8-bit images, or if the buffer is not suitably aligned
+    /// for `u16` access.
+    pub fn pixels_u16(&self) -> Option<&[u16]> {
+        if !self.is_u16_compatible() {
+            return None;
+        }
+        let len = self.buffer_len() / 2;
+        Some(unsafe { slice::from_raw_parts(self.inner.pixels as *const u16, len) })
+    }
+
+    /// Returns the pixel data as mutable 16-bit samples.
+    ///
+    /// Returns `None` for 8-bit images, or if the buffer is not suitably aligned
+    /// for `u16` access.
+    pub fn pixels_u16_mut(&mut self) -> Option<&mut [u16]> {
+        if !self.is_u16_compatible() {
+            return None;
+        }
+        let len = self.buffer_len() / 2;
+        Some(unsafe { slice::from_raw_parts_mut(self.inner.pixels as *mut u16, len) })
+    }
+
+    /// Returns the pixel data as half-float samples.
+    ///
+    /// Returns `None` if the image is not a float image, or if the buffer is not
+    /// suitably aligned for `f16` access.
+    #[cfg(feature = "half")]
+    pub fn pixels_f16(&self) -> Option<&[half::f16]> {
+        if !self.is_f16_compatible() {
+            return None;
+        }
+        let len = self.buffer_len() / 2;
+        Some(unsafe { slice::from_raw_parts(self.inner.pixels as *const half::f16, len) })
+    }
+
+    /// Returns the pixel data as mutable half-float samples.
+    ///
+    /// Returns `None` if the image is not a float image, or if the buffer is not
+    /// suitably aligned for `f16` access.
+    #[cfg(feature = "half")]
+    pub fn pixels_f16_mut(&mut self) -> Option<&mut [half::f16]> {
+        if !self.is_f16_compatible() {
+            return None;
+        }
+        let len = self.buffer_len() / 2;
+        Some(unsafe { slice::from_raw_parts_mut(self.inner.pixels as *mut half::f16, len) })
+    }
+
+    /// Returns the pixel data as `rgb` crate 8-bit RGB pixels.
+    ///
+    /// Returns `None` unless the image is 8-bit `Rgb`.
+    #[cfg(feature = "rgb")]
+    pub fn pixels_rgb8(&self) -> Option<&[rgb::RGB8]> {
+        self.pixels_as(RgbFormat::Rgb, false)
+    }
+
+    /// Returns the pixel data as mutable `rgb` crate 8-bit RGB pixels.
+    ///
+    /// Returns `None` unless the image is 8-bit `Rgb`.
+    #[cfg(feature = "rgb")]
+    pub fn pixels_rgb8_mut(&mut self) -> Option<&mut [rgb::RGB8]> {
+        self.pixels_as_mut(RgbFormat::Rgb, false)
+    }
+
+    /// Returns the pixel data as `rgb` crate 8-bit RGBA pixels.
+    ///
+    /// Returns `None` unless the image is 8-bit `Rgba`.
+    #[cfg(feature = "rgb")]
+    pub fn pixels_rgba8(&self) -> Option<&[rgb::RGBA8]> {
+        self.pixels_as(RgbFormat::Rgba, false)
+    }
+
+    /// Returns the pixel data as mutable `rgb` crate 8-bit RGBA pixels.
+    ///
+    /// Returns `None` unless the image is 8-bit `Rgba`.
+    #[cfg(feature = "rgb")]
+    pub fn pixels_rgba8_mut(&mut self) -> Option<&mut [rgb::RGBA8]> {
+        self.pixels_as_mut(RgbFormat::Rgba, false)
+    }
+
+    /// Returns the pixel data as `rgb` crate 16-bit RGB pixels.
+    ///
+    /// Returns `None` unless the image is 10- or 12-bit `Rgb`, or if the
+    /// buffer is not suitably aligned.
+    #[cfg(feature = "rgb")]
+    pub fn pixels_rgb16(&self) -> Option<&[rgb::RGB16]> {
+        self.pixels_as(RgbFormat::Rgb, true)
+    }
+
+    /// Returns the pixel data as mutable `rgb` crate 16-bit RGB pixels.
+    ///
+    /// Returns `None` unless the image is 10- or 12-bit `Rgb`, or if the
+    /// buffer is not suitably aligned.
+    #[cfg(feature = "rgb")]
+    pub fn pixels_rgb16_mut(&mut self) -> Option<&mut [rgb::RGB16]> {
+        self.pixels_as_mut(RgbFormat::Rgb, true)
+    }
+
+    /// Returns the pixel data as `rgb` crate 16-bit RGBA pixels.
+    ///
+    /// Returns `None` unless the image is 10- or 12-bit `Rgba`, or if the
+    /// buffer is not suitably aligned.
+    #[cfg(feature = "rgb")]
+    pub fn pixels_rgba16(&self) -> Option<&[rgb::RGBA16]> {
+        self.pixels_as(RgbFormat::Rgba, true)
+    }
+
+    /// Returns the pixel data as mutable `rgb` crate 16-bit RGBA pixels.
+    ///
+    /// Returns `None` unless the image is 10- or 12-bit `Rgba`, or if the
+    /// buffer is not suitably aligned.
+    #[cfg(feature = "rgb")]
+    pub fn pixels_rgba16_mut(&mut self) -> Option<&mut [rgb::RGBA16]> {
+        self.pixels_as_mut(RgbFormat::Rgba, true)
+    }
+
+    #[cfg(feature = "rgb")]
+    fn pixels_as<P>(&self, format: RgbFormat, wide: bool) -> Option<&[P]> {
+        if !self.is_pixel_compatible::<P>(format, wide) {
+            return None;
+        }
+        let len = self.buffer_len() / std::mem::size_of::<P>();
+        Some(unsafe { slice::from_raw_parts(self.inner.pixels as *const P, len) })
+    }
+
+    #[cfg(feature = "rgb")]
+    fn pixels_as_mut<P>(&mut self, format: RgbFormat, wide: bool) -> Option<&mut [P]> {
+        if !self.is_pixel_compatible::<P>(format, wide) {
+            return None;
+        }
+        let len = self.buffer_len() / std::mem::size_of::<P>();
+        Some(unsafe { slice::from_raw_parts_mut(self.inner.pixels as *mut P, len) })
+    }
+
+    #[cfg(feature = "rgb")]
+    fn is_pixel_compatible<P>(&self, format: RgbFormat, wide: bool) -> bool {
+        self.format() == format
+            && (self.inner.depth > 8) == wide
+            && self.inner.isFloat == 0
+            && (self.inner.rowBytes as usize).is_multiple_of(std::mem::size_of::<P>())
+            && self.inner.pixels.cast::<P>().is_aligned()
+    }
+
+    /// Reinterprets the pixel data as a slice of a plain-old-data type.
+    ///
+    /// Returns `None` if the buffer is not suitably aligned for `P` or its
+    /// length is not a multiple of `P`'s size.
+    #[cfg(feature = "bytemuck")]
+    pub fn cast_pixels<P: bytemuck::Pod>(&self) -> Option<&[P]> {
+        bytemuck::try_cast_slice(self.pixels()).ok()
+    }
+
+    /// Reinterprets the pixel data as a mutable slice of a plain-old-data
+    /// type.
+    ///
+    /// Returns `None` if the buffer is not suitably aligned for `P` or its
+    /// length is not a multiple of `P`'s size.
+    #[cfg(feature = "bytemuck")]
+    pub fn cast_pixels_mut<P: bytemuck::Pod>(&mut self) -> Option<&mut [P]> {
+        bytemuck::try_cast_slice_mut(self.pixels_mut()).ok()
+    }
+
+    #[cfg(feature = "half")]
+    fn is_f16_compatible(&self) -> bool {
+        self.inner.depth == 16
+            && self.inner.isFloat != 0
+            && self.inner.rowBytes.is_multiple_of(2)
+            && self.inner.pixels.cast::<half::f16>().is_aligned()
+    }
+
+    fn is_u16_compatible(&self) -> bool {
+        self.inner.depth > 8
+            && self.inner.isFloat == 0
+            && self.inner.format != avifRGBFormat_AVIF_RGB_FORMAT_RGB_565
+            && self.inner.rowBytes.is_multiple_of(2)
+            && self.inner.pixels.cast::<u16>().is_aligned()
+    }
+
     /// Returns the number of bytes per row.
     pub fn row_bytes(&self) -> u32 {
         self.inner.rowBytes
@@ -277,16 +897,228 @@ impl<'a> RgbImage<'a> {
     /// A new YUV Image or an error if conversion fails.
     pub fn to_yuv_image(&self, yuv_format: crate::PixelFormat) -> Result<Image> {
         let mut yuv_image = Image::new(self.width(), self.height(), self.depth(), yuv_format)?;
-        yuv_image.allocate_planes()?;
+        yuv_image.allocate_planes(Planes::ALL)?;
+        rgb_to_yuv(&mut yuv_image, &self.inner)?;
+        Ok(yuv_image)
+    }
 
-        let result = unsafe { avifImageRGBToYUV(yuv_image.inner, &self.inner) };
+    /// Converts this RGB image to a YUV image that preserves every sample.
+    ///
+    /// The result uses YUV 4:4:4, full range and identity matrix coefficients,
+    /// so the RGB values are stored unchanged as GBR planes. Encode it with
+    /// `Encoder::lossless` to produce a lossless AVIF.
+    ///
+    /// # Returns
+    /// A new YUV Image or an error if conversion fails.
+    pub fn to_lossless_yuv_image(&self) -> Result<Image> {
+        let mut yuv_image = Image::new(
+            self.width(),
+            self.height(),
+            self.depth(),
+            crate::PixelFormat::Yuv444,
+        )?;
+        yuv_image.set_yuv_range(crate::YuvRange::Full);
+        yuv_image
+            .set_matrix_coefficients(AVIF_MATRIX_COEFFICIENTS_IDENTITY as avifMatrixCoefficients);
+        yuv_image.allocate_planes(Planes::ALL)?;
+        self.convert_into(&mut yuv_image)?;
+        Ok(yuv_image)
+    }
+
+    /// Converts this image to a monochrome `Yuv400` image.
+    ///
+    /// Only the luma plane (and alpha, if present) is produced, which suits
+    /// grayscale sources such as scanned documents or medical images.
+    ///
+    /// # Returns
+    /// A new YUV Image or an error if conversion fails.
+    pub fn to_gray_image(&self) -> Result<Image> {
+        self.to_yuv_image(crate::PixelFormat::Yuv400)
+    }
+
+    /// Converts this RGB image into an existing YUV image.
+    ///
+    /// Planes already allocated in `image` are reused, which avoids a
+    /// per-call allocation when converting many frames of the same size. The
+    /// YUV format, bit depth and color properties of `image` are kept.
+    ///
+    /// # Arguments
+    /// * `image` - The YUV image to write into; must have the same dimensions
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if the dimensions differ or conversion
+    /// fails.
+    pub fn convert_into(&self, image: &mut Image) -> Result<()> {
+        if self.width() != image.width() || self.height() != image.height() {
+            return Err(AvifError::IncompatibleImage);
+        }
+        if image.yuv_format() == crate::PixelFormat::None {
+            return Err(AvifError::NoYuvFormatSelected);
+        }
+        rgb_to_yuv(image, &self.inner)
+    }
+
+    /// Converts a YUV image into this RGB image.
+    ///
+    /// The dimensions of both images must match; `for_image` creates a
+    /// suitable RGB image.
+    ///
+    /// # Arguments
+    /// * `image` - The YUV image to convert
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if conversion fails.
+    pub fn convert_from_yuv(&mut self, image: &Image) -> Result<()> {
+        let reduces_precision =
+            self.format() == RgbFormat::Rgb565 || u32::from(image.depth()) > self.inner.depth;
+        if self.dither != Dither::None && self.inner.isFloat == 0 && reduces_precision {
+            return self.convert_dithered(image);
+        }
+        yuv_to_rgb(image, &mut self.inner)
+    }
+
+    /// Converts a YUV image through a 16-bit intermediate and quantizes the
+    /// result into this image with dithering.
+    fn convert_dithered(&mut self, image: &Image) -> Result<()> {
+        let (format, bits) = match self.format() {
+            RgbFormat::Rgb565 => (RgbFormat::Rgb, vec![5, 6, 5]),
+            format => {
+                let channels = unsafe { avifRGBFormatChannelCount(format.into()) };
+                (format, vec![self.inner.depth; channels as usize])
+            }
+        };
+        let mut inner = self.inner;
+        inner.format = format.into();
+        inner.depth = 16;
+        inner.isFloat = 0;
+        inner.pixels = std::ptr::null_mut();
+        let mut wide = OwnedRgbImage::allocate(inner)?;
+        wide.image.convert_from_yuv(image)?;
+
+        let alpha = match format {
+            RgbFormat::Rgba | RgbFormat::Bgra => Some(3),
+            RgbFormat::Argb | RgbFormat::Abgr | RgbFormat::AGray => Some(0),
+            RgbFormat::GrayA => Some(1),
+            _ => None,
+        };
+        let samples = wide
+            .pixels_u16()
+            .expect("16-bit buffers are always u16-aligned");
+        let width = self.width() as usize;
+        let quantized = dither::quantize(self.dither, samples, width, &bits, alpha);
+
+        let rgb565 = self.format() == RgbFormat::Rgb565;
+        let wide_output = self.inner.depth > 8 || rgb565;
+        let row_bytes = self.inner.rowBytes as usize;
+        let pixels = self.pixels_mut();
+        for (y, row) in quantized.chunks_exact(width * bits.len()).enumerate() {
+            let out = &mut pixels[y * row_bytes..];
+            if rgb565 {
+                for (x, pixel) in row.chunks_exact(3).enumerate() {
+                    let value = (pixel[0] << 11) | (pixel[1] << 5) | pixel[2];
+                    write_sample(out, x, true, value);
+                }
+            } else {
+                for (x, &value) in row.iter().enumerate() {
+                    write_sample(out, x, wide_output, value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Converts a region of this RGB image into the same region of a YUV image.
+    ///
+    /// Both images must have the same dimensions. Only the samples inside
+    /// `rect` are written, which allows re-encoding just the modified area of a
+    /// large canvas. For subsampled formats, the region's offset must be aligned
+    /// to the chroma subsampling.
+    ///
+    /// # Arguments
+    /// * `image` - The YUV image to write into
+    /// * `rect` - The region to convert
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if the region is invalid or conversion
+    /// fails.
+    pub fn convert_region_to_yuv(&self, image: &mut Image, rect: Rect) -> Result<()> {
+        if image.plane(crate::Channel::Y).is_none() {
+            return Err(AvifError::NoContent);
+        }
+        let region = self.region(image, rect)?;
+        let view = image.view(rect)?;
+        let result = unsafe { avifImageRGBToYUV(view.inner, &region) };
         if result != avifResult_AVIF_RESULT_OK {
-            Err(AvifError::from(result))
+            Err(AvifError::from(result).context(Operation::RgbToYuv))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Converts a region of a YUV image into the same region of this RGB image.
+    ///
+    /// Both images must have the same dimensions. Only the pixels inside `rect`
+    /// are written.
+    ///
+    /// # Arguments
+    /// * `image` - The YUV image to read from
+    /// * `rect` - The region to convert
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if the region is invalid or conversion
+    /// fails.
+    pub fn convert_region_from_yuv(&mut self, image: &Image, rect: Rect) -> Result<()> {
+        let mut region = self.region(image, rect)?;
+        let view = image.view(rect)?;
+        let result = unsafe { avifImageYUVToRGB(view.inner, &mut region) };
+        if result != avifResult_AVIF_RESULT_OK {
+            Err(AvifError::from(result).context(Operation::YuvToRgb))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Converts a region of this RGB image into a new YUV image of the
+    /// region's size.
+    pub(crate) fn region_to_yuv_image(
+        &self,
+        rect: Rect,
+        yuv_format: crate::PixelFormat,
+    ) -> Result<Image> {
+        let region = self.sub_rect(rect)?;
+        let mut yuv_image = Image::new(rect.width, rect.height, self.depth(), yuv_format)?;
+        yuv_image.allocate_planes(Planes::ALL)?;
+        let result = unsafe { avifImageRGBToYUV(yuv_image.inner, &region) };
+        if result != avifResult_AVIF_RESULT_OK {
+            Err(AvifError::from(result).context(Operation::RgbToYuv))
         } else {
             Ok(yuv_image)
         }
     }
 
+    /// Returns a raw RGB image describing `rect` within this image's buffer.
+    fn region(&self, image: &Image, rect: Rect) -> Result<avifRGBImage> {
+        if self.width() != image.width() || self.height() != image.height() {
+            return Err(AvifError::IncompatibleImage);
+        }
+        self.sub_rect(rect)
+    }
+
+    /// Returns a raw RGB image describing `rect` within this image's buffer,
+    /// without checking it against a YUV image.
+    fn sub_rect(&self, rect: Rect) -> Result<avifRGBImage> {
+        if !rect.fits_within(self.width(), self.height()) {
+            return Err(AvifError::InvalidArgument);
+        }
+        let offset = rect.y as usize * self.inner.rowBytes as usize
+            + rect.x as usize * self.pixel_size() as usize;
+        let mut region = self.inner;
+        region.width = rect.width;
+        region.height = rect.height;
+        region.pixels = unsafe { self.inner.pixels.add(offset) };
+        Ok(region)
+    }
+
     /// Premultiplies the alpha channel with the color channels.
     ///
     /// This operation multiplies each color channel by the alpha value,
@@ -313,3 +1145,287 @@ impl<'a> RgbImage<'a> {
         }
     }
 }
+
+/// Storage backing an [`OwnedRgbImage`].
+enum PixelBuffer {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+}
+
+/// An RGB image that owns its pixel buffer.
+///
+/// This is typically the result of converting a YUV image to RGB. It
+/// dereferences to [`RgbImage`] for read access, and the buffer can be taken
+/// out without copying via `into_vec` or `into_vec_u16`.
+pub struct OwnedRgbImage {
+    image: RgbImage<'static>,
+    buffer: PixelBuffer,
+}
+
+impl OwnedRgbImage {
+    /// Creates a zero-filled RGB image.
+    ///
+    /// 8-bit images are backed by a `Vec<u8>`, deeper images by a `Vec<u16>`.
+    ///
+    /// # Arguments
+    /// * `width` - Image width in pixels
+    /// * `height` - Image height in pixels
+    /// * `depth` - Bit depth of the image
+    /// * `format` - RGB pixel format
+    ///
+    /// # Returns
+    /// A new OwnedRgbImage instance or an error if the size overflows.
+    pub fn new(width: u32, height: u32, depth: crate::BitDepth, format: RgbFormat) -> Result<Self> {
+        let inner = RgbImage::raw(
+            width,
+            height,
+            depth.into(),
+            format,
+            false,
+            std::ptr::null_mut(),
+        );
+        Self::allocate(inner)
+    }
+
+    /// Creates a zero-filled RGB image with the width, format, depth and
+    /// conversion settings of `rgb` and the given height.
+    pub(crate) fn like(rgb: &RgbImage, height: u32) -> Result<Self> {
+        let mut inner = rgb.inner;
+        inner.height = height;
+        inner.pixels = std::ptr::null_mut();
+        Self::allocate(inner)
+    }
+
+    /// Allocates a buffer for a raw RGB image with tightly packed rows.
+    fn allocate(mut inner: avifRGBImage) -> Result<Self> {
+        let pixel_size = unsafe { avifRGBImagePixelSize(&inner) };
+        let size = (inner.width as usize)
+            .checked_mul(pixel_size as usize)
+            .and_then(|row| row.checked_mul(inner.height as usize))
+            .ok_or(AvifError::Overflow)?;
+
+        let mut buffer = if inner.depth > 8 && inner.format != avifRGBFormat_AVIF_RGB_FORMAT_RGB_565
+        {
+            PixelBuffer::U16(vec![0; size / 2])
+        } else {
+            PixelBuffer::U8(vec![0; size])
+        };
+        let (ptr, len) = match &mut buffer {
+            PixelBuffer::U8(pixels) => (pixels.as_mut_ptr(), pixels.len()),
+            PixelBuffer::U16(pixels) => (pixels.as_mut_ptr() as *mut u8, pixels.len() * 2),
+        };
+        inner.pixels = ptr;
+        let image = RgbImage::validated(inner, None, len)?;
+
+        Ok(Self { image, buffer })
+    }
+
+    /// Returns the pixel data as a mutable byte slice.
+    pub fn pixels_mut(&mut self) -> &mut [u8] {
+        self.image.pixels_mut()
+    }
+
+    /// Returns the pixel data as mutable 16-bit samples.
+    ///
+    /// Returns `None` for 8-bit images.
+    pub fn pixels_u16_mut(&mut self) -> Option<&mut [u16]> {
+        self.image.pixels_u16_mut()
+    }
+
+    /// Reinterprets the pixel data as a mutable slice of a plain-old-data
+    /// type.
+    ///
+    /// Returns `None` if the buffer is not suitably aligned for `P` or its
+    /// length is not a multiple of `P`'s size.
+    #[cfg(feature = "bytemuck")]
+    pub fn cast_pixels_mut<P: bytemuck::Pod>(&mut self) -> Option<&mut [P]> {
+        self.image.cast_pixels_mut()
+    }
+
+    /// Consumes the image and returns its pixel buffer as bytes.
+    ///
+    /// This does not copy for 8-bit images. 16-bit buffers are converted to
+    /// native-endian bytes.
+    pub fn into_vec(self) -> Vec<u8> {
+        match self.buffer {
+            PixelBuffer::U8(pixels) => pixels,
+            PixelBuffer::U16(pixels) => pixels.iter().flat_map(|s| s.to_ne_bytes()).collect(),
+        }
+    }
+
+    /// Consumes the image and returns its 16-bit sample buffer without copying.
+    ///
+    /// Returns `None` for 8-bit images.
+    pub fn into_vec_u16(self) -> Option<Vec<u16>> {
+        match self.buffer {
+            PixelBuffer::U8(_) => None,
+            PixelBuffer::U16(pixels) => Some(pixels),
+        }
+    }
+}
+
+impl std::ops::Deref for OwnedRgbImage {
+    type Target = RgbImage<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.image
+    }
+}
+
+impl Image {
+    /// Converts this YUV image to a new RGB image.
+    ///
+    /// Bit depth and alpha premultiplication are taken from this image.
+    ///
+    /// # Arguments
+    /// * `format` - The target RGB pixel format
+    ///
+    /// # Returns
+    /// A new RGB image owning its pixels, or an error if conversion fails.
+    pub fn to_rgb_image(&self, format: RgbFormat) -> Result<OwnedRgbImage> {
+        let mut rgb = OwnedRgbImage::allocate(RgbImage::defaults_for(self, format))?;
+        rgb.image.convert_from_yuv(self)?;
+        Ok(rgb)
+    }
+
+    /// Converts this image to a grayscale RGB image with one sample per pixel.
+    ///
+    /// For `Yuv400` images this reads only the luma plane.
+    ///
+    /// # Returns
+    /// A new grayscale image owning its pixels, or an error if conversion
+    /// fails.
+    pub fn to_gray(&self) -> Result<OwnedRgbImage> {
+        self.to_rgb_image(RgbFormat::Gray)
+    }
+
+    /// Converts this image to RGB of the given depth with straight alpha,
+    /// as used for color management.
+    #[cfg(feature = "lcms2")]
+    pub(crate) fn to_straight_rgb(&self, format: RgbFormat, depth: u32) -> Result<OwnedRgbImage> {
+        let mut inner = RgbImage::defaults_for(self, format);
+        inner.depth = depth;
+        inner.alphaPremultiplied = 0;
+        let mut rgb = OwnedRgbImage::allocate(inner)?;
+        rgb.image.convert_from_yuv(self)?;
+        Ok(rgb)
+    }
+}
+
+/// Converts RGB pixels into the planes of `image`, using the crate's own
+/// conversion when libyuv is not compiled in and it supports the formats.
+fn rgb_to_yuv(image: &mut Image, rgb: &avifRGBImage) -> Result<()> {
+    if rgb.chromaDownsampling == ChromaDownsampling::SharpYuv.into()
+        && crate::sharpyuv_version().is_none()
+    {
+        return Err(AvifError::NotImplemented.with_detail(
+            Operation::RgbToYuv,
+            "sharp YUV downsampling needs the sharpyuv feature",
+        ));
+    }
+    #[cfg(all(feature = "simd-yuv", not(feature = "libyuv")))]
+    if crate::yuv::rgb_to_yuv(image, rgb) {
+        return Ok(());
+    }
+    let result = unsafe { avifImageRGBToYUV(image.inner, rgb) };
+    if result != avifResult_AVIF_RESULT_OK {
+        Err(AvifError::from(result).context(Operation::RgbToYuv))
+    } else {
+        Ok(())
+    }
+}
+
+/// Converts the planes of `image` into RGB pixels, using the crate's own
+/// conversion when libyuv is not compiled in and it supports the formats.
+fn yuv_to_rgb(image: &Image, rgb: &mut avifRGBImage) -> Result<()> {
+    #[cfg(all(feature = "simd-yuv", not(feature = "libyuv")))]
+    if crate::yuv::yuv_to_rgb(image, rgb) {
+        return Ok(());
+    }
+    let result = unsafe { avifImageYUVToRGB(image.inner, rgb) };
+    if result != avifResult_AVIF_RESULT_OK {
+        Err(AvifError::from(result).context(Operation::YuvToRgb))
+    } else {
+        Ok(())
+    }
+}
+
+/// Converts tightly packed 8-bit or 16-bit pixels to an 8-bit or 10-bit
+/// image. Gray pixels produce a `Yuv400` image.
+///
+/// With `lossless`, color pixels are stored unchanged as `Yuv444` GBR planes
+/// with identity matrix coefficients, as `RgbImage::to_lossless_yuv_image`
+/// does, and `yuv_format` is ignored.
+#[cfg(any(feature = "image", feature = "png", feature = "jpeg"))]
+pub(crate) fn image_from_rgb(
+    buf: &[u8],
+    width: u32,
+    height: u32,
+    format: RgbFormat,
+    wide: bool,
+    yuv_format: crate::PixelFormat,
+    lossless: bool,
+) -> Result<Image> {
+    use crate::{BitDepth, PixelFormat};
+
+    let depth = if wide { BitDepth::Ten } else { BitDepth::Eight };
+    let yuv_format = match format {
+        RgbFormat::Gray | RgbFormat::GrayA => PixelFormat::Yuv400,
+        _ if lossless => PixelFormat::Yuv444,
+        _ => yuv_format,
+    };
+
+    let mut pixels = buf.to_vec();
+    let mut rgb = RgbImage::from_pixels(width, height, depth, format, &mut pixels)?;
+    if wide {
+        // 16-bit samples take the same two bytes as 10-bit ones.
+        rgb.inner.depth = 16;
+    }
+    let mut image = Image::new(width, height, depth, yuv_format)?;
+    if lossless {
+        image.set_yuv_range(crate::YuvRange::Full);
+        if yuv_format != PixelFormat::Yuv400 {
+            image.set_matrix_coefficients(
+                AVIF_MATRIX_COEFFICIENTS_IDENTITY as avifMatrixCoefficients,
+            );
+        }
+    }
+    image.allocate_planes(Planes::ALL)?;
+    rgb.convert_into(&mut image)?;
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BitDepth;
+
+    #[test]
+    fn set_format_keeps_rows_inside_the_buffer() {
+        let mut pixels = vec![0u8; 4 * 3 * 2];
+        let mut rgb =
+            RgbImage::from_pixels(4, 2, BitDepth::Eight, RgbFormat::Rgb, &mut pixels).unwrap();
+        assert!(matches!(
+            rgb.set_format(RgbFormat::Rgba),
+            Err(AvifError::RowBytesTooSmall {
+                required: 16,
+                actual: 12
+            })
+        ));
+        assert_eq!(rgb.format(), RgbFormat::Rgb);
+        rgb.set_format(RgbFormat::Bgr).unwrap();
+        assert_eq!(rgb.format(), RgbFormat::Bgr);
+
+        let mut pixels = vec![0u8; 16 * 2];
+        let mut rgb = RgbImage::from_pixels_with_row_bytes(
+            4,
+            2,
+            BitDepth::Eight,
+            RgbFormat::Rgb,
+            16,
+            &mut pixels,
+        )
+        .unwrap();
+        rgb.set_format(RgbFormat::Rgba).unwrap();
+    }
+}
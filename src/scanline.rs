@@ -0,0 +1,206 @@
+//! Building grid images from incrementally supplied rows.
+//!
+//! Scanners and tiled renderers produce very large images a few rows at a
+//! time, often too large to hold as RGB. A `ScanlineGrid` collects those rows
+//! into one band of grid cells at a time and converts each completed band to
+//! YUV, so only a single band of RGB pixels is ever buffered. The YUV cells
+//! are kept until the grid is encoded, since libavif encodes all cells of a
+//! grid together.
+
+use crate::encoder::{AddImageFlags, Encoder, grid_cell_size};
+use crate::rgb::{OwnedRgbImage, RgbImage};
+use crate::{AvifError, Image, PixelFormat, Rect, Result, RwData, par};
+
+/// A grid image assembled from rows of RGB pixels supplied in order.
+pub struct ScanlineGrid {
+    width: u32,
+    height: u32,
+    cols: u32,
+    rows: u32,
+    cell_width: u32,
+    cell_height: u32,
+    yuv_format: PixelFormat,
+    /// Converted cells of the completed bands, in row-major order
+    cells: Vec<Image>,
+    /// RGB rows of the band being filled
+    band: Option<OwnedRgbImage>,
+    /// Number of rows already supplied
+    next_row: u32,
+    /// Error that interrupted a batch, after which the grid is unusable
+    error: Option<AvifError>,
+}
+
+impl ScanlineGrid {
+    /// Creates an empty grid.
+    ///
+    /// The image is split into `cols` x `rows` cells of equal size, except
+    /// that the last column and row take the remainder. Cells must be at
+    /// least 64x64 pixels, and cells before the last column or row must have
+    /// even dimensions where `yuv_format` subsamples chroma, and a grid has
+    /// at most 256 columns and rows.
+    ///
+    /// # Arguments
+    /// * `width` - Width of the whole image in pixels
+    /// * `height` - Height of the whole image in pixels
+    /// * `cols` - Number of columns in the grid
+    /// * `rows` - Number of rows in the grid
+    /// * `yuv_format` - The YUV format to convert the cells to
+    ///
+    /// # Returns
+    /// A new grid, `AvifError::InvalidArgument` if a dimension is 0, or
+    /// `AvifError::InvalidImageGrid` if the image cannot be split into a
+    /// valid grid.
+    pub fn new(
+        width: u32,
+        height: u32,
+        cols: u32,
+        rows: u32,
+        yuv_format: PixelFormat,
+    ) -> Result<Self> {
+        if width == 0 || height == 0 {
+            return Err(AvifError::InvalidArgument);
+        }
+        let (cell_width, cell_height) = grid_cell_size(width, height, cols, rows, yuv_format)?;
+        Ok(Self {
+            width,
+            height,
+            cols,
+            rows,
+            cell_width,
+            cell_height,
+            yuv_format,
+            cells: Vec::with_capacity(cols as usize * rows as usize),
+            band: None,
+            next_row: 0,
+            error: None,
+        })
+    }
+
+    /// Returns the number of rows still to be supplied.
+    pub fn remaining_rows(&self) -> u32 {
+        self.height - self.next_row
+    }
+
+    /// Appends the rows of `rgb` below the rows supplied so far.
+    ///
+    /// Batches may have any height and need not line up with the cells.
+    /// Every batch must have the image's width and the format and depth of
+    /// the first batch, whose conversion settings, such as chroma
+    /// downsampling, apply to the whole image.
+    ///
+    /// # Arguments
+    /// * `rgb` - The next rows of the image
+    ///
+    /// # Returns
+    /// Ok(()) on success, `AvifError::IncompatibleImage` if the batch does not
+    /// match the image, `AvifError::InvalidArgument` if it extends past the
+    /// bottom, or an error if converting a completed band fails. Since part
+    /// of the batch may already be taken by then, such an error leaves the
+    /// grid unusable, and later calls return it again.
+    pub fn push_rows(&mut self, rgb: &RgbImage) -> Result<()> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+        if rgb.width() != self.width {
+            return Err(AvifError::IncompatibleImage);
+        }
+        if rgb.height() > self.remaining_rows() {
+            return Err(AvifError::InvalidArgument);
+        }
+        if let Some(band) = &self.band
+            && (band.format() != rgb.format() || band.depth() != rgb.depth())
+        {
+            return Err(AvifError::IncompatibleImage);
+        }
+        self.append_rows(rgb)
+            .inspect_err(|error| self.error = Some(error.clone()))
+    }
+
+    /// Copies the rows of a validated batch into bands, converting each band
+    /// once it is complete.
+    fn append_rows(&mut self, rgb: &RgbImage) -> Result<()> {
+        let row_len = self.width as usize * rgb.pixel_size() as usize;
+        let src_row_bytes = rgb.row_bytes() as usize;
+        let mut src = rgb.pixels();
+        let mut rows = rgb.height();
+        while rows > 0 {
+            let band_top = self.next_row / self.cell_height * self.cell_height;
+            let band_height = self.cell_height.min(self.height - band_top);
+            let band = match &mut self.band {
+                Some(band) => band,
+                band => band.insert(OwnedRgbImage::like(rgb, self.cell_height)?),
+            };
+            let filled = self.next_row - band_top;
+            let count = rows.min(band_height - filled);
+            let dst_row_bytes = band.row_bytes() as usize;
+            let dst = &mut band.pixels_mut()[filled as usize * dst_row_bytes..];
+            for (dst, src) in dst
+                .chunks_mut(dst_row_bytes)
+                .zip(src.chunks(src_row_bytes))
+                .take(count as usize)
+            {
+                dst[..row_len].copy_from_slice(&src[..row_len]);
+            }
+            src = src
+                .get(count as usize * src_row_bytes..)
+                .unwrap_or_default();
+            rows -= count;
+            self.next_row += count;
+            if self.next_row == band_top + band_height {
+                self.convert_band(band_height)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Converts the cells of the filled band to YUV.
+    fn convert_band(&mut self, band_height: u32) -> Result<()> {
+        let Some(band) = &self.band else {
+            return Ok(());
+        };
+        let (width, cols, cell_width) = (self.width, self.cols, self.cell_width);
+        // Cells are converted in parallel with the `rayon` feature.
+        let cells = par::map_range(cols as usize, |col| {
+            let x = col as u32 * cell_width;
+            let rect = Rect::new(x, 0, cell_width.min(width - x), band_height);
+            band.region_to_yuv_image(rect, self.yuv_format)
+        });
+        for cell in cells {
+            self.cells.push(cell?);
+        }
+        Ok(())
+    }
+
+    /// Returns the converted cells once every row has been supplied.
+    ///
+    /// # Returns
+    /// The cells in row-major order, ready for `Encoder::add_image_grid`,
+    /// `AvifError::InvalidArgument` if rows are missing, or the error that
+    /// made `push_rows` fail.
+    pub fn into_cells(self) -> Result<Vec<Image>> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if self.remaining_rows() > 0 || self.cells.len() != self.cols as usize * self.rows as usize
+        {
+            return Err(AvifError::InvalidArgument);
+        }
+        Ok(self.cells)
+    }
+
+    /// Encodes the grid once every row has been supplied.
+    ///
+    /// # Arguments
+    /// * `encoder` - The encoder to encode the cells with
+    ///
+    /// # Returns
+    /// The encoded AVIF data, or an error if rows are missing or encoding
+    /// fails.
+    pub fn encode(self, encoder: &mut Encoder) -> Result<RwData> {
+        let (cols, rows) = (self.cols, self.rows);
+        let cells = self.into_cells()?;
+        let cell_refs: Vec<&Image> = cells.iter().collect();
+        encoder.add_image_grid(cols, rows, &cell_refs, AddImageFlags::SINGLE)?;
+        encoder.finish()
+    }
+}
@@ -0,0 +1,48 @@
+//! Crate-wide thread budget.
+//!
+//! Encoders, decoders and RGB images each have their own thread count. The
+//! budget set here becomes the default for all of them, so an application
+//! can bound the threads used for AVIF work in one place. Explicit
+//! `set_max_threads` calls still override it for a single object.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Thread budget for new objects; 0 means none is set.
+static THREAD_BUDGET: AtomicU32 = AtomicU32::new(0);
+
+/// Sets the number of threads that encoders, decoders, RGB images and batch
+/// encoders created afterwards use by default.
+///
+/// Passing 0 removes the budget, restoring libavif's single-threaded default
+/// for encoders and decoders and the number of available CPUs for RGB
+/// conversion and batches. Existing objects are not affected. Loops the
+/// crate runs on rayon with the `rayon` feature follow the rayon thread
+/// pool instead.
+///
+/// # Arguments
+/// * `threads` - Number of threads (clamped to 1024, or 1 on WebAssembly)
+pub fn set_thread_budget(threads: u32) {
+    THREAD_BUDGET.store(threads.min(crate::MAX_THREADS), Ordering::Relaxed);
+}
+
+/// Returns the thread budget set with `set_thread_budget`.
+///
+/// # Returns
+/// The number of threads, or `None` if no budget is set.
+pub fn thread_budget() -> Option<u32> {
+    match THREAD_BUDGET.load(Ordering::Relaxed) {
+        0 => None,
+        threads => Some(threads),
+    }
+}
+
+/// Returns the thread budget, or the number of available CPUs if none is
+/// set.
+pub(crate) fn budget_or_available() -> u32 {
+    thread_budget().unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|n| n.get() as u32)
+            .unwrap_or(1)
+            .min(crate::MAX_THREADS)
+    })
+}
@@ -0,0 +1,464 @@
+//! Pixel-level image transformations.
+//!
+//! This module extends `Image` with operations that rewrite the plane data
+//! itself, such as bit-depth conversion, rather than only changing the
+//! properties that describe it.
+
+use crate::{
+    AvifError, Axis, BitDepth, Channel, ChromaDownsampling, ChromaUpsampling, Image, PixelFormat,
+    Planes, Result, Rotation, YuvRange, par,
+};
+use libavif_sys::*;
+
+/// Reads the sample at column `x` of a plane row.
+pub(crate) fn read_sample(row: &[u8], x: usize, wide: bool) -> u16 {
+    if wide {
+        u16::from_ne_bytes([row[2 * x], row[2 * x + 1]])
+    } else {
+        row[x] as u16
+    }
+}
+
+/// Writes the sample at column `x` of a plane row.
+pub(crate) fn write_sample(row: &mut [u8], x: usize, wide: bool, value: u16) {
+    if wide {
+        row[2 * x..2 * x + 2].copy_from_slice(&value.to_ne_bytes());
+    } else {
+        row[x] = value as u8;
+    }
+}
+
+/// Rescales a sample from one bit depth to another.
+///
+/// Limited-range video samples are scaled by shifting so that black and white
+/// levels stay exact; full-range samples are scaled to preserve the extremes.
+fn rescale_sample(value: u16, from: u32, to: u32, limited: bool) -> u16 {
+    if limited {
+        if to >= from {
+            value << (to - from)
+        } else {
+            let shift = from - to;
+            let rounded = (value as u32 + (1 << (shift - 1))) >> shift;
+            rounded.min((1 << to) - 1) as u16
+        }
+    } else {
+        let from_max = (1u32 << from) - 1;
+        let to_max = (1u32 << to) - 1;
+        ((value as u32 * to_max + from_max / 2) / from_max) as u16
+    }
+}
+
+impl Image {
+    /// Creates an image carrying this image's properties and metadata with new
+    /// geometry, allocating the same set of planes this image has.
+    pub(crate) fn derive(
+        &self,
+        width: u32,
+        height: u32,
+        depth: BitDepth,
+        yuv_format: PixelFormat,
+    ) -> Result<Image> {
+        let inner = unsafe { avifImageCreateEmpty() };
+        if inner.is_null() {
+            return Err(AvifError::OutOfMemory);
+        }
+        let mut image = Image { inner };
+        let result = unsafe { avifImageCopy(image.inner, self.inner, 0) };
+        if result != avifResult_AVIF_RESULT_OK {
+            return Err(AvifError::from(result));
+        }
+        unsafe {
+            (*image.inner).width = width;
+            (*image.inner).height = height;
+            (*image.inner).depth = depth.into();
+            (*image.inner).yuvFormat = yuv_format.into();
+        }
+        if self.plane(Channel::Y).is_some() {
+            image.allocate_planes(Planes::YUV)?;
+        }
+        if self.has_alpha() {
+            image.allocate_planes(Planes::A)?;
+        }
+        Ok(image)
+    }
+
+    /// Converts the image to a different bit depth.
+    ///
+    /// All planes are rescaled with rounding. Limited-range color samples are
+    /// shifted so that nominal black and white stay exact, while full-range
+    /// color and alpha samples are scaled so that the maximum value maps to the
+    /// new maximum.
+    ///
+    /// # Arguments
+    /// * `depth` - The target bit depth
+    pub fn convert_depth(&mut self, depth: BitDepth) -> Result<()> {
+        let from = u32::from(self.depth());
+        let to = u32::from(depth);
+        if from == to {
+            return Ok(());
+        }
+
+        let mut converted = self.derive(self.width(), self.height(), depth, self.yuv_format())?;
+        let limited = self.yuv_range() == YuvRange::Limited;
+        let src_wide = self.uses_u16();
+        let dst_wide = converted.uses_u16();
+
+        for channel in Channel::ALL {
+            let Some(src) = self.plane(channel) else {
+                continue;
+            };
+            let width = self.plane_width(channel) as usize;
+            let height = self.plane_height(channel) as usize;
+            let src_row_bytes = self.plane_row_bytes(channel) as usize;
+            let dst_row_bytes = converted.plane_row_bytes(channel) as usize;
+            let shift_only = limited && channel != Channel::A;
+            let Some(dst) = converted.plane_mut(channel) else {
+                continue;
+            };
+
+            for y in 0..height {
+                let src_row = &src[y * src_row_bytes..];
+                let dst_row = &mut dst[y * dst_row_bytes..];
+                for x in 0..width {
+                    let value = read_sample(src_row, x, src_wide);
+                    let value = rescale_sample(value, from, to, shift_only);
+                    write_sample(dst_row, x, dst_wide, value);
+                }
+            }
+        }
+
+        *self = converted;
+        Ok(())
+    }
+
+    /// Converts the image to a different chroma subsampling format.
+    ///
+    /// The luma and alpha planes are kept as-is and the chroma planes are
+    /// resampled directly in YUV, avoiding a lossy round trip through RGB.
+    /// Converting to `Yuv400` drops the chroma planes; converting from `Yuv400`
+    /// fills them with neutral chroma.
+    ///
+    /// # Arguments
+    /// * `yuv_format` - The target YUV pixel format
+    /// * `downsampling` - Filter used when chroma resolution decreases
+    /// * `upsampling` - Filter used when chroma resolution increases
+    pub fn convert_format(
+        &mut self,
+        yuv_format: PixelFormat,
+        downsampling: ChromaDownsampling,
+        upsampling: ChromaUpsampling,
+    ) -> Result<()> {
+        if yuv_format == PixelFormat::None {
+            return Err(AvifError::NoYuvFormatSelected);
+        }
+        let from_format = self.yuv_format();
+        if from_format == yuv_format {
+            return Ok(());
+        }
+        if self.plane(Channel::Y).is_none() {
+            self.set_yuv_format(yuv_format);
+            return Ok(());
+        }
+
+        let mut converted = self.derive(self.width(), self.height(), self.depth(), yuv_format)?;
+        let wide = self.uses_u16();
+
+        for channel in [Channel::Y, Channel::A] {
+            let Some(src) = PlaneData::new(self, channel) else {
+                continue;
+            };
+            let row_len = src.width * if wide { 2 } else { 1 };
+            let dst_row_bytes = converted.plane_row_bytes(channel) as usize;
+            let Some(dst) = converted.plane_mut(channel) else {
+                continue;
+            };
+            for y in 0..src.height {
+                let src_row = &src.row(y)[..row_len];
+                dst[y * dst_row_bytes..y * dst_row_bytes + row_len].copy_from_slice(src_row);
+            }
+        }
+
+        let Some((dst_shift_x, dst_shift_y)) = yuv_format.chroma_shift() else {
+            *self = converted;
+            return Ok(());
+        };
+        let source_shift = from_format.chroma_shift();
+        let neutral = 1u16 << (u32::from(self.depth()) - 1);
+
+        for channel in [Channel::U, Channel::V] {
+            let src = PlaneData::new(self, channel);
+            let dst_width = converted.plane_width(channel) as usize;
+            let dst_height = converted.plane_height(channel) as usize;
+            let dst_row_bytes = converted.plane_row_bytes(channel) as usize;
+            let Some(dst) = converted.plane_mut(channel) else {
+                continue;
+            };
+
+            for y in 0..dst_height {
+                let dst_row = &mut dst[y * dst_row_bytes..];
+                for x in 0..dst_width {
+                    let value = match (&src, source_shift) {
+                        (Some(src), Some((src_shift_x, src_shift_y))) => {
+                            if dst_shift_x >= src_shift_x && dst_shift_y >= src_shift_y {
+                                let x0 = (x << dst_shift_x) >> src_shift_x;
+                                let y0 = (y << dst_shift_y) >> src_shift_y;
+                                if downsampling == ChromaDownsampling::Fastest {
+                                    src.get(x0, y0)
+                                } else {
+                                    let w = 1 << (dst_shift_x - src_shift_x);
+                                    let h = 1 << (dst_shift_y - src_shift_y);
+                                    src.average(x0, y0, w, h)
+                                }
+                            } else {
+                                let fx = source_position(x, dst_shift_x, src_shift_x);
+                                let fy = source_position(y, dst_shift_y, src_shift_y);
+                                match upsampling {
+                                    ChromaUpsampling::Fastest | ChromaUpsampling::Nearest => {
+                                        src.nearest(fx, fy)
+                                    }
+                                    _ => src.bilinear(fx, fy),
+                                }
+                            }
+                        }
+                        _ => neutral,
+                    };
+                    write_sample(dst_row, x, wide, value);
+                }
+            }
+        }
+
+        *self = converted;
+        Ok(())
+    }
+
+    /// Converts the sample values to a different YUV range.
+    ///
+    /// Unlike `set_yuv_range`, which only changes how the samples are
+    /// interpreted, this rescales the color planes so the image looks the same
+    /// afterwards. The alpha plane is always full range and is left untouched.
+    ///
+    /// # Arguments
+    /// * `range` - The target YUV range
+    pub fn convert_range(&mut self, range: YuvRange) -> Result<()> {
+        let from = self.yuv_range();
+        if from == range {
+            return Ok(());
+        }
+        if matches!(from, YuvRange::Unknown(_)) || matches!(range, YuvRange::Unknown(_)) {
+            return Err(AvifError::InvalidArgument);
+        }
+
+        let shift = u32::from(self.depth()) - 8;
+        let max = ((1u32 << u32::from(self.depth())) - 1) as f32;
+        let mid = (1u32 << (u32::from(self.depth()) - 1)) as f32;
+        let black = (16u32 << shift) as f32;
+        let luma_span = (219u32 << shift) as f32;
+        let chroma_span = (224u32 << shift) as f32;
+        let identity = self.matrix_coefficients()
+            == AVIF_MATRIX_COEFFICIENTS_IDENTITY as avifMatrixCoefficients;
+        let wide = self.uses_u16();
+
+        for channel in [Channel::Y, Channel::U, Channel::V] {
+            let width = self.plane_width(channel) as usize;
+            let height = self.plane_height(channel) as usize;
+            let row_bytes = self.plane_row_bytes(channel) as usize;
+            let luma_like = channel == Channel::Y || identity;
+            let Some(plane) = self.plane_mut(channel) else {
+                continue;
+            };
+
+            par::for_each_row(&mut plane[..height * row_bytes], row_bytes, |_, row| {
+                for x in 0..width {
+                    let value = read_sample(row, x, wide) as f32;
+                    let value = match (range, luma_like) {
+                        (YuvRange::Limited, true) => value * luma_span / max + black,
+                        (YuvRange::Limited, false) => (value - mid) * chroma_span / max + mid,
+                        (YuvRange::Full, true) => (value - black) * max / luma_span,
+                        (YuvRange::Full, false) => (value - mid) * max / chroma_span + mid,
+                        (YuvRange::Unknown(_), _) => unreachable!("checked above"),
+                    };
+                    write_sample(row, x, wide, value.round().clamp(0.0, max) as u16);
+                }
+            });
+        }
+
+        self.set_yuv_range(range);
+        Ok(())
+    }
+
+    /// Rotates the pixel data counter-clockwise.
+    ///
+    /// Unlike `set_rotation`, this physically moves the samples, for consumers
+    /// that do not honor transform properties. `Yuv422` images rotated by 90 or
+    /// 270 degrees are converted to `Yuv444` first, since the rotated chroma
+    /// layout has no AVIF equivalent.
+    ///
+    /// # Arguments
+    /// * `rotation` - The rotation to apply
+    pub fn rotate(&mut self, rotation: Rotation) -> Result<()> {
+        let quarter_turn = matches!(rotation, Rotation::Ccw90 | Rotation::Ccw270);
+        if quarter_turn && self.yuv_format() == PixelFormat::Yuv422 {
+            self.convert_format(
+                PixelFormat::Yuv444,
+                ChromaDownsampling::Automatic,
+                ChromaUpsampling::Bilinear,
+            )?;
+        }
+        let (width, height) = if quarter_turn {
+            (self.height(), self.width())
+        } else {
+            (self.width(), self.height())
+        };
+        match rotation {
+            Rotation::None => Ok(()),
+            Rotation::Ccw90 => self.remap(width, height, |x, y, w, _| (w - 1 - y, x)),
+            Rotation::Ccw180 => self.remap(width, height, |x, y, w, h| (w - 1 - x, h - 1 - y)),
+            Rotation::Ccw270 => self.remap(width, height, |x, y, _, h| (y, h - 1 - x)),
+        }
+    }
+
+    /// Mirrors the pixel data about the given axis.
+    ///
+    /// Unlike `set_mirror`, this physically moves the samples.
+    ///
+    /// # Arguments
+    /// * `axis` - The axis to mirror about
+    pub fn flip(&mut self, axis: Axis) -> Result<()> {
+        let (width, height) = (self.width(), self.height());
+        match axis {
+            Axis::Horizontal => self.remap(width, height, |x, y, _, h| (x, h - 1 - y)),
+            Axis::Vertical => self.remap(width, height, |x, y, w, _| (w - 1 - x, y)),
+        }
+    }
+
+    /// Applies the image's orientation to the pixel data.
+    ///
+    /// The `irot` and `imir` properties are applied, in that order, and then
+    /// cleared. If neither is set, the Exif orientation is applied instead.
+    /// The Exif orientation tag is reset to 1 so that viewers honoring it
+    /// do not orient the image a second time.
+    pub fn apply_orientation(&mut self) -> Result<()> {
+        if self.rotation() == Rotation::None && self.mirror().is_none() {
+            self.extract_exif_orientation()?;
+        } else {
+            self.reset_exif_orientation()?;
+        }
+        let (rotation, mirror) = (self.rotation(), self.mirror());
+        self.rotate(rotation)?;
+        if let Some(axis) = mirror {
+            self.flip(axis)?;
+        }
+        self.set_rotation(Rotation::None);
+        self.set_mirror(None);
+        Ok(())
+    }
+
+    /// Replaces the image with one of the given dimensions whose samples are
+    /// fetched through `map`.
+    ///
+    /// `map` receives destination coordinates and the source plane dimensions,
+    /// and returns the source coordinates, for every plane.
+    fn remap<F>(&mut self, width: u32, height: u32, map: F) -> Result<()>
+    where
+        F: Fn(usize, usize, usize, usize) -> (usize, usize),
+    {
+        let mut remapped = self.derive(width, height, self.depth(), self.yuv_format())?;
+        let wide = self.uses_u16();
+
+        for channel in Channel::ALL {
+            let Some(src) = PlaneData::new(self, channel) else {
+                continue;
+            };
+            let dst_width = remapped.plane_width(channel) as usize;
+            let dst_height = remapped.plane_height(channel) as usize;
+            let dst_row_bytes = remapped.plane_row_bytes(channel) as usize;
+            let Some(dst) = remapped.plane_mut(channel) else {
+                continue;
+            };
+
+            for y in 0..dst_height {
+                let dst_row = &mut dst[y * dst_row_bytes..];
+                for x in 0..dst_width {
+                    let (sx, sy) = map(x, y, src.width, src.height);
+                    write_sample(dst_row, x, wide, src.get(sx, sy));
+                }
+            }
+        }
+
+        *self = remapped;
+        Ok(())
+    }
+}
+
+/// Maps a sample index on a plane subsampled by `dst_shift` to a fractional
+/// sample position on a plane subsampled by `src_shift`, assuming centered
+/// sample siting.
+fn source_position(index: usize, dst_shift: u32, src_shift: u32) -> f32 {
+    let center = (index as f32 + 0.5) * (1u32 << dst_shift) as f32;
+    center / (1u32 << src_shift) as f32 - 0.5
+}
+
+/// Read-only access to the samples of a single plane.
+pub(crate) struct PlaneData<'a> {
+    data: &'a [u8],
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    row_bytes: usize,
+    wide: bool,
+}
+
+impl<'a> PlaneData<'a> {
+    /// Borrows the given plane of an image, or returns `None` if it is absent.
+    pub(crate) fn new(image: &'a Image, channel: Channel) -> Option<Self> {
+        Some(Self {
+            data: image.plane(channel)?,
+            width: image.plane_width(channel) as usize,
+            height: image.plane_height(channel) as usize,
+            row_bytes: image.plane_row_bytes(channel) as usize,
+            wide: image.uses_u16(),
+        })
+    }
+
+    /// Returns the bytes of row `y`, including any trailing padding.
+    pub(crate) fn row(&self, y: usize) -> &'a [u8] {
+        &self.data[y * self.row_bytes..(y + 1) * self.row_bytes]
+    }
+
+    /// Returns the sample at `(x, y)`, clamping coordinates to the plane.
+    pub(crate) fn get(&self, x: usize, y: usize) -> u16 {
+        let x = x.min(self.width - 1);
+        let y = y.min(self.height - 1);
+        read_sample(self.row(y), x, self.wide)
+    }
+
+    /// Returns the rounded average of a `w` x `h` block starting at `(x, y)`.
+    fn average(&self, x: usize, y: usize, w: usize, h: usize) -> u16 {
+        let mut sum = 0u32;
+        for dy in 0..h {
+            for dx in 0..w {
+                sum += self.get(x + dx, y + dy) as u32;
+            }
+        }
+        let count = (w * h) as u32;
+        ((sum + count / 2) / count) as u16
+    }
+
+    /// Returns the sample nearest to a fractional position.
+    fn nearest(&self, fx: f32, fy: f32) -> u16 {
+        let x = (fx + 0.5).floor().max(0.0) as usize;
+        let y = (fy + 0.5).floor().max(0.0) as usize;
+        self.get(x, y)
+    }
+
+    /// Returns the bilinear interpolation at a fractional position.
+    fn bilinear(&self, fx: f32, fy: f32) -> u16 {
+        let fx = fx.max(0.0);
+        let fy = fy.max(0.0);
+        let (x0, y0) = (fx.floor() as usize, fy.floor() as usize);
+        let (wx, wy) = (fx - x0 as f32, fy - y0 as f32);
+        let top = self.get(x0, y0) as f32 * (1.0 - wx) + self.get(x0 + 1, y0) as f32 * wx;
+        let bottom =
+            self.get(x0, y0 + 1) as f32 * (1.0 - wx) + self.get(x0 + 1, y0 + 1) as f32 * wx;
+        (top * (1.0 - wy) + bottom * wy).round() as u16
+    }
+}
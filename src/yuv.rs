@@ -0,0 +1,923 @@
+//! Rust RGB/YUV conversion for builds without libyuv.
+//!
+//! Without libyuv, libavif converts between RGB and YUV one pixel at a time
+//! in generic C code, which makes large images slow to encode and decode.
+//! This module handles the common cases itself: 8-bit RGB, BGR and their
+//! alpha variants to and from 8-bit 4:4:4, 4:2:2 and 4:2:0 YUV with the
+//! usual matrix coefficients. Rows are converted in parallel, eight pixels
+//! at a time, by kernels written against the [`Simd`] lane operations:
+//! AVX2 and FMA on x86-64 when the CPU supports them, NEON on AArch64, and
+//! plain arrays the compiler vectorizes otherwise. Anything else, including
+//! sharp YUV downsampling, is left to libavif.
+
+#![allow(non_upper_case_globals)]
+
+use crate::rgb::RgbFormat;
+use crate::{Channel, Image, YuvRange, par};
+use libavif_sys::*;
+use std::slice;
+
+/// Number of pixels the kernels process at a time.
+const LANES: usize = 8;
+
+/// Operations on eight `f32` lanes, the building blocks of the kernels.
+trait Simd: Copy {
+    type V: Copy;
+
+    /// Returns `value` in every lane.
+    fn splat(self, value: f32) -> Self::V;
+
+    fn load(self, src: &[f32; LANES]) -> Self::V;
+
+    /// Loads bytes, widened to `f32`.
+    fn load_u8(self, src: &[u8; LANES]) -> Self::V;
+
+    /// Returns `a * b + c`.
+    fn mul_add(self, a: Self::V, b: Self::V, c: Self::V) -> Self::V;
+
+    fn store(self, value: Self::V, dst: &mut [f32; LANES]);
+
+    /// Stores lanes rounded to the nearest byte, clamped to `0..=255`.
+    fn store_u8(self, value: Self::V, dst: &mut [u8; LANES]);
+}
+
+/// Portable lanes, left to the compiler to vectorize.
+#[cfg(not(target_arch = "aarch64"))]
+#[derive(Clone, Copy)]
+struct Scalar;
+
+#[cfg(not(target_arch = "aarch64"))]
+impl Simd for Scalar {
+    type V = [f32; LANES];
+
+    #[inline(always)]
+    fn splat(self, value: f32) -> Self::V {
+        [value; LANES]
+    }
+
+    #[inline(always)]
+    fn load(self, src: &[f32; LANES]) -> Self::V {
+        *src
+    }
+
+    #[inline(always)]
+    fn load_u8(self, src: &[u8; LANES]) -> Self::V {
+        src.map(f32::from)
+    }
+
+    #[inline(always)]
+    fn mul_add(self, a: Self::V, b: Self::V, c: Self::V) -> Self::V {
+        std::array::from_fn(|i| a[i] * b[i] + c[i])
+    }
+
+    #[inline(always)]
+    fn store(self, value: Self::V, dst: &mut [f32; LANES]) {
+        *dst = value;
+    }
+
+    #[inline(always)]
+    fn store_u8(self, value: Self::V, dst: &mut [u8; LANES]) {
+        *dst = value.map(|value| (value.clamp(0.0, 255.0) + 0.5) as u8);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use super::{LANES, Simd};
+    use std::arch::x86_64::*;
+
+    /// AVX2 and FMA lanes, which can only be created on CPUs that support
+    /// both.
+    #[derive(Clone, Copy)]
+    pub(super) struct Avx2(());
+
+    impl Avx2 {
+        pub(super) fn new() -> Option<Self> {
+            (std::arch::is_x86_feature_detected!("avx2")
+                && std::arch::is_x86_feature_detected!("fma"))
+            .then_some(Self(()))
+        }
+    }
+
+    /// Runs `f`, compiled for AVX2 and FMA.
+    ///
+    /// The kernels `f` calls are inlined into it, and `f` into this
+    /// function, so the intrinsics below are inlined as well.
+    #[target_feature(enable = "avx2,fma")]
+    pub(super) fn run(f: impl FnOnce()) {
+        f()
+    }
+
+    // SAFETY (all methods): an `Avx2` only exists when the CPU supports AVX2
+    // and FMA, and every load and store goes through a reference to exactly
+    // eight lanes.
+    impl Simd for Avx2 {
+        type V = __m256;
+
+        #[inline(always)]
+        fn splat(self, value: f32) -> __m256 {
+            unsafe { _mm256_set1_ps(value) }
+        }
+
+        #[inline(always)]
+        fn load(self, src: &[f32; LANES]) -> __m256 {
+            unsafe { _mm256_loadu_ps(src.as_ptr()) }
+        }
+
+        #[inline(always)]
+        fn load_u8(self, src: &[u8; LANES]) -> __m256 {
+            unsafe {
+                let bytes = _mm_loadl_epi64(src.as_ptr().cast());
+                _mm256_cvtepi32_ps(_mm256_cvtepu8_epi32(bytes))
+            }
+        }
+
+        #[inline(always)]
+        fn mul_add(self, a: __m256, b: __m256, c: __m256) -> __m256 {
+            unsafe { _mm256_fmadd_ps(a, b, c) }
+        }
+
+        #[inline(always)]
+        fn store(self, value: __m256, dst: &mut [f32; LANES]) {
+            unsafe { _mm256_storeu_ps(dst.as_mut_ptr(), value) }
+        }
+
+        #[inline(always)]
+        fn store_u8(self, value: __m256, dst: &mut [u8; LANES]) {
+            unsafe {
+                let value = _mm256_max_ps(value, _mm256_setzero_ps());
+                let value = _mm256_min_ps(value, _mm256_set1_ps(255.0));
+                let words = _mm256_cvttps_epi32(_mm256_add_ps(value, _mm256_set1_ps(0.5)));
+                let words = _mm_packus_epi32(
+                    _mm256_castsi256_si128(words),
+                    _mm256_extracti128_si256::<1>(words),
+                );
+                _mm_storel_epi64(dst.as_mut_ptr().cast(), _mm_packus_epi16(words, words));
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use super::{LANES, Simd};
+    use std::arch::aarch64::*;
+
+    /// NEON lanes, which every AArch64 CPU has.
+    #[derive(Clone, Copy)]
+    pub(super) struct Neon;
+
+    /// Rounds four lanes to the nearest integer, clamped to `0..=255`.
+    #[inline(always)]
+    fn narrow(value: float32x4_t) -> uint16x4_t {
+        let value = vminq_f32(vmaxq_f32(value, vdupq_n_f32(0.0)), vdupq_n_f32(255.0));
+        vmovn_u32(vcvtq_u32_f32(vaddq_f32(value, vdupq_n_f32(0.5))))
+    }
+
+    // SAFETY (loads and stores): each goes through a reference to exactly
+    // eight lanes.
+    impl Simd for Neon {
+        type V = (float32x4_t, float32x4_t);
+
+        #[inline(always)]
+        fn splat(self, value: f32) -> Self::V {
+            (vdupq_n_f32(value), vdupq_n_f32(value))
+        }
+
+        #[inline(always)]
+        fn load(self, src: &[f32; LANES]) -> Self::V {
+            let (low, high) = src.split_at(4);
+            unsafe { (vld1q_f32(low.as_ptr()), vld1q_f32(high.as_ptr())) }
+        }
+
+        #[inline(always)]
+        fn load_u8(self, src: &[u8; LANES]) -> Self::V {
+            let words = vmovl_u8(unsafe { vld1_u8(src.as_ptr()) });
+            (
+                vcvtq_f32_u32(vmovl_u16(vget_low_u16(words))),
+                vcvtq_f32_u32(vmovl_u16(vget_high_u16(words))),
+            )
+        }
+
+        #[inline(always)]
+        fn mul_add(self, a: Self::V, b: Self::V, c: Self::V) -> Self::V {
+            (vfmaq_f32(c.0, a.0, b.0), vfmaq_f32(c.1, a.1, b.1))
+        }
+
+        #[inline(always)]
+        fn store(self, value: Self::V, dst: &mut [f32; LANES]) {
+            let (low, high) = dst.split_at_mut(4);
+            unsafe {
+                vst1q_f32(low.as_mut_ptr(), value.0);
+                vst1q_f32(high.as_mut_ptr(), value.1);
+            }
+        }
+
+        #[inline(always)]
+        fn store_u8(self, value: Self::V, dst: &mut [u8; LANES]) {
+            let bytes = vmovn_u16(vcombine_u16(narrow(value.0), narrow(value.1)));
+            unsafe { vst1_u8(dst.as_mut_ptr(), bytes) }
+        }
+    }
+}
+
+/// Runs `$body` with `$s` bound to the widest [`Simd`] lanes the CPU
+/// supports, compiling the body once for each kind of lanes.
+macro_rules! with_simd {
+    ($s:ident => $body:expr) => {{
+        #[cfg(target_arch = "x86_64")]
+        match avx2::Avx2::new() {
+            Some($s) => {
+                let f = || $body;
+                // SAFETY: the CPU supports the features `run` enables.
+                unsafe { avx2::run(f) }
+            }
+            None => {
+                let $s = Scalar;
+                $body
+            }
+        };
+        #[cfg(target_arch = "aarch64")]
+        {
+            let $s = neon::Neon;
+            $body
+        };
+        #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+        {
+            let $s = Scalar;
+            $body
+        };
+    }};
+}
+
+/// Loads the eight bytes of `src` starting at `x`, reading zeros past its
+/// end.
+#[inline(always)]
+fn load_u8_at<S: Simd>(s: S, src: &[u8], x: usize) -> S::V {
+    let src = &src[x..];
+    match src.first_chunk::<LANES>() {
+        Some(lanes) => s.load_u8(lanes),
+        None => {
+            let mut lanes = [0; LANES];
+            lanes[..src.len()].copy_from_slice(src);
+            s.load_u8(&lanes)
+        }
+    }
+}
+
+/// Stores as many rounded lanes of `value` as `dst` holds, at most eight.
+#[inline(always)]
+fn store_u8_to<S: Simd>(s: S, value: S::V, dst: &mut [u8]) {
+    match dst.first_chunk_mut::<LANES>() {
+        Some(lanes) => s.store_u8(value, lanes),
+        None => {
+            let mut lanes = [0; LANES];
+            s.store_u8(value, &mut lanes);
+            dst.copy_from_slice(&lanes[..dst.len()]);
+        }
+    }
+}
+
+/// Luma coefficients of a YUV matrix.
+#[derive(Clone, Copy)]
+struct Matrix {
+    kr: f32,
+    kg: f32,
+    kb: f32,
+}
+
+impl Matrix {
+    /// Returns the coefficients of the given matrix coefficients, treating
+    /// unspecified as BT.709 like libavif, or `None` for matrices that are
+    /// not a plain weighting of R, G and B.
+    fn new(matrix_coefficients: avifMatrixCoefficients) -> Option<Self> {
+        let (kr, kb) = match matrix_coefficients as u32 {
+            AVIF_MATRIX_COEFFICIENTS_BT709 | AVIF_MATRIX_COEFFICIENTS_UNSPECIFIED => {
+                (0.2126, 0.0722)
+            }
+            AVIF_MATRIX_COEFFICIENTS_FCC => (0.30, 0.11),
+            AVIF_MATRIX_COEFFICIENTS_BT470BG | AVIF_MATRIX_COEFFICIENTS_BT601 => (0.299, 0.114),
+            AVIF_MATRIX_COEFFICIENTS_SMPTE240 => (0.212, 0.087),
+            AVIF_MATRIX_COEFFICIENTS_BT2020_NCL => (0.2627, 0.0593),
+            _ => return None,
+        };
+        Some(Self {
+            kr,
+            kg: 1.0 - kr - kb,
+            kb,
+        })
+    }
+}
+
+/// Positions of the channels within an RGB pixel.
+#[derive(Clone, Copy)]
+struct Layout {
+    r: usize,
+    g: usize,
+    b: usize,
+    a: Option<usize>,
+}
+
+impl Layout {
+    fn new(format: RgbFormat) -> Option<Self> {
+        let (r, g, b, a) = match format {
+            RgbFormat::Rgb => (0, 1, 2, None),
+            RgbFormat::Rgba => (0, 1, 2, Some(3)),
+            RgbFormat::Argb => (1, 2, 3, Some(0)),
+            RgbFormat::Bgr => (2, 1, 0, None),
+            RgbFormat::Bgra => (2, 1, 0, Some(3)),
+            RgbFormat::Abgr => (3, 2, 1, Some(0)),
+            _ => return None,
+        };
+        Some(Self { r, g, b, a })
+    }
+
+    fn pixel_size(&self) -> usize {
+        if self.a.is_some() { 4 } else { 3 }
+    }
+
+    /// Splits up to eight pixels into their R, G and B lanes.
+    #[inline(always)]
+    fn split<const N: usize>(&self, pixels: &[[u8; N]]) -> [[f32; LANES]; 3] {
+        let mut lanes = [[0.0; LANES]; 3];
+        for (i, px) in pixels.iter().enumerate() {
+            lanes[0][i] = px[self.r] as f32;
+            lanes[1][i] = px[self.g] as f32;
+            lanes[2][i] = px[self.b] as f32;
+        }
+        lanes
+    }
+}
+
+/// Parameters of a conversion this module can perform.
+struct Conversion {
+    matrix: Matrix,
+    layout: Layout,
+    /// Scale and offset of luma code values
+    y_scale: f32,
+    y_offset: f32,
+    /// Scale of chroma code values around 128
+    uv_scale: f32,
+    shift_x: u32,
+    shift_y: u32,
+    width: usize,
+    height: usize,
+    rgb_row_bytes: usize,
+    /// Whether RGB alpha is read or written rather than ignored
+    rgb_alpha: bool,
+}
+
+impl Conversion {
+    /// Checks whether a conversion between `image` and `rgb` is supported.
+    fn new(image: &Image, rgb: &avifRGBImage) -> Option<Self> {
+        if rgb.avoidLibYUV != 0
+            || rgb.isFloat != 0
+            || rgb.depth != 8
+            || unsafe { (*image.as_raw()).depth } != 8
+            || rgb.width != image.width()
+            || rgb.height != image.height()
+            || image.plane(Channel::Y).is_none()
+        {
+            return None;
+        }
+        let layout = Layout::new(RgbFormat::from(rgb.format))?;
+        let rgb_alpha = layout.a.is_some() && rgb.ignoreAlpha == 0;
+        if rgb_alpha
+            && image.plane(Channel::A).is_some()
+            && (rgb.alphaPremultiplied != 0) != image.alpha_premultiplied()
+        {
+            return None;
+        }
+        let (shift_x, shift_y) = image.yuv_format().chroma_shift()?;
+        let (y_scale, y_offset, uv_scale) = match image.yuv_range() {
+            YuvRange::Full => (255.0, 0.0, 255.0),
+            _ => (219.0, 16.0, 224.0),
+        };
+        Some(Self {
+            matrix: Matrix::new(image.matrix_coefficients())?,
+            layout,
+            y_scale,
+            y_offset,
+            uv_scale,
+            shift_x,
+            shift_y,
+            width: rgb.width as usize,
+            height: rgb.height as usize,
+            rgb_row_bytes: rgb.rowBytes as usize,
+            rgb_alpha,
+        })
+    }
+
+    /// Returns the length of the RGB buffer, whose last row may omit the
+    /// padding of the others.
+    fn rgb_len(&self) -> usize {
+        match self.height {
+            0 => 0,
+            height => (height - 1) * self.rgb_row_bytes + self.width * self.layout.pixel_size(),
+        }
+    }
+
+    fn chroma_width(&self) -> usize {
+        (self.width + (1 << self.shift_x) - 1) >> self.shift_x
+    }
+
+    fn chroma_height(&self) -> usize {
+        (self.height + (1 << self.shift_y) - 1) >> self.shift_y
+    }
+
+    /// Returns the pixels of row `y` of an RGB buffer.
+    fn rgb_row<'a, const N: usize>(&self, pixels: &'a [u8], y: usize) -> &'a [[u8; N]] {
+        pixels[y * self.rgb_row_bytes..][..self.width * N]
+            .as_chunks()
+            .0
+    }
+}
+
+/// Writes the luma of one row of pixels.
+#[inline(always)]
+fn luma_row<S: Simd, const N: usize>(s: S, conv: &Conversion, pixels: &[[u8; N]], dst: &mut [u8]) {
+    let (m, l) = (conv.matrix, conv.layout);
+    let scale = conv.y_scale / 255.0;
+    let (kr, kg, kb) = (
+        s.splat(m.kr * scale),
+        s.splat(m.kg * scale),
+        s.splat(m.kb * scale),
+    );
+    let offset = s.splat(conv.y_offset);
+    for (dst, pixels) in dst[..conv.width]
+        .chunks_mut(LANES)
+        .zip(pixels.chunks(LANES))
+    {
+        let [r, g, b] = l.split(pixels);
+        let y = s.mul_add(s.load(&b), kb, offset);
+        let y = s.mul_add(s.load(&g), kg, y);
+        let y = s.mul_add(s.load(&r), kr, y);
+        store_u8_to(s, y, dst);
+    }
+}
+
+/// Writes one row of the U or V plane from the average of the pixels each
+/// chroma sample covers.
+#[inline(always)]
+fn chroma_row<S: Simd, const N: usize>(
+    s: S,
+    conv: &Conversion,
+    pixels: &[u8],
+    cy: usize,
+    v: bool,
+    dst: &mut [u8],
+) {
+    let (m, l) = (conv.matrix, conv.layout);
+    // Each sample sums two pixels from each of two rows. Where the block
+    // has fewer pixels, because of the image edge or no subsampling along
+    // an axis, the last row or column is counted twice, which keeps the
+    // average exact and the divisor constant.
+    let top = cy << conv.shift_y;
+    let bottom = (top + (1 << conv.shift_y) - 1).min(conv.height - 1);
+    let (top, bottom) = (
+        conv.rgb_row::<N>(pixels, top),
+        conv.rgb_row::<N>(pixels, bottom),
+    );
+    let step = 1 << conv.shift_x;
+
+    // The color difference is a weighting of R, G and B; fold its scale to
+    // code values and the division by four into the weights.
+    let (difference, weights) = if v {
+        (2.0 * (1.0 - m.kr), [1.0 - m.kr, -m.kg, -m.kb])
+    } else {
+        (2.0 * (1.0 - m.kb), [-m.kr, -m.kg, 1.0 - m.kb])
+    };
+    let scale = conv.uv_scale / 255.0 / difference / 4.0;
+    let [kr, kg, kb] = weights.map(|w| s.splat(w * scale));
+    let bias = s.splat(128.0);
+
+    let chroma_width = conv.chroma_width();
+    let blocks = top.chunks(LANES * step).zip(bottom.chunks(LANES * step));
+    for (dst, (top, bottom)) in dst[..chroma_width].chunks_mut(LANES).zip(blocks) {
+        let mut sums = [[0.0f32; LANES]; 3];
+        let last = top.len() - 1;
+        for (i, left) in (0..top.len()).step_by(step).enumerate() {
+            let right = (left + step - 1).min(last);
+            for px in [top[left], top[right], bottom[left], bottom[right]] {
+                sums[0][i] += px[l.r] as f32;
+                sums[1][i] += px[l.g] as f32;
+                sums[2][i] += px[l.b] as f32;
+            }
+        }
+        let value = s.mul_add(s.load(&sums[2]), kb, bias);
+        let value = s.mul_add(s.load(&sums[1]), kg, value);
+        let value = s.mul_add(s.load(&sums[0]), kr, value);
+        store_u8_to(s, value, dst);
+    }
+}
+
+/// Converts RGB pixels into the planes of `image` if the conversion is
+/// supported.
+///
+/// # Returns
+/// Whether the conversion was performed; if not, nothing was written.
+pub(crate) fn rgb_to_yuv(image: &mut Image, rgb: &avifRGBImage) -> bool {
+    // Sharp YUV is left to libsharpyuv.
+    if rgb.chromaDownsampling == avifChromaDownsampling_AVIF_CHROMA_DOWNSAMPLING_SHARP_YUV {
+        return false;
+    }
+    let Some(conv) = Conversion::new(image, rgb) else {
+        return false;
+    };
+    if conv.width == 0 || conv.height == 0 {
+        return true;
+    }
+    let pixels = unsafe { slice::from_raw_parts(rgb.pixels, conv.rgb_len()) };
+
+    let row_bytes = image.plane_row_bytes(Channel::Y) as usize;
+    if let Some(plane) = image.plane_mut(Channel::Y) {
+        par::for_each_row(plane, row_bytes, |y, row| {
+            with_simd!(s => match conv.layout.pixel_size() {
+                4 => luma_row(s, &conv, conv.rgb_row::<4>(pixels, y), row),
+                _ => luma_row(s, &conv, conv.rgb_row::<3>(pixels, y), row),
+            })
+        });
+    }
+    for (channel, v) in [(Channel::U, false), (Channel::V, true)] {
+        let row_bytes = image.plane_row_bytes(channel) as usize;
+        if let Some(plane) = image.plane_mut(channel) {
+            par::for_each_row(plane, row_bytes, |cy, row| {
+                with_simd!(s => match conv.layout.pixel_size() {
+                    4 => chroma_row::<_, 4>(s, &conv, pixels, cy, v, row),
+                    _ => chroma_row::<_, 3>(s, &conv, pixels, cy, v, row),
+                })
+            });
+        }
+    }
+
+    let row_bytes = image.plane_row_bytes(Channel::A) as usize;
+    if let Some(plane) = image.plane_mut(Channel::A) {
+        let alpha = conv.layout.a.filter(|_| conv.rgb_alpha);
+        par::for_each_row(plane, row_bytes, |y, row| {
+            let row = &mut row[..conv.width];
+            match alpha {
+                Some(a) => {
+                    for (out, px) in row.iter_mut().zip(conv.rgb_row::<4>(pixels, y)) {
+                        *out = px[a];
+                    }
+                }
+                None => row.fill(255),
+            }
+        });
+    }
+    true
+}
+
+/// Chroma rows contributing to a luma row, with their weights.
+type Taps = [(usize, f32); 2];
+
+/// Returns the chroma rows for luma row `y`, given the vertical subsampling
+/// shift and the number of chroma rows.
+fn taps(y: usize, shift: u32, count: usize, bilinear: bool) -> Taps {
+    let center = y >> shift;
+    if shift == 0 || !bilinear {
+        return [(center, 1.0), (center, 0.0)];
+    }
+    // Chroma samples sit between the two luma samples they cover, so each
+    // luma sample is 1/4 of the way to its nearest chroma neighbor.
+    let neighbor = if y & 1 == 0 {
+        center.saturating_sub(1)
+    } else {
+        (center + 1).min(count - 1)
+    };
+    [(center, 0.75), (neighbor, 0.25)]
+}
+
+/// Per-thread buffers for upsampled chroma, padded to whole groups of
+/// lanes.
+struct Scratch {
+    /// Chroma rows blended to the luma row
+    u_line: Vec<f32>,
+    v_line: Vec<f32>,
+    /// The blended rows expanded to luma columns, when subsampled
+    u: Vec<f32>,
+    v: Vec<f32>,
+}
+
+impl Scratch {
+    fn new(conv: &Conversion) -> Self {
+        let line = conv.chroma_width().next_multiple_of(LANES);
+        let row = match conv.shift_x {
+            0 => 0,
+            _ => conv.width.next_multiple_of(LANES),
+        };
+        Self {
+            u_line: vec![0.0; line],
+            v_line: vec![0.0; line],
+            u: vec![0.0; row],
+            v: vec![0.0; row],
+        }
+    }
+
+    /// Returns the blended chroma rows at luma columns, as normalized color
+    /// differences.
+    fn expanded(&mut self, conv: &Conversion, bilinear: bool) -> (&[f32], &[f32]) {
+        if conv.shift_x == 0 {
+            return (&self.u_line, &self.v_line);
+        }
+        let chroma_width = conv.chroma_width();
+        expand(&self.u_line[..chroma_width], bilinear, &mut self.u);
+        expand(&self.v_line[..chroma_width], bilinear, &mut self.v);
+        (&self.u, &self.v)
+    }
+}
+
+/// Blends the chroma rows `rows` of `plane` into `line`, as normalized
+/// color differences.
+#[inline(always)]
+fn blend_rows<S: Simd>(
+    s: S,
+    conv: &Conversion,
+    plane: &[u8],
+    row_bytes: usize,
+    rows: Taps,
+    line: &mut [f32],
+) {
+    let chroma_width = conv.chroma_width();
+    let [(top, top_weight), (bottom, bottom_weight)] = rows;
+    let top = &plane[top * row_bytes..][..chroma_width];
+    let bottom = &plane[bottom * row_bytes..][..chroma_width];
+    let top_weight = s.splat(top_weight / conv.uv_scale);
+    let bottom_weight = s.splat(bottom_weight / conv.uv_scale);
+    let bias = s.splat(-128.0 / conv.uv_scale);
+    let (groups, _) = line.as_chunks_mut::<LANES>();
+    for (x, dst) in (0..chroma_width).step_by(LANES).zip(groups) {
+        let value = s.mul_add(load_u8_at(s, bottom, x), bottom_weight, bias);
+        let value = s.mul_add(load_u8_at(s, top, x), top_weight, value);
+        s.store(value, dst);
+    }
+}
+
+/// Expands a row of horizontally subsampled chroma to luma columns.
+fn expand(line: &[f32], bilinear: bool, dst: &mut [f32]) {
+    let last = line.len() - 1;
+    let (pairs, _) = dst.as_chunks_mut::<2>();
+    for (c, (pair, &center)) in pairs.iter_mut().zip(line).enumerate() {
+        *pair = if bilinear {
+            // Each luma column is 1/4 of the way from its chroma sample to
+            // the nearest neighbor, as in `taps`.
+            let (left, right) = (line[c.saturating_sub(1)], line[(c + 1).min(last)]);
+            [0.75 * center + 0.25 * left, 0.75 * center + 0.25 * right]
+        } else {
+            [center; 2]
+        };
+    }
+}
+
+/// Writes one row of RGB pixels from luma and upsampled chroma.
+#[inline(always)]
+fn rgb_row<S: Simd, const N: usize>(
+    s: S,
+    conv: &Conversion,
+    luma: &[u8],
+    u: &[f32],
+    v: &[f32],
+    alpha: Option<&[u8]>,
+    dst: &mut [u8],
+) {
+    let (m, l) = (conv.matrix, conv.layout);
+    // R, G and B scaled to code values, as weightings of luma and the
+    // color differences.
+    let (cr_to_r, cb_to_b) = (2.0 * (1.0 - m.kr), 2.0 * (1.0 - m.kb));
+    let y_scale = s.splat(255.0 / conv.y_scale);
+    let y_bias = s.splat(-255.0 * conv.y_offset / conv.y_scale);
+    let r_cr = s.splat(255.0 * cr_to_r);
+    let g_cr = s.splat(-255.0 * m.kr * cr_to_r / m.kg);
+    let g_cb = s.splat(-255.0 * m.kb * cb_to_b / m.kg);
+    let b_cb = s.splat(255.0 * cb_to_b);
+
+    let luma = &luma[..conv.width];
+    let (pixels, _) = dst.as_chunks_mut::<N>();
+    let pixels = &mut pixels[..conv.width];
+    let (u, _) = u.as_chunks::<LANES>();
+    let (v, _) = v.as_chunks::<LANES>();
+    let groups = pixels.chunks_mut(LANES).zip(u.iter().zip(v));
+    for (x, (pixels, (u, v))) in (0..conv.width).step_by(LANES).zip(groups) {
+        let y = s.mul_add(load_u8_at(s, luma, x), y_scale, y_bias);
+        let (cb, cr) = (s.load(u), s.load(v));
+        let mut lanes = [[0; LANES]; 3];
+        s.store_u8(s.mul_add(cr, r_cr, y), &mut lanes[0]);
+        s.store_u8(s.mul_add(cr, g_cr, s.mul_add(cb, g_cb, y)), &mut lanes[1]);
+        s.store_u8(s.mul_add(cb, b_cb, y), &mut lanes[2]);
+        for (i, px) in pixels.iter_mut().enumerate() {
+            px[l.r] = lanes[0][i];
+            px[l.g] = lanes[1][i];
+            px[l.b] = lanes[2][i];
+        }
+    }
+    if let Some(a) = l.a.filter(|_| conv.rgb_alpha) {
+        match alpha {
+            Some(alpha) => {
+                for (px, &value) in pixels.iter_mut().zip(alpha) {
+                    px[a] = value;
+                }
+            }
+            None => pixels.iter_mut().for_each(|px| px[a] = 255),
+        }
+    }
+}
+
+/// Converts the planes of `image` into RGB pixels if the conversion is
+/// supported.
+///
+/// # Returns
+/// Whether the conversion was performed; if not, nothing was written.
+pub(crate) fn yuv_to_rgb(image: &Image, rgb: &mut avifRGBImage) -> bool {
+    let Some(conv) = Conversion::new(image, rgb) else {
+        return false;
+    };
+    let (Some(luma), Some(u), Some(v)) = (
+        image.plane(Channel::Y),
+        image.plane(Channel::U),
+        image.plane(Channel::V),
+    ) else {
+        return false;
+    };
+    if conv.width == 0 || conv.height == 0 {
+        return true;
+    }
+    let bilinear = !matches!(
+        rgb.chromaUpsampling,
+        avifChromaUpsampling_AVIF_CHROMA_UPSAMPLING_FASTEST
+            | avifChromaUpsampling_AVIF_CHROMA_UPSAMPLING_NEAREST
+    );
+    let luma_row_bytes = image.plane_row_bytes(Channel::Y) as usize;
+    let chroma_row_bytes = image.plane_row_bytes(Channel::U) as usize;
+    let chroma_height = conv.chroma_height();
+    let alpha = image.plane(Channel::A);
+    let alpha_row_bytes = image.plane_row_bytes(Channel::A) as usize;
+
+    let pixels = unsafe { slice::from_raw_parts_mut(rgb.pixels, conv.rgb_len()) };
+    let init = || Scratch::new(&conv);
+    par::for_each_row_with(pixels, conv.rgb_row_bytes, init, |scratch, y, row| {
+        let rows = taps(y, conv.shift_y, chroma_height, bilinear);
+        let luma = &luma[y * luma_row_bytes..];
+        let alpha = alpha.map(|plane| &plane[y * alpha_row_bytes..]);
+        with_simd!(s => {
+            blend_rows(s, &conv, u, chroma_row_bytes, rows, &mut scratch.u_line);
+            blend_rows(s, &conv, v, chroma_row_bytes, rows, &mut scratch.v_line);
+            let (u, v) = scratch.expanded(&conv, bilinear);
+            match conv.layout.pixel_size() {
+                4 => rgb_row::<_, 4>(s, &conv, luma, u, v, alpha, row),
+                _ => rgb_row::<_, 3>(s, &conv, luma, u, v, alpha, row),
+            }
+        })
+    });
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rgb::ChromaUpsampling;
+    use crate::{BitDepth, PixelFormat, Planes};
+
+    // Odd sizes exercise the partial chroma blocks and lane groups at the
+    // right and bottom edges.
+    const WIDTH: u32 = 37;
+    const HEIGHT: u32 = 19;
+
+    const MATRICES: [u32; 6] = [
+        AVIF_MATRIX_COEFFICIENTS_UNSPECIFIED,
+        AVIF_MATRIX_COEFFICIENTS_BT709,
+        AVIF_MATRIX_COEFFICIENTS_FCC,
+        AVIF_MATRIX_COEFFICIENTS_BT601,
+        AVIF_MATRIX_COEFFICIENTS_SMPTE240,
+        AVIF_MATRIX_COEFFICIENTS_BT2020_NCL,
+    ];
+
+    /// Returns bytes alternating between smooth gradients and noise.
+    fn test_bytes(len: usize) -> Vec<u8> {
+        let mut state = 0x1234_5678u32;
+        (0..len)
+            .map(|i| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                match (i / 97) % 2 {
+                    0 => (i * 7 % 256) as u8,
+                    _ => (state >> 16) as u8,
+                }
+            })
+            .collect()
+    }
+
+    fn yuv_image(format: PixelFormat, range: YuvRange, matrix: u32) -> Image {
+        let mut image = Image::new(WIDTH, HEIGHT, BitDepth::Eight, format).unwrap();
+        image.set_yuv_range(range);
+        image.set_matrix_coefficients(matrix as avifMatrixCoefficients);
+        image.allocate_planes(Planes::YUV).unwrap();
+        image
+    }
+
+    /// Returns an RGB image description of `pixels` in `format`, with the
+    /// defaults libavif derives from `image`.
+    fn rgb_image(image: &Image, format: RgbFormat, pixels: &mut [u8]) -> avifRGBImage {
+        let mut rgb: avifRGBImage = unsafe { std::mem::zeroed() };
+        unsafe { avifRGBImageSetDefaults(&mut rgb, image.as_raw()) };
+        rgb.format = format.into();
+        rgb.rowBytes = (pixels.len() / HEIGHT as usize) as u32;
+        rgb.pixels = pixels.as_mut_ptr();
+        rgb
+    }
+
+    /// Calls `f` with every combination of subsampling, range and matrix.
+    fn for_each_case(mut f: impl FnMut(PixelFormat, YuvRange, u32)) {
+        for format in [
+            PixelFormat::Yuv444,
+            PixelFormat::Yuv422,
+            PixelFormat::Yuv420,
+        ] {
+            for range in [YuvRange::Full, YuvRange::Limited] {
+                for matrix in MATRICES {
+                    f(format, range, matrix);
+                }
+            }
+        }
+    }
+
+    /// Returns the largest difference between the visible samples of a
+    /// plane of two images.
+    fn plane_difference(a: &Image, b: &Image, channel: Channel) -> u8 {
+        let width = a.plane_width(channel) as usize;
+        let row_bytes = a.plane_row_bytes(channel) as usize;
+        let (a, b) = (a.plane(channel).unwrap(), b.plane(channel).unwrap());
+        a.chunks(row_bytes)
+            .zip(b.chunks(row_bytes))
+            .flat_map(|(a, b)| a[..width].iter().zip(&b[..width]))
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn rgb_to_yuv_matches_libavif() {
+        for_each_case(|format, range, matrix| {
+            for rgb_format in [RgbFormat::Rgb, RgbFormat::Bgra, RgbFormat::Argb] {
+                let size = if rgb_format == RgbFormat::Rgb { 3 } else { 4 };
+                let mut pixels = test_bytes((WIDTH * HEIGHT) as usize * size);
+                let mut expected = yuv_image(format, range, matrix);
+                let mut actual = yuv_image(format, range, matrix);
+                let mut rgb = rgb_image(&expected, rgb_format, &mut pixels);
+
+                rgb.avoidLibYUV = 1;
+                let result = unsafe { avifImageRGBToYUV(expected.as_raw_mut(), &rgb) };
+                assert_eq!(result, avifResult_AVIF_RESULT_OK);
+                rgb.avoidLibYUV = 0;
+                assert!(rgb_to_yuv(&mut actual, &rgb));
+
+                for channel in [Channel::Y, Channel::U, Channel::V] {
+                    let difference = plane_difference(&expected, &actual, channel);
+                    assert!(
+                        difference <= 1,
+                        "{channel:?} differs by {difference} for {format:?} {range:?} \
+                         matrix {matrix} {rgb_format:?}"
+                    );
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn yuv_to_rgb_matches_libavif() {
+        for_each_case(|format, range, matrix| {
+            let mut image = yuv_image(format, range, matrix);
+            let mut source = test_bytes((WIDTH * HEIGHT * 3) as usize);
+            let rgb = rgb_image(&image, RgbFormat::Rgb, &mut source);
+            assert!(rgb_to_yuv(&mut image, &rgb));
+
+            for upsampling in [ChromaUpsampling::Bilinear, ChromaUpsampling::Nearest] {
+                for rgb_format in [RgbFormat::Rgb, RgbFormat::Bgra, RgbFormat::Argb] {
+                    let size = if rgb_format == RgbFormat::Rgb { 3 } else { 4 };
+                    let mut expected = vec![0; (WIDTH * HEIGHT) as usize * size];
+                    let mut actual = expected.clone();
+
+                    let mut rgb = rgb_image(&image, rgb_format, &mut expected);
+                    rgb.chromaUpsampling = upsampling.into();
+                    rgb.avoidLibYUV = 1;
+                    let result = unsafe { avifImageYUVToRGB(image.as_raw(), &mut rgb) };
+                    assert_eq!(result, avifResult_AVIF_RESULT_OK);
+
+                    let mut rgb = rgb_image(&image, rgb_format, &mut actual);
+                    rgb.chromaUpsampling = upsampling.into();
+                    assert!(yuv_to_rgb(&image, &mut rgb));
+
+                    let difference = expected
+                        .iter()
+                        .zip(&actual)
+                        .map(|(a, b)| a.abs_diff(*b))
+                        .max()
+                        .unwrap();
+                    assert!(
+                        difference <= 1,
+                        "RGB differs by {difference} for {format:?} {range:?} matrix {matrix} \
+                         {rgb_format:?} {upsampling:?}"
+                    );
+                }
+            }
+        });
+    }
+}